@@ -135,7 +135,7 @@ impl Device {
         (irq_tx, res_tx))
     }
 
-    pub fn init_devices(&mut self, chan: (Sender<u8>, Sender<IOResult>), mem: Arc<RwLock<memory::Memory>>, imgbuf: Arc<Mutex<Vec<[u8; 3]>>>) {
+    pub fn init_devices(&mut self, chan: (Sender<u8>, Sender<IOResult>), (mem, bus): (Arc<RwLock<memory::Memory>>, Arc<Mutex<()>>), imgbuf: Arc<Mutex<Vec<[u8; 3]>>>) {
         let (irq_tx, res_tx) = chan;
 
         self.memio_range.push(0x1000..0x1000+0x100);
@@ -147,7 +147,7 @@ impl Device {
             let mut memory_io_map: MemoryIOMap = Vec::new();
 
             let mut vga = vga::VGA::new(imgbuf);
-            let (mut tst_dma_ctl, mut tst_dma_adr) = testdma::TestDMA::new(IReq::new(&irq_tx, 1), mem.clone());
+            let (mut tst_dma_ctl, mut tst_dma_adr) = testdma::TestDMA::new(IReq::new(&irq_tx, 1), mem.clone(), bus.clone());
             let mut tst_timer = testtimer::TestTimer::new(IReq::new(&irq_tx, 2));
 
             port_io_map.push((0x3b4..0x3e0, &mut vga.0));
@@ -265,12 +265,27 @@ impl Device {
         self.io_req_que.enqueue(req);
     }
 
-    pub fn check_memio(&self, addr: u64, length: u64) -> bool {
+    pub fn check_memio(&self, addr: u64, length: u64) -> (bool, u64) {
+        let mut next = addr + length;
         for r in self.memio_range.iter() {
-            if r.start <= addr && addr+length-1 < r.end {
-                return true;
+            if r.start <= addr && addr < r.end {
+                return (true, std::cmp::min(length, r.end - addr));
+            } else if addr < r.start && r.start < next {
+                next = r.start;
             }
         }
-        false
+        (false, next - addr)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+#[test]
+fn memio_range_test() {
+    let (mut dev, _) = Device::new();
+    dev.memio_range.push(0x1000..0x1100);
+
+    assert_eq!(dev.check_memio(0x10, 4), (false, 4));
+    assert_eq!(dev.check_memio(0x1000, 8), (true, 8));
+    assert_eq!(dev.check_memio(0xffc, 8), (false, 4));
+    assert_eq!(dev.check_memio(0x10fe, 4), (true, 2));
+}
@@ -1,20 +1,22 @@
 use core::convert::TryInto;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use crate::hardware::memory;
 
 pub struct TestDMA {
     irq: super::IReq,
     mem: Arc<RwLock<memory::Memory>>,
+    bus: Arc<Mutex<()>>,
     raw: [u8; 0x10],
 }
 
 impl TestDMA {
-    pub fn new(irq: super::IReq, mem: Arc<RwLock<memory::Memory>>) -> (DMACtrl, DMAAddr) {
+    pub fn new(irq: super::IReq, mem: Arc<RwLock<memory::Memory>>, bus: Arc<Mutex<()>>) -> (DMACtrl, DMAAddr) {
         let dma = Rc::new(RefCell::new(Self {
             irq,
             mem,
+            bus,
             raw: [0; 0x10],
         }));
 
@@ -43,6 +45,7 @@ impl super::PortIO for DMACtrl {
         let mut tmp = vec![0; size];
 
         let dma = self.0.borrow_mut();
+        let _lock = dma.bus.lock().unwrap();
         dma.mem.read().unwrap().read_data(tmp.as_mut_ptr() as *mut _, dma.get_src() as usize, size).unwrap();
         dma.mem.write().unwrap().write_data(dma.get_dst() as usize, tmp.as_ptr() as *const _, size).unwrap();
         dma.irq.send_irq();
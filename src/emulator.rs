@@ -8,6 +8,7 @@ use interrupt::IntrEvent;
 use super::hardware;
 use super::device;
 use crate::hardware::processor::control::*;
+pub use crate::hardware::processor::tsc::TscMode;
 
 #[derive(Debug, Error)]
 pub enum EmuException {
@@ -17,6 +18,8 @@ pub enum EmuException {
     Interrupt(u8),
     #[error("Halt")]
     Halt,
+    #[error("Triple Fault {0:?}")]
+    TripleFault(CPUException),
     #[error("Undefined Opecode")]
     UndefinedOpcode,
     #[error("Not Implemented Opecode")]
@@ -30,7 +33,7 @@ pub enum EmuException {
 #[derive(Debug)]
 pub enum CPUException {
     DE, DB,     BP, OF, BR, UD, NM,
-    DF,     TS, NP, SS(Option<u16>), GP(Option<u16>), PF(u64),
+    DF,     TS, NP, SS(Option<u16>), GP(Option<u16>), PF(u64, u32),
     MF, AC, MC, XF, VE,
     SX
 }
@@ -41,7 +44,7 @@ impl From<&CPUException> for u8 {
             CPUException::DE => 0,     CPUException::DB => 1,                                CPUException::BP => 3,
             CPUException::OF => 4,     CPUException::BR => 5,     CPUException::UD => 6,     CPUException::NM => 7,
             CPUException::DF => 8,                                CPUException::TS => 10,    CPUException::NP => 11,
-            CPUException::SS(_) => 12, CPUException::GP(_) => 13, CPUException::PF(_) => 14,
+            CPUException::SS(_) => 12, CPUException::GP(_) => 13, CPUException::PF(..) => 14,
             CPUException::MF => 16,    CPUException::AC => 17,    CPUException::MC => 18,    CPUException::XF => 19,
             CPUException::VE => 20,
             CPUException::SX => 30,
@@ -49,20 +52,58 @@ impl From<&CPUException> for u8 {
     }
 }
 
+impl CPUException {
+    pub fn error_code(&self) -> Option<u32> {
+        match self {
+            CPUException::DF | CPUException::TS | CPUException::NP | CPUException::AC | CPUException::SX => Some(0),
+            CPUException::SS(sel) | CPUException::GP(sel) => Some(sel.unwrap_or(0) as u32),
+            CPUException::PF(_, code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    pub fn is_fault(&self) -> bool {
+        match self {
+            CPUException::DB | CPUException::BP | CPUException::OF => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TripleFaultAction { Reset, Stop }
+
+impl std::str::FromStr for TripleFaultAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reset" => Ok(TripleFaultAction::Reset),
+            "stop"  => Ok(TripleFaultAction::Stop),
+            _ => Err(format!("unknown triple fault action '{}'", s)),
+        }
+    }
+}
+
 pub struct Emulator {
     pub ac: access::Access,
     inst: instruction::Instruction,
     intrpt: interrupt::Interrupt,
     halt: bool,
     pub breakpoints: Vec<u32>,
+    pub triple_fault: TripleFaultAction,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchKind { Write, Read, ReadWrite }
+
 #[derive(Debug)]
 pub enum Event {
     Halted,
     Break,
     WatchWrite(u32),
     WatchRead(u32),
+    Shutdown(EmuException),
 }
 
 impl Emulator {
@@ -73,32 +114,38 @@ impl Emulator {
             intrpt: Default::default(),
             halt: false,
             breakpoints: Vec::new(),
+            triple_fault: TripleFaultAction::Reset,
         }
     }
 
-    pub fn run(&mut self) -> () {
+    pub fn run(&mut self) -> Event {
         loop {
-            self.step(false);
+            if let Some(ev @ Event::Shutdown(_)) = self.step(false) {
+                return ev;
+            }
         }
     }
 
+    pub fn reset(&mut self) -> () {
+        self.ac.reset();
+        self.intrpt.clear();
+        self.halt = false;
+    }
+
     pub fn wake(&mut self) -> () {
         self.halt = false;
     }
 
     pub fn step(&mut self, debugged: bool) -> Option<Event> {
+        let mut watched = None;
         if !self.halt {
             debug!("IP : 0x{:016x}", self.ac.core.ip.get_rip());
             match self.inst.fetch_exec(&mut self.ac) {
                 Err(EmuException::Interrupt(i))    => self.intrpt.enqueue_top(IntrEvent::Software(i)),
                 Err(EmuException::CPUException(e)) => {
-                    match e {
-                        CPUException::BP => self.ac.dump(),
-                        CPUException::PF(laddr) => self.ac.core.cregs.2.from_u64(laddr),
-                        _ => panic!("CPUException : {:?}", e),
-                    }
+                    if let CPUException::BP = e { self.ac.dump(); }
                     debug!("CPUException : {:?}", e);
-                    //self.intrpt.enqueue_top(IntrEvent::Hardware(u8::from(&e)));
+                    self.intrpt.enqueue_top(IntrEvent::Exception(e));
                 },
                 Err(EmuException::Halt)            => self.halt = true,
                 Err(err) => {
@@ -107,6 +154,7 @@ impl Emulator {
                 },
                 _ => {},
             }
+            watched = self.ac.take_watched();
         }
 
         if let Some(ev) = self.ac.check_irq(self.halt && !debugged) {
@@ -116,7 +164,16 @@ impl Emulator {
         }
 
         match self.intrpt.handle(&mut self.ac) {
-            Err(EmuException::CPUException(e)) => self.intrpt.enqueue_top(IntrEvent::Hardware(u8::from(&e))),
+            Err(err @ EmuException::TripleFault(_)) => {
+                debug!("{}", err);
+                match self.triple_fault {
+                    TripleFaultAction::Reset => self.reset(),
+                    TripleFaultAction::Stop => {
+                        self.halt = true;
+                        return Some(Event::Shutdown(err));
+                    },
+                }
+            },
             Err(err) => {
                 self.ac.dump();
                 panic!("{}", err)
@@ -126,6 +183,8 @@ impl Emulator {
 
         if debugged && self.breakpoints.contains(&(self.ac.core.ip.get_eip())) {
             Some(Event::Break)
+        } else if let (true, Some((addr, write))) = (debugged, watched) {
+            Some(if write { Event::WatchWrite(addr as u32) } else { Event::WatchRead(addr as u32) })
         } else if self.halt {
             Some(Event::Halted)
         } else {
@@ -151,6 +210,15 @@ impl Emulator {
         Ok(())
     }
 
+    pub fn set_tsc_mode(&mut self, mode: TscMode) -> () {
+        self.ac.core.tsc.mode = mode;
+    }
+
+    pub fn load_cpuid(&mut self, path: String) -> Result<(), Box<dyn error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        self.ac.cpuid.load_override(&text)
+    }
+
     pub fn dump(&self) -> () {
         self.ac.dump();
     }
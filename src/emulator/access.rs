@@ -4,14 +4,14 @@ mod msr;
 pub(super) mod descriptor;
 mod port;
 
-use std::cell::RefCell;
-use std::sync::{Arc, RwLock};
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, Mutex, RwLock};
 use crate::hardware;
 use crate::device;
-use crate::emulator::{EmuException, CPUException};
+use crate::emulator::{EmuException, CPUException, WatchKind};
 
 #[derive(Debug, PartialEq)]
-pub(super) enum CpuMode { Real, Protected, Long }
+pub(super) enum CpuMode { Real, Protected, V8086, Long }
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum AcsSize { BIT16, BIT32, BIT64 }
@@ -33,12 +33,18 @@ enum PagingMode { Legacy, LegacyPAE, Ia32e4Lv, Ia32e5Lv }
 pub struct Access {
     pub core: hardware::processor::Processor,
     pub mem: Arc<RwLock<hardware::memory::Memory>>,
+    pub bus: Arc<Mutex<()>>,
+    pub cpuid: hardware::processor::cpuid::CpuidModel,
+    pub watchpoints: Vec<(u64, WatchKind)>,
     dev: device::Device,
     pub(super) mode: CpuMode,
     pub(super) oasz: OpAdSize,
     stsz: AcsSize,
     pgmd: Option<PagingMode>,
     tlb: RefCell<memory::TLB>,
+    watch: Cell<u8>,
+    watched: Cell<Option<(u64, bool)>>,
+    supervisor: Cell<bool>,
     a20gate: bool,
 }
 
@@ -47,22 +53,44 @@ impl Access {
         Self {
             core: hw.core,
             mem: hw.mem,
+            bus: hw.bus,
+            cpuid: Default::default(),
+            watchpoints: Vec::new(),
             dev,
             mode: CpuMode::Real,
             oasz: Default::default(),
             stsz: Default::default(),
             pgmd: None,
             tlb: Default::default(),
+            watch: Default::default(),
+            watched: Default::default(),
+            supervisor: Default::default(),
             a20gate: false,
         }
     }
 
+    pub(super) fn reset(&mut self) -> () {
+        let tsc_mode = self.core.tsc.mode;
+        self.core = hardware::processor::Processor::new();
+        self.core.tsc.mode = tsc_mode;
+        self.mode = CpuMode::Real;
+        self.oasz = Default::default();
+        self.stsz = Default::default();
+        self.pgmd = None;
+        self.tlb.borrow_mut().flush();
+        self.watch.set(0);
+        self.watched.set(None);
+        self.supervisor.set(false);
+        self.a20gate = false;
+    }
+
     pub(super) fn update_cpumode(&mut self) -> Result<(), EmuException> {
         let efer = &self.core.msr.efer;
         let cr0 = &self.core.cregs.0;
 
         self.mode = match (efer.LME, cr0.PE) {
             (0, 0) => CpuMode::Real,
+            (0, 1) if self.core.rflags.is_v8086() => CpuMode::V8086,
             (0, 1) => CpuMode::Protected,
             (1, 1) => CpuMode::Long,
             _ => return Err(EmuException::CPUException(CPUException::GP(None))),
@@ -87,8 +115,10 @@ impl Access {
 
     pub(super) fn update_stacksize(&mut self) -> Result<(), EmuException> {
         let ss = &self.core.sgregs.get(register::SgReg::SS);
+        let long64 = self.mode == CpuMode::Long && self.oasz.ad == AcsSize::BIT64;
 
         self.stsz = match (ss.cache.L, ss.cache.DB) {
+            _ if long64 => AcsSize::BIT64,
             (0, 0) => AcsSize::BIT16,
             (0, 1) => AcsSize::BIT32,
             (1, 0) => AcsSize::BIT64,
@@ -108,8 +138,8 @@ impl Access {
 
         self.pgmd = match (&self.mode, cr0.PG, cr4.PAE, cr4.LA57) {
             (CpuMode::Real, _, _, _) | (_, 0, _, _) => None,
-            (CpuMode::Protected, 1, 0, _)           => Some(PagingMode::Legacy),
-            (CpuMode::Protected, 1, 1, _)           => Some(PagingMode::LegacyPAE),
+            (CpuMode::Protected, 1, 0, _) | (CpuMode::V8086, 1, 0, _) => Some(PagingMode::Legacy),
+            (CpuMode::Protected, 1, 1, _) | (CpuMode::V8086, 1, 1, _) => Some(PagingMode::LegacyPAE),
             (CpuMode::Long, 1, 1, 0)                => Some(PagingMode::Ia32e4Lv),
             (CpuMode::Long, 1, 1, 1)                => Some(PagingMode::Ia32e5Lv),
             _ => return Err(EmuException::CPUException(CPUException::GP(None))),
@@ -87,6 +87,12 @@ pub struct TSSDesc {
     #[packed_field(bits="56:95")]  pub base_h:  u64,
 }
 
+impl TSSDesc {
+    pub fn attr(&self) -> u16 {
+        ((self.P as u16) << 7) + ((self.DPL as u16) << 5) + ((self.D as u16) << 3) + ((self.B as u16) << 1) + 1
+    }
+}
+
 impl From<TSSDesc> for DescTbl {
     fn from(desc: TSSDesc) -> Self {
         Self {
@@ -123,9 +129,17 @@ pub struct CallGateDesc {
     #[packed_field(bits="16:31")]  pub selector:u16,
     #[packed_field(bits="32:39")]  pub pc:      u8,
     #[packed_field(bits="40:42")]  Type:    u8,
+    #[packed_field(bits="43")]     pub D:       u8,
     #[packed_field(bits="45:46")]  pub DPL:     u8,
     #[packed_field(bits="47")]     pub P:       u8,
     #[packed_field(bits="48:63")]  pub offset_h:u16,
+    #[packed_field(bits="64:95")]  pub offset_u:u32,
+}
+
+impl CallGateDesc {
+    pub fn offset(&self) -> u64 {
+        ((self.offset_u as u64) << 32) + ((self.offset_h as u64) << 16) + self.offset_l as u64
+    }
 }
 
 #[derive(Default, PackedStruct, Debug)]
@@ -142,11 +156,19 @@ pub struct TaskGateDesc {
 pub struct IntrTrapGateDesc {
     #[packed_field(bits="0:15")]   pub offset_l:u16,
     #[packed_field(bits="16:31")]  pub selector:u16,
+    #[packed_field(bits="32:34")]  pub IST:     u8,
     #[packed_field(bits="40:42")]  Type:    u8,
     #[packed_field(bits="43")]     pub D:       u8,
     #[packed_field(bits="45:46")]  pub DPL:     u8,
     #[packed_field(bits="47")]     pub P:       u8,
     #[packed_field(bits="48:63")]  pub offset_h:u16,
+    #[packed_field(bits="64:95")]  pub offset_u:u32,
+}
+
+impl IntrTrapGateDesc {
+    pub fn offset(&self) -> u64 {
+        ((self.offset_u as u64) << 32) + ((self.offset_h as u64) << 16) + self.offset_l as u64
+    }
 }
 
 #[derive(Default)]
@@ -223,8 +245,21 @@ struct TSS32 {
     io_base: u16,
 }
 
+#[derive(Default, Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct TSS64 {
+    _r0: u32,
+    rsp: [u64; 3],
+    _r1: u64,
+    ist: [u64; 7],
+    _r2: u64,
+    _r3: u16,
+    io_base: u16,
+}
+
 const TSS16_SIZE: usize = std::mem::size_of::<TSS16>();
 const TSS32_SIZE: usize = std::mem::size_of::<TSS32>();
+const TSS64_SIZE: usize = std::mem::size_of::<TSS64>();
 
 fn classify_descriptor(raw: &[u8; 16]) -> Option<DescType> {
     let desc = Desc::unpack(&raw).unwrap_or(Default::default());
@@ -253,6 +288,7 @@ fn classify_descriptor(raw: &[u8; 16]) -> Option<DescType> {
 
 impl super::Access {
     pub fn get_cpl(&self) -> Result<u8, EmuException> {
+        if self.mode == access::CpuMode::V8086 { return Ok(3); }
         Ok(self.core.sgregs.get(SgReg::CS).selector.RPL)
     }
 
@@ -265,7 +301,22 @@ impl super::Access {
 
         let (dt_base, dt_limit) = if selector.TI == 1 { &core.dtregs.ldtr.cache } else { &core.dtregs.gdtr }.get();
         if dt_index > dt_limit { return Err(EmuException::CPUException(CPUException::GP(Some(sel)))) }
-        self.obtain_descriptor(dt_base + dt_index as u64)
+        self.obtain_descriptor(dt_base + dt_index as u64, self.desc_size())
+    }
+
+    pub fn obtain_gl_desc_raw(&self, sel: u16) -> Result<Option<u64>, EmuException> {
+        let core = &self.core;
+
+        let selector = SgDescSelector::new(sel);
+        let dt_index = (selector.IDX as u32) << 3;
+        if dt_index == 0 { return Ok(None); }
+
+        let (dt_base, dt_limit) = if selector.TI == 1 { &core.dtregs.ldtr.cache } else { &core.dtregs.gdtr }.get();
+        if dt_index + 7 > dt_limit { return Ok(None); }
+
+        let mut raw: [u8;8] = [0;8];
+        self.read_l_sys(raw.as_mut_ptr() as *mut _, dt_base + dt_index as u64, 8)?;
+        Ok(Some(u64::from_le_bytes(raw)))
     }
 
     pub fn obtain_g_desc(&self, sel: u16) -> Result<Option<DescType>, EmuException> {
@@ -275,20 +326,24 @@ impl super::Access {
 
         let (dt_base, dt_limit) = self.core.dtregs.gdtr.get();
         if dt_index > dt_limit { return Err(EmuException::CPUException(CPUException::GP(Some(sel)))) }
-        self.obtain_descriptor(dt_base + dt_index as u64)
+        self.obtain_descriptor(dt_base + dt_index as u64, self.desc_size())
     }
 
     pub fn obtain_i_desc(&self, idx: u8) -> Result<Option<DescType>, EmuException> {
-        let dt_index = (idx as u32) << 3;
+        let desc_size = if self.mode == access::CpuMode::Long { 16 } else { 8 };
+        let dt_index = idx as u32 * desc_size as u32;
         let (dt_base, dt_limit) = self.core.dtregs.idtr.get();
-        if dt_index > dt_limit { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        self.obtain_descriptor(dt_base + dt_index as u64)
+        if dt_index + desc_size as u32 - 1 > dt_limit { return Err(EmuException::CPUException(CPUException::GP(None))) }
+        self.obtain_descriptor(dt_base + dt_index as u64, desc_size)
     }
 
-    fn obtain_descriptor(&self, desc_addr: u64) -> Result<Option<DescType>, EmuException> {
+    fn desc_size(&self) -> usize {
+        if self.mode == access::CpuMode::Long { 16 } else { 8 }
+    }
+
+    fn obtain_descriptor(&self, desc_addr: u64, desc_size: usize) -> Result<Option<DescType>, EmuException> {
         let mut raw: [u8;16] = [0;16];
-        let desc_size = if let (access::CpuMode::Long, access::AcsSize::BIT64) = (&self.mode, &self.oasz.ad) { 16 } else { 8 };
-        self.read_l(raw.as_mut_ptr() as *mut _, desc_addr, desc_size)?;
+        self.read_l_sys(raw.as_mut_ptr() as *mut _, desc_addr, desc_size)?;
         raw.reverse();
 
         Ok(classify_descriptor(&raw))
@@ -304,7 +359,7 @@ impl super::Access {
     }
 
     fn install_descriptor(&mut self, desc_addr: u64, desc: DescType) -> Result<(), EmuException> {
-        let desc_size = if let (access::CpuMode::Long, access::AcsSize::BIT64) = (&self.mode, &self.oasz.ad) { 16 } else { 8 };
+        let desc_size = self.desc_size();
         let (mut raw, desc_size) = match desc {
             DescType::System(sysdsc) => {
                 match sysdsc {
@@ -318,14 +373,14 @@ impl super::Access {
             DescType::Segment(SegDescType::Code(d)) | DescType::Segment(SegDescType::Data(d)) => (SegDesc::pack(&d).unwrap(), 8),
         };
         raw.reverse();
-        self.write_l(desc_addr, raw.as_ptr() as *const _, desc_size)?;
+        self.write_l_sys(desc_addr, raw.as_ptr() as *const _, desc_size)?;
 
         Ok(())
     }
 
     pub fn load_segment(&mut self, reg: SgReg, sel: u16) -> Result<(), EmuException> {
         let cache = match self.mode {
-            access::CpuMode::Real => {
+            access::CpuMode::Real | access::CpuMode::V8086 => {
                 let mut cache: SgDescCache = Default::default();
                 cache.base = (sel as u64) << 4;
                 cache.limit = 0xffff;
@@ -417,78 +472,178 @@ impl super::Access {
 
 
     pub fn save_regs(&mut self, size: access::AcsSize, new_pl: Option<u8>) -> Result<(), EmuException> {
+        if let access::AcsSize::BIT64 = size {
+            return self.save_regs64(new_pl, 0);
+        }
+
         let cs_sel = self.get_sgreg(SgReg::CS)?.0;
+        let rflags = self.get_rflags()?;
 
+        if self.mode != access::CpuMode::V8086 {
+            return self.push_intr_frame(size, new_pl, cs_sel, rflags, false);
+        }
+
+        let (ss, rsp) = (self.get_sgreg(SgReg::SS)?, self.get_gpreg(GpReg64::RSP)?);
+        self.core.rflags.set_v8086(false);
+        self.update_cpumode()?;
+        self.push_intr_frame(size, new_pl, cs_sel, rflags, true).or_else(|e| {
+            self.core.rflags.set_v8086(true);
+            self.update_cpumode()?;
+            self.set_sgreg(SgReg::SS, ss.0, ss.1)?;
+            self.set_gpreg(GpReg64::RSP, rsp)?;
+            self.update_stacksize()?;
+            Err(e)
+        })
+    }
+
+    fn push_intr_frame(&mut self, size: access::AcsSize, new_pl: Option<u8>, cs_sel: u16, rflags: u64, v86: bool) -> Result<(), EmuException> {
         if let Some(pl) = new_pl {
-            let tssd = self.core.dtregs.tr.cache;
             let old_rsp = self.get_gpreg(GpReg64::RSP)?;
             let old_ss = self.get_sgreg(SgReg::SS)?.0;
 
             match size {
                 access::AcsSize::BIT16 => {
-                    if (tssd.limit as usize) < TSS16_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
-
-                    let mut tss: TSS16 = Default::default();
-                    self.read_l(&mut tss as *mut TSS16 as *mut _, tssd.base, TSS16_SIZE)?;
-
-                    let (ss, sp) = match pl {
-                        0 => { (tss.ss0, tss.sp0) },
-                        1 => { (tss.ss1, tss.sp1) },
-                        2 => { (tss.ss2, tss.sp2) },
-                        _ => { panic!("{:?}", EmuException::UnexpectedError); }
-                    };
-                    self.load_segment(SgReg::SS, ss)?;
-                    self.set_gpreg(GpReg16::SP, sp)?;
+                    self.load_tss_stack(size, pl)?;
 
+                    if v86 {
+                        for r in [SgReg::GS, SgReg::FS, SgReg::DS, SgReg::ES].iter() {
+                            self.push_u16(self.get_sgreg(*r)?.0)?;
+                        }
+                    }
                     self.push_u16(old_ss)?;
                     self.push_u16(old_rsp as u16)?;
                 },
                 access::AcsSize::BIT32 => {
-                    if (tssd.limit as usize) < TSS32_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
-
-                    let mut tss: TSS32 = Default::default();
-                    self.read_l(&mut tss as *mut TSS32 as *mut _, tssd.base, TSS32_SIZE)?;
-
-                    let (ss, esp) = match pl {
-                        0 => { (tss.ss0, tss.esp0) },
-                        1 => { (tss.ss1, tss.esp1) },
-                        2 => { (tss.ss2, tss.esp2) },
-                        _ => { panic!("{:?}", EmuException::UnexpectedError); }
-                    };
-                    self.load_segment(SgReg::SS, ss)?;
-                    self.set_gpreg(GpReg32::ESP, esp)?;
+                    self.load_tss_stack(size, pl)?;
 
+                    if v86 {
+                        for r in [SgReg::GS, SgReg::FS, SgReg::DS, SgReg::ES].iter() {
+                            self.push_u32(self.get_sgreg(*r)?.0 as u32)?;
+                        }
+                    }
                     self.push_u32(old_ss as u32)?;
                     self.push_u32(old_rsp as u32)?;
                 },
-                access::AcsSize::BIT64 => {
-                    self.push_u64(old_rsp)?;
-                    return Err(EmuException::NotImplementedFunction);
-                },
+                access::AcsSize::BIT64 => return Err(EmuException::CPUException(CPUException::GP(None))),
             }
         }
 
         match (&self.mode, size) {
             (access::CpuMode::Real, access::AcsSize::BIT16) | (access::CpuMode::Protected, access::AcsSize::BIT16) => {
-                self.push_u16(self.get_rflags()? as u16)?;
+                self.push_u16(rflags as u16)?;
                 self.push_u16(cs_sel)?;
                 self.push_u16(self.get_ip()? as u16)?;
             },
             (access::CpuMode::Protected, access::AcsSize::BIT32) => {
-                self.push_u32(self.get_rflags()? as u32)?;
+                self.push_u32(rflags as u32)?;
                 self.push_u32(cs_sel as u32)?;
                 self.push_u32(self.get_ip()? as u32)?;
             },
-            (access::CpuMode::Long, access::AcsSize::BIT64) => {
-                self.push_u64(self.get_rflags()?)?;
-                self.push_u64(cs_sel as u64)?;
-                self.push_u64(self.get_ip()?)?;
-            },
             _ => { return Err(EmuException::CPUException(CPUException::GP(None))); },
         }
+
+        if v86 {
+            for r in [SgReg::ES, SgReg::DS, SgReg::FS, SgReg::GS].iter() {
+                self.set_sgreg(*r, 0, Default::default())?;
+            }
+        }
         Ok(())
     }
 
+    pub fn load_tss_stack(&mut self, size: access::AcsSize, pl: u8) -> Result<(), EmuException> {
+        let tssd = self.core.dtregs.tr.cache;
+        let tss32 = self.core.dtregs.tr.attr & 8 != 0;
+
+        let (ss, sp) = match size {
+            access::AcsSize::BIT16 | access::AcsSize::BIT32 if !tss32 => {
+                if (tssd.limit as usize) < TSS16_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
+
+                let mut tss: TSS16 = Default::default();
+                self.read_l_sys(&mut tss as *mut TSS16 as *mut _, tssd.base, TSS16_SIZE)?;
+                match pl {
+                    0 => (tss.ss0, tss.sp0 as u64),
+                    1 => (tss.ss1, tss.sp1 as u64),
+                    2 => (tss.ss2, tss.sp2 as u64),
+                    _ => { panic!("{:?}", EmuException::UnexpectedError); }
+                }
+            },
+            access::AcsSize::BIT16 | access::AcsSize::BIT32 => {
+                if (tssd.limit as usize) < TSS32_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
+
+                let mut tss: TSS32 = Default::default();
+                self.read_l_sys(&mut tss as *mut TSS32 as *mut _, tssd.base, TSS32_SIZE)?;
+                match pl {
+                    0 => (tss.ss0, tss.esp0 as u64),
+                    1 => (tss.ss1, tss.esp1 as u64),
+                    2 => (tss.ss2, tss.esp2 as u64),
+                    _ => { panic!("{:?}", EmuException::UnexpectedError); }
+                }
+            },
+            access::AcsSize::BIT64 => {
+                if (tssd.limit as usize) < TSS64_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
+
+                let mut tss: TSS64 = Default::default();
+                self.read_l_sys(&mut tss as *mut TSS64 as *mut _, tssd.base, TSS64_SIZE)?;
+                let rsp = tss.rsp;
+                self.set_sgreg(SgReg::SS, pl as u16, Default::default())?;
+                self.set_gpreg(GpReg64::RSP, rsp[pl as usize])?;
+                self.stsz = access::AcsSize::BIT64;
+                return Ok(());
+            },
+        };
+
+        let cache = match self.obtain_gl_desc(ss)? {
+            Some(DescType::Segment(SegDescType::Data(ddesc))) => {
+                if (ss & 3) as u8 != pl || ddesc.DPL != pl || !DataDescFlag::from(&ddesc).contains(DataDescFlag::W) {
+                    return Err(EmuException::CPUException(CPUException::TS));
+                } else if ddesc.P == 0 {
+                    return Err(EmuException::CPUException(CPUException::SS(Some(ss))));
+                }
+                SgDescCache::from(ddesc)
+            },
+            _ => { return Err(EmuException::CPUException(CPUException::TS)); },
+        };
+        self.set_sgreg(SgReg::SS, ss, cache)?;
+        self.set_gpreg(GpReg32::ESP, sp as u32)?;
+        self.update_stacksize()
+    }
+
+    pub fn save_regs64(&mut self, new_pl: Option<u8>, ist: u8) -> Result<(), EmuException> {
+        let cs_sel = self.get_sgreg(SgReg::CS)?.0;
+        let rflags = self.get_rflags()?;
+        let old_ss = self.get_sgreg(SgReg::SS)?.0;
+        let old_rsp = self.get_gpreg(GpReg64::RSP)?;
+
+        if new_pl.is_some() || ist > 0 {
+            let tssd = self.core.dtregs.tr.cache;
+            if (tssd.limit as usize) < TSS64_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
+
+            let mut tss: TSS64 = Default::default();
+            self.read_l_sys(&mut tss as *mut TSS64 as *mut _, tssd.base, TSS64_SIZE)?;
+
+            let (rsp, ist_stack) = (tss.rsp, tss.ist);
+            let rsp = match (ist, new_pl) {
+                (1..=7, _) => ist_stack[ist as usize - 1],
+                (_, Some(pl)) if pl < 3 => rsp[pl as usize],
+                _ => return Err(EmuException::CPUException(CPUException::TS)),
+            };
+            if let Some(pl) = new_pl {
+                self.set_sgreg(SgReg::SS, pl as u16, Default::default())?;
+            }
+            self.set_gpreg(GpReg64::RSP, rsp)?;
+        }
+
+        let rsp = self.get_gpreg(GpReg64::RSP)?;
+        self.set_gpreg(GpReg64::RSP, rsp & !0xf)?;
+        self.stsz = access::AcsSize::BIT64;
+
+        self.push_u64(old_ss as u64)?;
+        self.push_u64(old_rsp)?;
+        self.push_u64(rflags)?;
+        self.push_u64(cs_sel as u64)?;
+        self.push_u64(self.get_ip()?)
+    }
+
     pub fn switch_task(&mut self, mode: TSMode, new_sel: u16, desc: TSSDesc) -> Result<(), EmuException> {
         let old_sel  = self.core.dtregs.tr.selector;
         let old_tssd = self.core.dtregs.tr.cache;
@@ -501,17 +656,17 @@ impl super::Access {
         }
         if let TSMode::Iret = &mode { self.core.rflags.set_nesttask(false); }
 
-        let d = desc.D;
+        let (d, attr) = (desc.D, desc.attr());
         let new_tssd = DescTbl::from(desc);
 
         debug!("TaskSwitch");
         match (&self.mode, d) {
-            (access::CpuMode::Protected, 0) => {
+            (access::CpuMode::Protected, 0) | (access::CpuMode::V8086, 0) => {
                 // 16bit tss
                 if (new_tssd.limit as usize) < TSS16_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
 
                 let mut tss: TSS16 = Default::default();
-                self.read_l(&mut tss as *mut TSS16 as *mut _, old_tssd.base, TSS16_SIZE)?;
+                self.read_l_sys(&mut tss as *mut TSS16 as *mut _, old_tssd.base, TSS16_SIZE)?;
                 tss.prev_task = old_sel;
                 tss.ip     = self.get_ip()? as u16;
                 tss.flags  = self.get_rflags()? as u16;
@@ -529,12 +684,13 @@ impl super::Access {
                 tss.ds     = self.get_sgreg(SgReg::DS)?.0;
                 tss.ldtr   = self.get_ldtr()?;
                 debug!("From: {:x?}", tss);
-                self.write_l(old_tssd.base, &tss as *const TSS16 as *const _, TSS16_SIZE)?;
+                self.write_l_sys(old_tssd.base, &tss as *const TSS16 as *const _, TSS16_SIZE)?;
 
-                self.read_l(&mut tss as *mut TSS16 as *mut _, new_tssd.base, TSS16_SIZE)?;
+                self.read_l_sys(&mut tss as *mut TSS16 as *mut _, new_tssd.base, TSS16_SIZE)?;
                 debug!("To: {:x?}", tss);
                 self.set_ip(tss.ip as u64)?;
                 self.set_rflags(tss.flags as u64)?;
+                self.update_cpumode()?;
                 self.set_gpreg(GpReg16::AX, tss.ax)?;
                 self.set_gpreg(GpReg16::CX, tss.cx)?;
                 self.set_gpreg(GpReg16::DX, tss.dx)?;
@@ -551,15 +707,15 @@ impl super::Access {
 
                 if let TSMode::CallInt = &mode {
                     tss.prev_task = old_sel;
-                    self.write_l(new_tssd.base, &tss as *const TSS16 as *const _, TSS16_SIZE)?;
+                    self.write_l_sys(new_tssd.base, &tss as *const TSS16 as *const _, TSS16_SIZE)?;
                 }
             },
-            (access::CpuMode::Protected, 1) => {
+            (access::CpuMode::Protected, 1) | (access::CpuMode::V8086, 1) => {
                 // 32bit tss
                 if (new_tssd.limit as usize) < TSS32_SIZE-1 { return Err(EmuException::CPUException(CPUException::TS)); }
 
                 let mut tss: TSS32 = Default::default();
-                self.read_l(&mut tss as *mut TSS32 as *mut _, old_tssd.base, TSS32_SIZE)?;
+                self.read_l_sys(&mut tss as *mut TSS32 as *mut _, old_tssd.base, TSS32_SIZE)?;
                 tss.prev_task = old_sel;
                 tss.cr3    = self.get_creg(3)?;
                 tss.eip    = self.get_ip()? as u32;
@@ -580,13 +736,14 @@ impl super::Access {
                 tss.gs     = self.get_sgreg(SgReg::GS)?.0;
                 tss.ldtr   = self.get_ldtr()?;
                 debug!("From: {:x?}", tss);
-                self.write_l(old_tssd.base, &tss as *const TSS32 as *const _, TSS32_SIZE)?;
+                self.write_l_sys(old_tssd.base, &tss as *const TSS32 as *const _, TSS32_SIZE)?;
 
-                self.read_l(&mut tss as *mut TSS32 as *mut _, new_tssd.base, TSS32_SIZE)?;
+                self.read_l_sys(&mut tss as *mut TSS32 as *mut _, new_tssd.base, TSS32_SIZE)?;
                 debug!("To: {:x?}", tss);
                 self.set_creg(3, tss.cr3)?;
                 self.set_ip(tss.eip as u64)?;
                 self.set_rflags(tss.eflags as u64)?;
+                self.update_cpumode()?;
                 self.set_gpreg(GpReg32::EAX, tss.eax)?;
                 self.set_gpreg(GpReg32::ECX, tss.ecx)?;
                 self.set_gpreg(GpReg32::EDX, tss.edx)?;
@@ -605,14 +762,10 @@ impl super::Access {
 
                 if let TSMode::CallInt = &mode {
                     tss.prev_task = old_sel;
-                    self.write_l(new_tssd.base, &tss as *const TSS32 as *const _, TSS32_SIZE)?;
+                    self.write_l_sys(new_tssd.base, &tss as *const TSS32 as *const _, TSS32_SIZE)?;
                 }
             },
-            (access::CpuMode::Long, 1) => {
-                // 64bit tss
-                return Err(EmuException::NotImplementedFunction);
-            },
-            (access::CpuMode::Long, 0) => { return Err(EmuException::CPUException(CPUException::TS)); },
+            (access::CpuMode::Long, _) => { return Err(EmuException::CPUException(CPUException::GP(Some(new_sel)))); },
             _ => { panic!("{:?}", EmuException::UnexpectedError); },
         }
 
@@ -630,7 +783,7 @@ impl super::Access {
             },
         }
 
-        self.core.dtregs.tr = DescTblSel { selector: new_sel, cache: new_tssd, };
+        self.core.dtregs.tr = DescTblSel { selector: new_sel, attr: attr | 2, cache: new_tssd, };
         self.core.cregs.0.TS = 1;
         Ok(())
     }
@@ -639,7 +792,7 @@ impl super::Access {
         let old_tssd = self.core.dtregs.tr.cache;
 
         let mut prev_task: u16 = 0;
-        self.read_l(&mut prev_task as *mut u16 as *mut _, old_tssd.base, std::mem::size_of_val(&prev_task))?;
+        self.read_l_sys(&mut prev_task as *mut u16 as *mut _, old_tssd.base, std::mem::size_of_val(&prev_task))?;
 
         if let Some(DescType::System(SysDescType::TSS(tssdesc))) = self.obtain_g_desc(prev_task)? {
             self.switch_task(TSMode::Iret, prev_task, tssdesc)
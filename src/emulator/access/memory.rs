@@ -3,7 +3,8 @@ use libc::c_void;
 use packed_struct::prelude::*;
 use crate::emulator::*;
 use super::register::*;
-use crate::hardware::memory::MemDumpSize;
+use super::descriptor::{DataDescFlag, CodeDescFlag};
+use crate::hardware::memory::{Memory, MemDumpSize};
 
 #[derive(Debug, Default, PackedStruct)]
 #[packed_struct(bit_numbering="lsb0", size_bytes="8", endian="msb")]
@@ -86,22 +87,23 @@ struct PageCache {
     PWT: bool,
     PCD: bool,
     G:   bool,
+    D:   bool,
     base: u64,
     XD:  bool,
 }
 impl From<&PDPTE> for PageCache {
     fn from(e: &PDPTE) -> Self {
-        Self { RW: e.RW, US: e.US, PWT: e.PWT, PCD: e.PCD, G: e.G, base: (e.pdt_base as u64) << 12, XD: e.XD, }
+        Self { RW: e.RW, US: e.US, PWT: e.PWT, PCD: e.PCD, G: e.G, D: e.D, base: (e.pdt_base as u64) << 12, XD: e.XD, }
     }
 }
 impl From<&PDE> for PageCache {
     fn from(e: &PDE) -> Self {
-        Self { RW: e.RW, US: e.US, PWT: e.PWT, PCD: e.PCD, G: e.G, base: (e.pt_base as u64) << 12, XD: e.XD, }
+        Self { RW: e.RW, US: e.US, PWT: e.PWT, PCD: e.PCD, G: e.G, D: e.D, base: (e.pt_base as u64) << 12, XD: e.XD, }
     }
 }
 impl From<&PTE> for PageCache {
     fn from(e: &PTE) -> Self {
-        Self { RW: e.RW, US: e.US, PWT: e.PWT, PCD: e.PCD, G: e.G, base: (e.page_base as u64) << 12, XD: e.XD, }
+        Self { RW: e.RW, US: e.US, PWT: e.PWT, PCD: e.PCD, G: e.G, D: e.D, base: (e.page_base as u64) << 12, XD: e.XD, }
     }
 }
 
@@ -163,12 +165,14 @@ enum PageType {
     Page1GB(PageCache), Page4MB(PageCache), Page2MB(PageCache), Page4KB(PageCache)
 }
 
+type TLBMap = BTreeMap<(Option<u16>, u64), PageCache>;
+
 #[derive(Default)]
 pub(super) struct TLB {
-    p1gb: BTreeMap<u64, PageCache>,
-    p4mb: BTreeMap<u64, PageCache>,
-    p2mb: BTreeMap<u64, PageCache>,
-    p4kb: BTreeMap<u64, PageCache>,
+    p1gb: TLBMap,
+    p4mb: TLBMap,
+    p2mb: TLBMap,
+    p4kb: TLBMap,
 }
 
 impl TLB {
@@ -179,25 +183,40 @@ impl TLB {
         self.p4kb.clear();
     }
 
-    fn add_cache(&mut self, vpn: u64, cache: PageType) -> () {
+    pub fn flush_nonglobal(&mut self, pcid: Option<u16>) -> () {
+        for map in [&mut self.p1gb, &mut self.p4mb, &mut self.p2mb, &mut self.p4kb].iter_mut() {
+            map.retain(|(tag, _), _| tag.is_none() || (pcid.is_some() && *tag != pcid));
+        }
+    }
+
+    pub fn invalidate(&mut self, pcid: u16, vpn: u64, global: bool) -> () {
+        for (map, sft) in [(&mut self.p1gb, 18), (&mut self.p4mb, 10), (&mut self.p2mb, 9), (&mut self.p4kb, 0)].iter_mut() {
+            map.remove(&(Some(pcid), vpn >> *sft));
+            if global { map.remove(&(None, vpn >> *sft)); }
+        }
+    }
+
+    fn add_cache(&mut self, tag: Option<u16>, vpn: u64, cache: PageType) -> () {
         match cache {
-            PageType::Page1GB(tbl) if !tbl.PCD => { self.p1gb.insert(vpn >> 18, tbl); },
-            PageType::Page4MB(tbl) if !tbl.PCD => { self.p4mb.insert(vpn >> 10, tbl); },
-            PageType::Page2MB(tbl) if !tbl.PCD => { self.p2mb.insert(vpn >> 9, tbl); },
-            PageType::Page4KB(tbl) if !tbl.PCD => { self.p4kb.insert(vpn, tbl); },
+            PageType::Page1GB(tbl) if !tbl.PCD => { self.p1gb.insert((tag, vpn >> 18), tbl); },
+            PageType::Page4MB(tbl) if !tbl.PCD => { self.p4mb.insert((tag, vpn >> 10), tbl); },
+            PageType::Page2MB(tbl) if !tbl.PCD => { self.p2mb.insert((tag, vpn >> 9), tbl); },
+            PageType::Page4KB(tbl) if !tbl.PCD => { self.p4kb.insert((tag, vpn), tbl); },
             _ => {},
         }
     }
 
-    fn find_cache(&self, vpn: u64) -> Option<PageType> {
-        if let Some(tbl) = self.p1gb.get(&(vpn >> 18)) {
-            Some(PageType::Page1GB(*tbl))
-        } else if let Some(tbl) = self.p4mb.get(&(vpn >> 10)){
-            Some(PageType::Page4MB(*tbl))
-        } else if let Some(tbl) = self.p2mb.get(&(vpn >> 9)){
-            Some(PageType::Page2MB(*tbl))
-        } else if let Some(tbl) = self.p4kb.get(&vpn){
-            Some(PageType::Page4KB(*tbl))
+    fn find_cache(&self, pcid: u16, vpn: u64) -> Option<PageType> {
+        let get = |map: &TLBMap, idx: u64| map.get(&(Some(pcid), idx)).or_else(|| map.get(&(None, idx))).copied();
+
+        if let Some(tbl) = get(&self.p1gb, vpn >> 18) {
+            Some(PageType::Page1GB(tbl))
+        } else if let Some(tbl) = get(&self.p4mb, vpn >> 10) {
+            Some(PageType::Page4MB(tbl))
+        } else if let Some(tbl) = get(&self.p2mb, vpn >> 9) {
+            Some(PageType::Page2MB(tbl))
+        } else if let Some(tbl) = get(&self.p4kb, vpn) {
+            Some(PageType::Page4KB(tbl))
         } else {
             None
         }
@@ -255,6 +274,22 @@ impl super::Access {
         self.get_data64((SgReg::SS, rsp-8))
     }
 
+    pub fn stack_mask(&self) -> u64 {
+        match self.stsz {
+            super::AcsSize::BIT16 => 0xffff,
+            super::AcsSize::BIT32 => 0xffff_ffff,
+            super::AcsSize::BIT64 => !0,
+        }
+    }
+
+    pub fn release_stack(&mut self, size: u16) -> Result<(), EmuException> {
+        match self.stsz {
+            super::AcsSize::BIT16 => self.update_gpreg(GpReg16::SP, size as i16),
+            super::AcsSize::BIT32 => self.update_gpreg(GpReg32::ESP, size as i32),
+            super::AcsSize::BIT64 => self.update_gpreg(GpReg64::RSP, size as i64),
+        }
+    }
+
     pub fn read_p(&self, dst: *mut c_void, src_addr: u64, len: usize) -> usize {
         if let Ok(n) = self.mem.read().unwrap().read_data(dst, src_addr as usize, len) { n } else { 0 }
     }
@@ -264,32 +299,116 @@ impl super::Access {
     }
 
     pub fn read_l(&self, dst: *mut c_void, src_addr: u64, len: usize) -> Result<usize, EmuException> {
-        Ok(self.read_p(dst, self.trans_l2p(MemAccessMode::Read, src_addr)?, len))
+        let (mut n, mut ofs) = (0, 0);
+        for (paddr, size) in self.trans_l2p_pages(MemAccessMode::Read, src_addr, len)? {
+            n += self.read_p((dst as *mut u8).wrapping_add(ofs) as *mut _, paddr, size);
+            ofs += size;
+        }
+        Ok(n)
     }
 
     pub fn write_l(&mut self, dst_addr: u64, src: *const c_void, len: usize) -> Result<usize, EmuException> {
-        Ok(self.write_p(self.trans_l2p(MemAccessMode::Write, dst_addr)?, src, len))
+        let (mut n, mut ofs) = (0, 0);
+        for (paddr, size) in self.trans_l2p_pages(MemAccessMode::Write, dst_addr, len)? {
+            n += self.write_p(paddr, (src as *const u8).wrapping_add(ofs) as *const _, size);
+            ofs += size;
+        }
+        Ok(n)
+    }
+
+    pub fn read_l_sys(&self, dst: *mut c_void, src_addr: u64, len: usize) -> Result<usize, EmuException> {
+        let prev = self.set_supervisor(true);
+        let ret = self.read_l(dst, src_addr, len);
+        self.set_supervisor(prev);
+        ret
+    }
+
+    pub fn write_l_sys(&mut self, dst_addr: u64, src: *const c_void, len: usize) -> Result<usize, EmuException> {
+        let prev = self.set_supervisor(true);
+        let ret = self.write_l(dst_addr, src, len);
+        self.set_supervisor(prev);
+        ret
+    }
+
+    pub fn set_supervisor(&self, sys: bool) -> bool {
+        self.supervisor.replace(sys)
     }
 
     pub fn read_v(&self, seg: SgReg, dst: *mut c_void, src_addr: u64, len: usize) -> Result<usize, EmuException> {
-        Ok(self.read_p(dst, self.trans_v2p(MemAccessMode::Read, seg, src_addr)?, len))
+        Ok(self.read_p(dst, self.trans_v2p(MemAccessMode::Read, seg, src_addr, len)?, len))
     }
 
     pub fn write_v(&mut self, seg: SgReg, dst_addr: u64, src: *const c_void, len: usize) -> Result<usize, EmuException> {
-        Ok(self.write_p(self.trans_v2p(MemAccessMode::Write, seg, dst_addr)?, src, len))
+        Ok(self.write_p(self.trans_v2p(MemAccessMode::Write, seg, dst_addr, len)?, src, len))
     }
 
     pub fn addr_v2p(&mut self, seg: SgReg, vaddr: u64) -> Result<u64, EmuException> {
-        self.trans_v2p(MemAccessMode::Monitor, seg, vaddr)
+        self.trans_v2p(MemAccessMode::Monitor, seg, vaddr, 1)
+    }
+
+    pub fn check_writable(&self, target: (SgReg, u64), len: usize) -> Result<(), EmuException> {
+        self.trans_v2p_pages(MemAccessMode::Write, target.0, target.1, len)?;
+        Ok(())
+    }
+
+    pub fn addr_v2l(&self, seg: SgReg, vaddr: u64) -> Result<u64, EmuException> {
+        self.trans_v2l(MemAccessMode::Monitor, seg, vaddr, 1)
+    }
+
+    pub fn is_memio(&self, paddr: u64) -> bool {
+        self.dev.check_memio(paddr, 1).0
+    }
+
+    pub fn check_code_breakpoint(&mut self) -> Result<(), EmuException> {
+        if self.core.rflags.is_resume() || !self.core.dregs.dr7.is_any_enabled() { return Ok(()); }
+
+        let laddr = self.trans_v2l(MemAccessMode::Monitor, SgReg::CS, self.get_ip()?, 1)?;
+        match self.core.dregs.match_bp(laddr, 1, None) {
+            0 => Ok(()),
+            hits => {
+                self.core.dregs.dr6.set_hits(hits);
+                Err(EmuException::CPUException(CPUException::DB))
+            },
+        }
+    }
+
+    pub fn take_watch_hits(&self) -> u8 {
+        self.watch.replace(0)
+    }
+
+    pub fn take_watched(&self) -> Option<(u64, bool)> {
+        self.watched.take()
+    }
+
+    pub fn get_pcid(&self) -> u16 {
+        if self.core.cregs.4.PCIDE == 1 { self.core.cregs.3.get_pcid() } else { 0 }
+    }
+
+    pub fn invalidate_page(&mut self, seg: SgReg, vaddr: u64) -> Result<(), EmuException> {
+        let laddr = self.trans_v2l(MemAccessMode::Monitor, seg, vaddr, 1)?;
+        self.tlb.borrow_mut().invalidate(self.get_pcid(), laddr >> 12, true);
+        Ok(())
+    }
+
+    pub fn invalidate_pcid(&mut self, ty: u64, pcid: u16, laddr: u64) -> Result<(), EmuException> {
+        let mut tlb = self.tlb.borrow_mut();
+        match ty {
+            0 => tlb.invalidate(pcid, laddr >> 12, false),
+            1 => tlb.flush_nonglobal(Some(pcid)),
+            2 => tlb.flush(),
+            3 => tlb.flush_nonglobal(None),
+            _ => { return Err(EmuException::CPUException(CPUException::GP(None))); },
+        }
+        Ok(())
     }
 
     pub fn dump_code(&self, unit: MemDumpSize) -> () {
-        let addr = self.trans_v2p(MemAccessMode::Read, SgReg::CS, self.get_ip().unwrap()).unwrap();
+        let addr = self.trans_v2p(MemAccessMode::Monitor, SgReg::CS, self.get_ip().unwrap(), 1).unwrap();
         self.mem.read().unwrap().dump(addr as usize -0x10, 0x20, unit);
     }
 
     pub fn dump_stack(&self, unit: MemDumpSize) -> () {
-        let addr = self.trans_v2p(MemAccessMode::Read, SgReg::SS, self.get_gpreg(GpReg64::RSP).unwrap()).unwrap();
+        let addr = self.trans_v2p(MemAccessMode::Monitor, SgReg::SS, self.get_gpreg(GpReg64::RSP).unwrap(), 1).unwrap();
         self.mem.read().unwrap().dump(addr as usize, 0x40, unit);
     }
 }
@@ -313,86 +432,168 @@ impl super::Access {
         Ok(sp)
     }
 
+    fn check_watchpoint(&self, acsmode: MemAccessMode, sg: SgReg, vaddr: u64, size: MemAccessSize) -> Result<(), EmuException> {
+        if !self.core.dregs.dr7.is_any_enabled() && self.watchpoints.is_empty() { return Ok(()); }
+
+        let laddr = self.trans_v2l(acsmode, sg, vaddr, size as usize)?;
+        let write = acsmode == MemAccessMode::Write;
+        let hits = self.core.dregs.match_bp(laddr, size as u64, Some(write));
+        self.watch.set(self.watch.get() | hits);
+
+        let found = self.watchpoints.iter().find(|(addr, kind)| {
+            let cond = match kind {
+                WatchKind::Write => write,
+                WatchKind::Read => !write,
+                WatchKind::ReadWrite => true,
+            };
+            cond && laddr <= *addr && *addr < laddr + size as u64
+        });
+        if let (Some((addr, _)), None) = (found, self.watched.get()) {
+            self.watched.set(Some((*addr, write)));
+        }
+        Ok(())
+    }
+
     fn get_data_size(&self, sg: SgReg, vaddr: u64, size: MemAccessSize) -> Result<u64, EmuException> {
-        let paddr = self.trans_v2p(MemAccessMode::Read, sg, vaddr)?;
-        let v = if self.dev.check_memio(paddr, size as u64 - 1) {
-            let mut data = vec![0; size as usize];
-            self.dev.read_memio(paddr, &mut data);
-            let mut dst = [0; 8];
-            dst[..size as usize].copy_from_slice(&data);
-            u64::from_le_bytes(dst)
-        } else {
-            let paddr = paddr as usize;
-            let mem = self.mem.read().unwrap();
-            match size {
-                MemAccessSize::Byte  => mem.read8(paddr) as u64,
-                MemAccessSize::Word  => mem.read16(paddr) as u64,
-                MemAccessSize::DWord => mem.read32(paddr) as u64,
-                MemAccessSize::QWord => mem.read64(paddr),
-            }
-        };
-        Ok(v)
+        let pages = self.trans_v2p_pages(MemAccessMode::Read, sg, vaddr, size as usize)?;
+        self.check_watchpoint(MemAccessMode::Read, sg, vaddr, size)?;
+
+        let mut data = [0; 8];
+        let mut ofs = 0;
+        for (paddr, len) in pages.iter() {
+            self.read_phys(*paddr, &mut data[ofs..ofs+len]);
+            ofs += len;
+        }
+        Ok(u64::from_le_bytes(data))
     }
 
     fn set_data_size(&mut self, sg: SgReg, vaddr: u64, v: u64, size: MemAccessSize) -> Result<(), EmuException> {
-        let paddr = self.trans_v2p(MemAccessMode::Write, sg, vaddr)?;
-        if self.dev.check_memio(paddr, size as u64 - 1) {
-            self.dev.write_memio(paddr, &v.to_le_bytes()[..size as usize]);
-        } else {
-            let paddr = paddr as usize;
-            let mut mem = self.mem.write().unwrap();
-            match size {
-                MemAccessSize::Byte  => mem.write8(paddr, v as u8),
-                MemAccessSize::Word  => mem.write16(paddr, v as u16),
-                MemAccessSize::DWord => mem.write32(paddr, v as u32),
-                MemAccessSize::QWord => mem.write64(paddr, v),
-            }
+        let pages = self.trans_v2p_pages(MemAccessMode::Write, sg, vaddr, size as usize)?;
+        self.check_watchpoint(MemAccessMode::Write, sg, vaddr, size)?;
+
+        let data = v.to_le_bytes();
+        let mut ofs = 0;
+        for (paddr, len) in pages.iter() {
+            self.write_phys(*paddr, &data[ofs..ofs+len]);
+            ofs += len;
         }
         Ok(())
     }
 
     fn get_code_size(&self, index: u64, size: MemAccessSize) -> Result<u64, EmuException> {
         let ip: u64 = self.get_ip()?;
-        let paddr = self.trans_v2p(MemAccessMode::Exec, SgReg::CS, ip + index)? as usize;
-        let mem = self.mem.read().unwrap();
-        let v = match size {
-            MemAccessSize::Byte  => mem.read8(paddr) as u64,
-            MemAccessSize::Word  => mem.read16(paddr) as u64,
-            MemAccessSize::DWord => mem.read32(paddr) as u64,
-            MemAccessSize::QWord => mem.read64(paddr),
-        };
-        Ok(v)
+        let pages = self.trans_v2p_pages(MemAccessMode::Exec, SgReg::CS, ip + index, size as usize)?;
+
+        let mut data = [0; 8];
+        let mut ofs = 0;
+        for (paddr, len) in pages.iter() {
+            self.read_phys(*paddr, &mut data[ofs..ofs+len]);
+            ofs += len;
+        }
+        Ok(u64::from_le_bytes(data))
+    }
+
+    fn read_phys(&self, paddr: u64, dst: &mut [u8]) {
+        let mut ofs = 0;
+        while ofs < dst.len() {
+            let addr = paddr + ofs as u64;
+            let (memio, len) = self.dev.check_memio(addr, (dst.len() - ofs) as u64);
+            let part = &mut dst[ofs..ofs + len as usize];
+            if memio {
+                self.dev.read_memio(addr, part);
+            } else {
+                self.read_p(part.as_mut_ptr() as *mut _, addr, part.len());
+            }
+            ofs += len as usize;
+        }
+    }
+
+    fn write_phys(&mut self, paddr: u64, src: &[u8]) {
+        let mut ofs = 0;
+        while ofs < src.len() {
+            let addr = paddr + ofs as u64;
+            let (memio, len) = self.dev.check_memio(addr, (src.len() - ofs) as u64);
+            let part = &src[ofs..ofs + len as usize];
+            if memio {
+                self.dev.write_memio(addr, part);
+            } else {
+                self.write_p(addr, part.as_ptr() as *const _, part.len());
+            }
+            ofs += len as usize;
+        }
+    }
+
+    fn trans_v2p_pages(&self, acsmode: MemAccessMode, sg: SgReg, vaddr: u64, len: usize) -> Result<[(u64, usize); 2], EmuException> {
+        let laddr = self.trans_v2l(acsmode, sg, vaddr, len)?;
+        let first = std::cmp::min(len, 0x1000 - (laddr & 0xfff) as usize);
+
+        let paddr = self.trans_v2p(acsmode, sg, vaddr, first)?;
+        if first == len {
+            return Ok([(paddr, len), (0, 0)]);
+        }
+        Ok([(paddr, first), (self.trans_v2p(acsmode, sg, vaddr + first as u64, len - first)?, len - first)])
+    }
+
+    fn trans_l2p_pages(&self, acsmode: MemAccessMode, laddr: u64, len: usize) -> Result<Vec<(u64, usize)>, EmuException> {
+        let mut pages = Vec::new();
+        let mut ofs = 0;
+        while ofs < len {
+            let addr = laddr + ofs as u64;
+            let size = std::cmp::min(len - ofs, 0x1000 - (addr & 0xfff) as usize);
+            pages.push((self.trans_l2p(acsmode, addr)?, size));
+            ofs += size;
+        }
+        Ok(pages)
     }
 
-    fn trans_v2p(&self, acsmode: MemAccessMode, sg: SgReg, vaddr: u64) -> Result<u64, EmuException> {
-        let laddr = self.trans_v2l(acsmode, sg, vaddr)?;
+    fn trans_v2p(&self, acsmode: MemAccessMode, sg: SgReg, vaddr: u64, len: usize) -> Result<u64, EmuException> {
+        let laddr = self.trans_v2l(acsmode, sg, vaddr, len)?;
         let paddr = self.trans_l2p(acsmode, laddr)?;
 
         Ok( if self.a20gate { paddr } else { paddr & (1<<20)-1 } )
     }
 
-    fn trans_v2l(&self, _acsmode: MemAccessMode, sg: SgReg, vaddr: u64) -> Result<u64, EmuException> {
-        let long64 = self.mode == super::CpuMode::Long && self.oasz.ad == super::AcsSize::BIT64;
-
+    fn trans_v2l(&self, acsmode: MemAccessMode, sg: SgReg, vaddr: u64, len: usize) -> Result<u64, EmuException> {
+        let fault = if sg == SgReg::SS { CPUException::SS(None) } else { CPUException::GP(None) };
         let cache = self.get_sgreg(sg)?.1;
-        let base  = if long64 && !(sg == SgReg::FS || sg == SgReg::GS) { 0 } else { cache.base };
-
-        match (&self.mode, &self.oasz.ad, sg) {
-            (super::CpuMode::Long, super::AcsSize::BIT64, SgReg::CS) | (super::CpuMode::Protected, _, _) => {
-                /*
-                if cache.Type & 0 {
 
-                } else {
+        match (&self.mode, &self.oasz.ad) {
+            (super::CpuMode::Real, _) => {},
+            (super::CpuMode::Long, super::AcsSize::BIT64) => {
+                let laddr = if sg == SgReg::FS || sg == SgReg::GS { cache.base.wrapping_add(vaddr) } else { vaddr };
+                let bits = if self.core.cregs.4.LA57 == 1 { 57 } else { 48 };
+                if acsmode != MemAccessMode::Monitor && ((laddr as i64) << (64-bits) >> (64-bits)) as u64 != laddr {
+                    return Err(EmuException::CPUException(fault));
+                }
+                return Ok(laddr);
+            },
+            (mode, _) => {
+                if *mode != super::CpuMode::V8086 && cache.P == 0 {
+                    return Err(EmuException::CPUException(fault));
+                }
 
+                let code = cache.Type & 8 != 0;
+                if *mode != super::CpuMode::V8086 && match (acsmode, code) {
+                    (MemAccessMode::Read, true)   => !CodeDescFlag::from_bits_truncate(cache.Type).contains(CodeDescFlag::R),
+                    (MemAccessMode::Write, true)  => true,
+                    (MemAccessMode::Write, false) => !DataDescFlag::from_bits_truncate(cache.Type).contains(DataDescFlag::W),
+                    _ => false,
+                } {
+                    return Err(EmuException::CPUException(fault));
                 }
-                */
 
-                if !long64 && (vaddr >> 12*cache.G) > cache.limit as u64 { return Err(EmuException::CPUException(CPUException::GP(None))); }
+                let limit = if cache.G == 1 { ((cache.limit as u64) << 12) | 0xfff } else { cache.limit as u64 };
+                let end = vaddr + len.max(1) as u64 - 1;
+                let valid = if !code && DataDescFlag::from_bits_truncate(cache.Type).contains(DataDescFlag::E) {
+                    vaddr > limit && end <= if cache.DB == 1 { 0xffffffff } else { 0xffff }
+                } else {
+                    end <= limit
+                };
+                if !valid { return Err(EmuException::CPUException(fault)); }
             },
-            _ => {}
         }
 
-        Ok(base + vaddr)
+        Ok(cache.base + vaddr)
     }
 
     fn trans_l2p(&self, acs: MemAccessMode, laddr: u64) -> Result<u64, EmuException> {
@@ -400,13 +601,15 @@ impl super::Access {
             let vpn = laddr >> 12;
             let mut tlb = self.tlb.borrow_mut();
 
-            let ptype = if let Some(ty) = tlb.find_cache(vpn) {
-                match ty {
-                    PageType::Page1GB(e) | PageType::Page4MB(e) | PageType::Page2MB(e) | PageType::Page4KB(e) => {
-                        if (acs == MemAccessMode::Write && !e.RW) || (self.get_cpl()? > 2 && !e.US ){
-                            return Err(EmuException::CPUException(CPUException::PF(laddr)));
-                        }
-                    },
+            let pcid = self.get_pcid();
+            let code = self.pf_errcode(acs)?;
+            let cached = tlb.find_cache(pcid, vpn).filter(|ty| match ty {
+                PageType::Page1GB(e) | PageType::Page4MB(e) | PageType::Page2MB(e) | PageType::Page4KB(e) => acs != MemAccessMode::Write || e.D,
+            });
+
+            let ptype = if let Some(ty) = cached {
+                if let Err(code) = self.check_permission(acs, code, &ty) {
+                    return Err(EmuException::CPUException(CPUException::PF(laddr, code.bits)));
                 }
                 ty
             } else {
@@ -418,28 +621,26 @@ impl super::Access {
                         PagingStructIndex::from(&LAddrPAE::unpack(&(laddr as u32).to_be_bytes()).unwrap())
                     },
                     super::PagingMode::Ia32e4Lv => {
-                        if laddr >> 48 != 0 {
-                            return Err(EmuException::CPUException(CPUException::GP(None)));
-                        }
                         let l = LAddrIa32e::unpack(&laddr.to_be_bytes()).unwrap();
                         let mut psi = PagingStructIndex::from(&l);
                         psi.pml5 = None;
                         psi
                     },
                     super::PagingMode::Ia32e5Lv => {
-                        if laddr >> 57 != 0 {
-                            return Err(EmuException::CPUException(CPUException::GP(None)));
-                        }
                         PagingStructIndex::from(&LAddrIa32e::unpack(&laddr.to_be_bytes()).unwrap())
                     },
                 };
 
-                if let Some(p) = self.page_walk(acs, &md, psidx) {
-                    debug!("{:x?}", p);
-                    tlb.add_cache(vpn, p);
-                    p
-                } else {
-                    return Err(EmuException::CPUException(CPUException::PF(laddr)));
+                match self.page_walk(acs, code, &md, psidx) {
+                    Ok(p) => {
+                        debug!("{:x?}", p);
+                        let global = match p {
+                            PageType::Page1GB(e) | PageType::Page4MB(e) | PageType::Page2MB(e) | PageType::Page4KB(e) => e.G && self.core.cregs.4.PGE == 1,
+                        };
+                        tlb.add_cache(if global { None } else { Some(pcid) }, vpn, p);
+                        p
+                    },
+                    Err(code) => { return Err(EmuException::CPUException(CPUException::PF(laddr, code.bits))); },
                 }
             };
 
@@ -453,65 +654,177 @@ impl super::Access {
         Ok(paddr)
    }
 
-    fn page_walk(&self, acs: MemAccessMode, pmd: &super::PagingMode, psidx: PagingStructIndex) -> Option<PageType> {
-        let cpl = self.get_cpl().ok()?;
+    fn pf_errcode(&self, acs: MemAccessMode) -> Result<PFErrorCode, EmuException> {
+        let mut code = PFErrorCode::empty();
+        if acs == MemAccessMode::Write { code |= PFErrorCode::W; }
+        if !self.supervisor.get() && self.get_cpl()? > 2 { code |= PFErrorCode::U; }
+        if acs == MemAccessMode::Exec && (self.core.msr.efer.NXE == 1 || self.core.cregs.4.SMEP == 1) { code |= PFErrorCode::I; }
+        Ok(code)
+    }
+
+    fn check_permission(&self, acs: MemAccessMode, code: PFErrorCode, ptype: &PageType) -> Result<(), PFErrorCode> {
+        let e = match ptype {
+            PageType::Page1GB(e) | PageType::Page4MB(e) | PageType::Page2MB(e) | PageType::Page4KB(e) => e,
+        };
+        let (cr0, cr4) = (&self.core.cregs.0, &self.core.cregs.4);
+
+        let permit = match (acs, code.contains(PFErrorCode::U)) {
+            (MemAccessMode::Exec, _) if e.XD && self.core.msr.efer.NXE == 1 => false,
+            (MemAccessMode::Exec, true)  => e.US,
+            (MemAccessMode::Exec, false) => !(e.US && cr4.SMEP == 1),
+            (MemAccessMode::Write, true) => e.US && e.RW,
+            (_, true) => e.US,
+            (acs, false) => {
+                (acs != MemAccessMode::Write || e.RW || cr0.WP == 0) &&
+                !(e.US && cr4.SMAP == 1 && (self.supervisor.get() || !self.core.rflags.is_aligncheck()))
+            },
+        };
+
+        if permit { Ok(()) } else { Err(code | PFErrorCode::P) }
+    }
+
+    fn page_walk(&self, acs: MemAccessMode, code: PFErrorCode, pmd: &super::PagingMode, psidx: PagingStructIndex) -> Result<PageType, PFErrorCode> {
+        let mut mem = self.mem.write().unwrap();
+        let mut walk = PageWalk::new(code);
+
+        let ptype = self.walk_table(&mem, &mut walk, pmd, psidx)?;
+        let ptype = walk.combine(acs, ptype);
+        self.check_permission(acs, code, &ptype)?;
+        walk.update(&mut mem, acs);
+
+        Ok(ptype)
+    }
+
+    fn walk_table(&self, mem: &Memory, walk: &mut PageWalk, pmd: &super::PagingMode, psidx: PagingStructIndex) -> Result<PageType, PFErrorCode> {
+        let nxe = self.core.msr.efer.NXE == 1;
+
+        let legacy = if let super::PagingMode::Legacy = pmd { true } else { false };
+        let pae = if let super::PagingMode::LegacyPAE = pmd { true } else { false };
+        let rsvd = if legacy { 0 } else { (((1<<12)-1) << 40) | if nxe { 0 } else { 1<<63 } };
+
         let cr3 = &self.core.cregs.3;
-        let mem = self.mem.read().unwrap();
 
         let pml5e: Option<PML5E> = if let Some(idx) = psidx.pml5 {
-            let pml5_base = cr3.get_pagedir_base() as usize;
-            Some(PML5E::unpack(&mem.read64(pml5_base + (idx as usize)*8).to_be_bytes()).unwrap())
+            let raw = walk.entry(mem, cr3.get_pagedir_base() as usize + (idx as usize)*8, false, rsvd | 1<<7)?;
+            Some(PML5E::unpack(&raw.to_be_bytes()).unwrap())
         } else { None };
 
         let pml4e: Option<PML4E> = if let Some(idx) = psidx.pml4 {
-            let pml4_base = if let Some(e) = pml5e {
-                if !e.P || (acs == MemAccessMode::Write && !e.RW) || (cpl > 2 && !e.US) { return None; }
-                (e.pml4_base as usize) << 12
-            } else { cr3.get_pagedir_base() as usize };
-            Some(PML4E::unpack(&mem.read64(pml4_base + (idx as usize)*8).to_be_bytes()).unwrap())
+            let pml4_base = if let Some(e) = pml5e { (e.pml4_base as usize) << 12 } else { cr3.get_pagedir_base() as usize };
+            let raw = walk.entry(mem, pml4_base + (idx as usize)*8, false, rsvd | 1<<7)?;
+            Some(PML4E::unpack(&raw.to_be_bytes()).unwrap())
         } else { None };
 
         let pdpte: Option<PDPTE> = if let Some(idx) = psidx.pdpt {
-            let pdpt_base = if let Some(e) = pml4e {
-                if !e.P || (acs == MemAccessMode::Write && !e.RW) || (cpl > 2 && !e.US) { return None; }
-                (e.pdpt_base as usize) << 12
-            } else { cr3.get_pagedir_base() as usize };
-            let raw = if let super::PagingMode::Legacy = pmd { mem.read32(pdpt_base + (idx as usize)*4) as u64 } else { mem.read64(pdpt_base + (idx as usize)*8) };
+            let raw = if let Some(e) = pml4e {
+                walk.entry(mem, ((e.pdpt_base as usize) << 12) + (idx as usize)*8, false, rsvd)?
+            } else {
+                walk.pdpte_pae(mem, cr3.get_pagedir_base() as usize + (idx as usize)*8, rsvd | 0x1e6 | 1<<63)?
+            };
             Some(PDPTE::unpack(&raw.to_be_bytes()).unwrap())
         } else { None };
 
         let pd_base = if let Some(mut e) = pdpte {
-            if e.PS {
+            if e.PS && !pae {
+                if ((e.pdt_base as u64) << 12) & ((1<<30)-1) & !(1<<12) != 0 { return Err(walk.code | PFErrorCode::P | PFErrorCode::RSVD); }
                 e.pdt_base &= !((1<<18)-1);
-                return Some(PageType::Page1GB(PageCache::from(&e)));
-            } else {
-                if !e.P || (acs == MemAccessMode::Write && !e.RW) || (cpl > 2 && !e.US) { return None; }
-                (e.pdt_base as usize) << 12
+                return Ok(PageType::Page1GB(PageCache::from(&e)));
             }
+            (e.pdt_base as usize) << 12
         } else { cr3.get_pagedir_base() as usize };
-        let raw = if let super::PagingMode::Legacy = pmd { mem.read32(pd_base + (psidx.pd as usize)*4) as u64 } else { mem.read64(pd_base + (psidx.pd as usize)*8) };
+        let raw = walk.entry(mem, pd_base + (psidx.pd as usize)*if legacy { 4 } else { 8 }, legacy, rsvd)?;
         let mut pde = PDE::unpack(&raw.to_be_bytes()).unwrap();
 
         let pt_base = match (psidx.legacy, self.core.cregs.4.PSE, pde.PS) {
             (true, 1, true) => {
+                if raw & (1<<21) != 0 { return Err(walk.code | PFErrorCode::P | PFErrorCode::RSVD); }
                 pde.pt_base &= !((1<<10)-1);
-                return Some(PageType::Page4MB(PageCache::from(&pde)));
+                return Ok(PageType::Page4MB(PageCache::from(&pde)));
             },
             (false, _, true) => {
+                if raw & ((1<<21)-1) & !((1<<13)-1) != 0 { return Err(walk.code | PFErrorCode::P | PFErrorCode::RSVD); }
                 pde.pt_base &= !((1<<9)-1);
-                return Some(PageType::Page2MB(PageCache::from(&pde)));
-            },
-            _ => {
-                if !pde.P || (acs == MemAccessMode::Write && !pde.RW) || (cpl > 2 && !pde.US) { return None; }
-                (pde.pt_base as usize) << 12
+                return Ok(PageType::Page2MB(PageCache::from(&pde)));
             },
+            _ => (pde.pt_base as usize) << 12,
         };
-        let raw = if let super::PagingMode::Legacy = pmd { mem.read32(pt_base + (psidx.pt as usize)*4) as u64 } else { mem.read64(pt_base + (psidx.pt as usize)*8) };
+        let raw = walk.entry(mem, pt_base + (psidx.pt as usize)*if legacy { 4 } else { 8 }, legacy, rsvd)?;
         let pte = PTE::unpack(&raw.to_be_bytes()).unwrap();
 
-        if !pte.P || (acs == MemAccessMode::Write && !pte.RW) || (cpl > 2 && !pte.US) { return None; }
+        Ok(PageType::Page4KB(PageCache::from(&pte)))
+    }
+}
+
+bitflags! { struct PFErrorCode: u32 {
+    const P    = 0b00000001;
+    const W    = 0b00000010;
+    const U    = 0b00000100;
+    const RSVD = 0b00001000;
+    const I    = 0b00010000;
+} }
+
+struct PageWalk {
+    code: PFErrorCode,
+    entries: Vec<(usize, bool)>,
+    RW: bool,
+    US: bool,
+    XD: bool,
+}
+
+impl PageWalk {
+    fn new(code: PFErrorCode) -> Self {
+        Self { code, entries: Vec::new(), RW: true, US: true, XD: false }
+    }
+
+    fn read(mem: &Memory, addr: usize, legacy: bool) -> u64 {
+        if legacy { mem.read32(addr) as u64 } else { mem.read64(addr) }
+    }
+
+    fn entry(&mut self, mem: &Memory, addr: usize, legacy: bool, rsvd: u64) -> Result<u64, PFErrorCode> {
+        let raw = PageWalk::read(mem, addr, legacy);
+
+        if raw & 1 == 0 { return Err(self.code); }
+        if raw & rsvd != 0 { return Err(self.code | PFErrorCode::P | PFErrorCode::RSVD); }
+
+        self.RW &= raw & (1<<1) != 0;
+        self.US &= raw & (1<<2) != 0;
+        self.XD |= raw & (1<<63) != 0;
+        self.entries.push((addr, legacy));
+        Ok(raw)
+    }
+
+    fn pdpte_pae(&mut self, mem: &Memory, addr: usize, rsvd: u64) -> Result<u64, PFErrorCode> {
+        let raw = mem.read64(addr);
+
+        if raw & 1 == 0 { return Err(self.code); }
+        if raw & rsvd != 0 { return Err(self.code | PFErrorCode::P | PFErrorCode::RSVD); }
+        Ok(raw)
+    }
 
-        Some(PageType::Page4KB(PageCache::from(&pte)))
+    fn combine(&self, acs: MemAccessMode, mut ptype: PageType) -> PageType {
+        match ptype {
+            PageType::Page1GB(ref mut e) | PageType::Page4MB(ref mut e) | PageType::Page2MB(ref mut e) | PageType::Page4KB(ref mut e) => {
+                e.RW = self.RW;
+                e.US = self.US;
+                e.XD = self.XD;
+                e.D  = e.D || acs == MemAccessMode::Write;
+            },
+        }
+        ptype
+    }
+
+    fn update(&self, mem: &mut Memory, acs: MemAccessMode) -> () {
+        let last = self.entries.len()-1;
+        for (i, (addr, legacy)) in self.entries.iter().enumerate() {
+            let mut raw = PageWalk::read(mem, *addr, *legacy);
+            let old = raw;
+
+            raw |= 1<<5;
+            if i == last && acs == MemAccessMode::Write { raw |= 1<<6; }
+            if raw != old {
+                if *legacy { mem.write32(*addr, raw as u32); } else { mem.write64(*addr, raw); }
+            }
+        }
     }
 }
 
@@ -683,6 +996,7 @@ fn page_walk_ia32e4l_test() {
     assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x7ff0cafebabe).unwrap(), 0x1ffffffabe);
     assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x7ff0deadbeef).unwrap(), 0x2fffcdbeef);
     assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x8000feedc0de).unwrap(), 0x3ffeedc0de);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0xffff8000feedc0de).unwrap(), 0x3ffeedc0de);
 }
 
 #[cfg(test)]
@@ -743,6 +1057,200 @@ fn page_walk_ia32e5l_test() {
     assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x1007ff0cafebabe).unwrap(), 0x1ffffffabe);
     assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x1007ff0deadbeef).unwrap(), 0x2fffcdbeef);
     assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x1008000feedc0de).unwrap(), 0x3ffeedc0de);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0xff008000feedc0de).unwrap(), 0x3ffeedc0de);
+}
+
+#[cfg(test)]
+#[test]
+fn page_walk_fault_test() {
+    let hw = hardware::Hardware::new(0x3000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.pgmd = Some(super::PagingMode::LegacyPAE);
+    ac.core.cregs.0.WP = 1;
+    ac.core.cregs.3.from_u64(0);
+    {
+        let mut mem = ac.mem.write().unwrap();
+
+        let mut pdpte: PDPTE = Default::default();
+        pdpte.P = true;
+        pdpte.pdt_base = 0x1;
+        mem.write64(0 + 8*0x3, u64::from_be_bytes(pdpte.pack().unwrap()));
+
+        let mut pde: PDE = Default::default();
+        pde.P = true;
+        pde.RW = true;
+        pde.pt_base = 0x2;
+        mem.write64(0x1000 + 8*0x57, u64::from_be_bytes(pde.pack().unwrap()));
+
+        let mut pte: PTE = Default::default();
+        pte.P = true;
+        pte.RW = true;
+        pte.page_base = 0x1;
+        mem.write64(0x2000 + 8*0x1eb, u64::from_be_bytes(pte.pack().unwrap()));
+
+        let mut pte: PTE = Default::default();
+        pte.P = true;
+        pte.page_base = 0x2;
+        mem.write64(0x2000 + 8*0x1ec, u64::from_be_bytes(pte.pack().unwrap()));
+
+        mem.write64(0x2000 + 8*0x1ed, 0x0000_f000_0000_3001);
+    }
+
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0xcafebabe).unwrap(), 0x1abe);
+    {
+        let mem = ac.mem.read().unwrap();
+        assert_eq!(mem.read64(0x1000 + 8*0x57) & 0x60, 0x20);
+        assert_eq!(mem.read64(0x2000 + 8*0x1eb) & 0x60, 0x20);
+    }
+
+    assert_eq!(ac.trans_l2p(MemAccessMode::Write, 0xcafebabe).unwrap(), 0x1abe);
+    assert_eq!(ac.mem.read().unwrap().read64(0x2000 + 8*0x1eb) & 0x60, 0x60);
+
+    match ac.trans_l2p(MemAccessMode::Write, 0xcafecabe) {
+        Err(EmuException::CPUException(CPUException::PF(0xcafecabe, 0x3))) => {},
+        r => panic!("{:x?}", r),
+    }
+    match ac.trans_l2p(MemAccessMode::Read, 0xcafedabe) {
+        Err(EmuException::CPUException(CPUException::PF(0xcafedabe, 0x9))) => {},
+        r => panic!("{:x?}", r),
+    }
+    match ac.trans_l2p(MemAccessMode::Read, 0xcafeeabe) {
+        Err(EmuException::CPUException(CPUException::PF(0xcafeeabe, 0x0))) => {},
+        r => panic!("{:x?}", r),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn page_permission_test() {
+    let hw = hardware::Hardware::new(0x5000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.pgmd = Some(super::PagingMode::Ia32e4Lv);
+    ac.core.msr.efer.NXE = 1;
+    ac.core.cregs.3.from_u64(0);
+    {
+        let mut mem = ac.mem.write().unwrap();
+
+        mem.write64(0, 0x1007);
+        mem.write64(0x1000, 0x2007);
+        mem.write64(0x2000, 0x3007);
+        mem.write64(0x3000, 0x8000_0000_0000_4003);
+        mem.write64(0x3008, 0x0000_0000_0000_4007);
+    }
+
+    match ac.trans_l2p(MemAccessMode::Exec, 0x0abc) {
+        Err(EmuException::CPUException(CPUException::PF(0x0abc, 0x11))) => {},
+        r => panic!("{:x?}", r),
+    }
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x0abc).unwrap(), 0x4abc);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Exec, 0x1abc).unwrap(), 0x4abc);
+
+    ac.core.cregs.4.SMEP = 1;
+    match ac.trans_l2p(MemAccessMode::Exec, 0x1abc) {
+        Err(EmuException::CPUException(CPUException::PF(0x1abc, 0x11))) => {},
+        r => panic!("{:x?}", r),
+    }
+
+    ac.core.cregs.4.SMAP = 1;
+    match ac.trans_l2p(MemAccessMode::Write, 0x1abc) {
+        Err(EmuException::CPUException(CPUException::PF(0x1abc, 0x3))) => {},
+        r => panic!("{:x?}", r),
+    }
+    ac.core.rflags.set_aligncheck(true);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Write, 0x1abc).unwrap(), 0x4abc);
+}
+
+#[cfg(test)]
+#[test]
+fn supervisor_access_test() {
+    use crate::hardware::processor::segment::SgDescCache;
+
+    let hw = hardware::Hardware::new(0x4000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.mode = super::CpuMode::Protected;
+    ac.pgmd = Some(super::PagingMode::Legacy);
+    ac.core.cregs.3.from_u64(0);
+    {
+        let mut mem = ac.mem.write().unwrap();
+
+        mem.write32(0, 0x1007);
+        mem.write32(0x1008, 0x2003);
+        mem.write32(0x100c, 0x3007);
+        mem.write64(0x2028, 0x00cff2000000ffff);
+    }
+    ac.set_gdtr(0x2000, 0x2f).unwrap();
+    ac.set_sgreg(SgReg::CS, 0x1b, SgDescCache { Type: 0xb, DPL: 3, P: 1, DB: 1, ..Default::default() }).unwrap();
+
+    match ac.trans_l2p(MemAccessMode::Read, 0x2028) {
+        Err(EmuException::CPUException(CPUException::PF(0x2028, 0x5))) => {},
+        r => panic!("{:x?}", r),
+    }
+    ac.load_segment(SgReg::DS, 0x2b).unwrap();
+    assert_eq!(ac.get_sgreg(SgReg::DS).unwrap().0, 0x2b);
+
+    ac.core.cregs.4.SMAP = 1;
+    ac.core.rflags.set_aligncheck(true);
+    let mut v = 0u32;
+    assert!(ac.read_l(&mut v as *mut u32 as *mut _, 0x3000, 4).is_ok());
+    match ac.read_l_sys(&mut v as *mut u32 as *mut _, 0x3000, 4) {
+        Err(EmuException::CPUException(CPUException::PF(0x3000, 0x1))) => {},
+        r => panic!("{:x?}", r),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tlb_test() {
+    let hw = hardware::Hardware::new(0x3000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.pgmd = Some(super::PagingMode::Legacy);
+    ac.core.cregs.3.from_u64(0);
+    ac.core.cregs.4.PGE = 1;
+    {
+        let mut mem = ac.mem.write().unwrap();
+        mem.write32(0, 0x1003);
+        mem.write32(0x1000 + 4*1, 0x2103);
+        mem.write32(0x1000 + 4*2, 0x2003);
+    }
+
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x1abc).unwrap(), 0x2abc);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x2abc).unwrap(), 0x2abc);
+    {
+        let mut mem = ac.mem.write().unwrap();
+        mem.write32(0x1000 + 4*1, 0x1103);
+        mem.write32(0x1000 + 4*2, 0x1003);
+    }
+
+    CregAccess::<u32>::set_creg(&mut ac, 3, 0).unwrap();
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x1abc).unwrap(), 0x2abc);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x2abc).unwrap(), 0x1abc);
+
+    ac.invalidate_page(SgReg::DS, 0x1abc).unwrap();
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x1abc).unwrap(), 0x1abc);
+
+    ac.core.cregs.4.PCIDE = 1;
+    ac.core.cregs.3.from_u64(0x1);
+    ac.mem.write().unwrap().write32(0x1000 + 4*2, 0x2003);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x2abc).unwrap(), 0x2abc);
+
+    CregAccess::<u64>::set_creg(&mut ac, 3, (1<<63) + 1).unwrap();
+    ac.mem.write().unwrap().write32(0x1000 + 4*2, 0x1003);
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x2abc).unwrap(), 0x2abc);
+
+    ac.invalidate_pcid(1, 1, 0).unwrap();
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x2abc).unwrap(), 0x1abc);
+
+    ac.mem.write().unwrap().write32(0x1000 + 4*2, 0x2003);
+    CregAccess::<u64>::set_creg(&mut ac, 3, 1).unwrap();
+    assert_eq!(ac.trans_l2p(MemAccessMode::Read, 0x2abc).unwrap(), 0x2abc);
 }
 
 #[cfg(test)]
@@ -771,4 +1279,124 @@ fn real_mem_test() {
 
     ac.set_data32((SgReg::DS, 0x1010), 0xdeadbeef).unwrap();
     assert_eq!(ac.get_data8((SgReg::DS, 0x1010)).unwrap(), 0);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+#[test]
+fn watchpoint_test() {
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.watchpoints.push((0x12, WatchKind::Write));
+    ac.watchpoints.push((0x20, WatchKind::ReadWrite));
+
+    ac.get_data32((SgReg::DS, 0x10)).unwrap();
+    assert_eq!(ac.take_watched(), None);
+    ac.set_data32((SgReg::DS, 0x10), 0xdeadbeef).unwrap();
+    assert_eq!(ac.take_watched(), Some((0x12, true)));
+    ac.get_data8((SgReg::DS, 0x20)).unwrap();
+    assert_eq!(ac.take_watched(), Some((0x20, false)));
+    ac.set_data16((SgReg::DS, 0x22), 0).unwrap();
+    assert_eq!(ac.take_watched(), None);
+}
+
+#[cfg(test)]
+#[test]
+fn segment_check_test() {
+    use crate::hardware::processor::segment::SgDescCache;
+
+    let hw = hardware::Hardware::new(0x2000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+    ac.mode = super::CpuMode::Protected;
+
+    let stack = SgDescCache { base: 0, limit: 0xfff, Type: 0x7, DPL: 0, P: 1, AVL: 0, L: 0, DB: 1, G: 0 };
+    ac.set_sgreg(SgReg::SS, 0x10, stack).unwrap();
+    ac.set_data32((SgReg::SS, 0x1ff0), 0xdeadbeef).unwrap();
+    match ac.get_data32((SgReg::SS, 0xff0)) {
+        Err(EmuException::CPUException(CPUException::SS(None))) => {},
+        _ => panic!("expected #SS"),
+    }
+
+    let rodata = SgDescCache { Type: 0x1, ..Default::default() };
+    ac.set_sgreg(SgReg::DS, 0x18, rodata).unwrap();
+    assert_eq!(ac.get_data32((SgReg::DS, 0x1ff0)).unwrap(), 0xdeadbeef);
+    assert!(ac.set_data32((SgReg::DS, 0x10), 0).is_err());
+    assert!(ac.get_data8((SgReg::DS, 0x10000)).is_err());
+    assert!(ac.get_data8((SgReg::DS, 0xffff)).is_ok());
+    assert!(ac.get_data32((SgReg::DS, 0xfffe)).is_err());
+
+    let stack16 = SgDescCache { DB: 0, ..stack };
+    ac.set_sgreg(SgReg::SS, 0x10, stack16).unwrap();
+    assert!(ac.get_data16((SgReg::SS, 0xfffe)).is_ok());
+    match ac.get_data32((SgReg::SS, 0xfffe)) {
+        Err(EmuException::CPUException(CPUException::SS(None))) => {},
+        _ => panic!("expected #SS"),
+    }
+    ac.set_sgreg(SgReg::SS, 0x10, stack).unwrap();
+
+    ac.set_sgreg(SgReg::FS, 0, SgDescCache { P: 0, ..Default::default() }).unwrap();
+    match ac.get_data8((SgReg::FS, 0x10)) {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        _ => panic!("expected #GP"),
+    }
+
+    let xonly = SgDescCache { Type: 0x8, ..Default::default() };
+    ac.set_sgreg(SgReg::ES, 0x20, xonly).unwrap();
+    assert!(ac.get_data8((SgReg::ES, 0x10)).is_err());
+
+    ac.mode = super::CpuMode::Long;
+    ac.oasz.ad = super::AcsSize::BIT64;
+    assert_eq!(ac.get_data32((SgReg::DS, 0x1ff0)).unwrap(), 0xdeadbeef);
+    match ac.get_data8((SgReg::SS, 0x0000800000000000)) {
+        Err(EmuException::CPUException(CPUException::SS(None))) => {},
+        _ => panic!("expected #SS"),
+    }
+    assert!(ac.get_data8((SgReg::DS, 0xffff800000000000)).is_ok());
+}
+
+#[cfg(test)]
+#[test]
+fn page_cross_test() {
+    let hw = hardware::Hardware::new(0x5000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.pgmd = Some(super::PagingMode::Legacy);
+    ac.core.cregs.3.from_u64(0);
+    {
+        let mut mem = ac.mem.write().unwrap();
+
+        let mut pde: PDE = Default::default();
+        pde.P = true;
+        pde.RW = true;
+        pde.pt_base = 0x1;
+        mem.write32(0, u64::from_be_bytes(pde.pack().unwrap()) as u32);
+
+        for (idx, frame) in [(2, 0x4), (3, 0x2)].iter() {
+            let mut pte: PTE = Default::default();
+            pte.P = true;
+            pte.RW = true;
+            pte.page_base = *frame;
+            mem.write32(0x1000 + 4*idx, u64::from_be_bytes(pte.pack().unwrap()) as u32);
+        }
+    }
+
+    ac.set_data64((SgReg::DS, 0x2ffc), 0x1122334455667788).unwrap();
+    assert_eq!(ac.mem.read().unwrap().read32(0x4ffc), 0x55667788);
+    assert_eq!(ac.mem.read().unwrap().read32(0x2000), 0x11223344);
+    assert_eq!(ac.get_data32((SgReg::DS, 0x2ffe)).unwrap(), 0x33445566);
+
+    ac.load_segment(SgReg::CS, 0).unwrap();
+    ac.set_ip(0x2fff).unwrap();
+    assert_eq!(ac.get_code16(0).unwrap(), 0x4455);
+    assert!(ac.get_data16((SgReg::DS, 0x3fff)).is_err());
+
+    let mut raw = [0u8; 8];
+    assert_eq!(ac.read_l(raw.as_mut_ptr() as *mut _, 0x2ffc, 8).unwrap(), 8);
+    assert_eq!(u64::from_le_bytes(raw), 0x1122334455667788);
+    let raw = 0x8877665544332211u64.to_le_bytes();
+    assert_eq!(ac.write_l(0x2ffa, raw.as_ptr() as *const _, 8).unwrap(), 8);
+    assert_eq!(ac.mem.read().unwrap().read32(0x4ffc), 0x66554433);
+    assert_eq!(ac.mem.read().unwrap().read16(0x2000), 0x8877);
+}
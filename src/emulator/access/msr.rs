@@ -6,14 +6,19 @@ use super::register::*;
 
 #[derive(TryFromPrimitive)] #[repr(u32)]
 enum MSRAddress {
-    IA32_EFER    = 0xc0000080,
-    STAR         = 0xc0000081,
-    CSTAR        = 0xc0000082,
-    LSTAR        = 0xc0000083,
-    FMASK        = 0xc0000084,
-    FSBase       = 0xc0000100,
-    GSBase       = 0xc0000101,
-    KernelGSBase = 0xc0000102,
+    IA32_TSC          = 0x00000010,
+    IA32_SYSENTER_CS  = 0x00000174,
+    IA32_SYSENTER_ESP = 0x00000175,
+    IA32_SYSENTER_EIP = 0x00000176,
+    IA32_EFER         = 0xc0000080,
+    STAR              = 0xc0000081,
+    CSTAR             = 0xc0000082,
+    LSTAR             = 0xc0000083,
+    FMASK             = 0xc0000084,
+    FSBase            = 0xc0000100,
+    GSBase            = 0xc0000101,
+    KernelGSBase      = 0xc0000102,
+    IA32_TSC_AUX      = 0xc0000103,
 }
 
 impl super::Access {
@@ -35,14 +40,19 @@ impl super::Access {
     fn get_msr(&self, addr: u32) -> Option<&dyn MSRAccess> {
         if let Ok(ad) = MSRAddress::try_from(addr) {
             let v: &dyn MSRAccess = match ad {
-                MSRAddress::IA32_EFER    => &self.core.msr.efer,
-                MSRAddress::STAR         => &self.core.msr.star,
-                MSRAddress::CSTAR        => &self.core.msr.cstar,
-                MSRAddress::LSTAR        => &self.core.msr.lstar,
-                MSRAddress::FMASK        => &self.core.msr.fmask,
-                MSRAddress::FSBase       => &self.core.sgregs.get(SgReg::FS).cache,
-                MSRAddress::GSBase       => &self.core.sgregs.get(SgReg::GS).cache,
-                MSRAddress::KernelGSBase => &self.core.sgregs.get(SgReg::KernelGS).cache,
+                MSRAddress::IA32_TSC          => &self.core.tsc,
+                MSRAddress::IA32_SYSENTER_CS  => &self.core.msr.sysenter_cs,
+                MSRAddress::IA32_SYSENTER_ESP => &self.core.msr.sysenter_esp,
+                MSRAddress::IA32_SYSENTER_EIP => &self.core.msr.sysenter_eip,
+                MSRAddress::IA32_EFER         => &self.core.msr.efer,
+                MSRAddress::STAR              => &self.core.msr.star,
+                MSRAddress::CSTAR             => &self.core.msr.cstar,
+                MSRAddress::LSTAR             => &self.core.msr.lstar,
+                MSRAddress::FMASK             => &self.core.msr.fmask,
+                MSRAddress::FSBase            => &self.core.sgregs.get(SgReg::FS).cache,
+                MSRAddress::GSBase            => &self.core.sgregs.get(SgReg::GS).cache,
+                MSRAddress::KernelGSBase      => &self.core.sgregs.get(SgReg::KernelGS).cache,
+                MSRAddress::IA32_TSC_AUX      => &self.core.msr.tsc_aux,
             };
             return Some(v);
         }
@@ -52,14 +62,19 @@ impl super::Access {
     fn get_mut_msr(&mut self, addr: u32) -> Option<&mut dyn MSRAccess> {
         if let Ok(ad) = MSRAddress::try_from(addr) {
             let v: &mut dyn MSRAccess = match ad {
-                MSRAddress::IA32_EFER    => &mut self.core.msr.efer,
-                MSRAddress::STAR         => &mut self.core.msr.star,
-                MSRAddress::CSTAR        => &mut self.core.msr.cstar,
-                MSRAddress::LSTAR        => &mut self.core.msr.lstar,
-                MSRAddress::FMASK        => &mut self.core.msr.fmask,
-                MSRAddress::FSBase       => &mut self.core.sgregs.get_mut(SgReg::FS).cache,
-                MSRAddress::GSBase       => &mut self.core.sgregs.get_mut(SgReg::GS).cache,
-                MSRAddress::KernelGSBase => &mut self.core.sgregs.get_mut(SgReg::KernelGS).cache,
+                MSRAddress::IA32_TSC          => &mut self.core.tsc,
+                MSRAddress::IA32_SYSENTER_CS  => &mut self.core.msr.sysenter_cs,
+                MSRAddress::IA32_SYSENTER_ESP => &mut self.core.msr.sysenter_esp,
+                MSRAddress::IA32_SYSENTER_EIP => &mut self.core.msr.sysenter_eip,
+                MSRAddress::IA32_EFER         => &mut self.core.msr.efer,
+                MSRAddress::STAR              => &mut self.core.msr.star,
+                MSRAddress::CSTAR             => &mut self.core.msr.cstar,
+                MSRAddress::LSTAR             => &mut self.core.msr.lstar,
+                MSRAddress::FMASK             => &mut self.core.msr.fmask,
+                MSRAddress::FSBase            => &mut self.core.sgregs.get_mut(SgReg::FS).cache,
+                MSRAddress::GSBase            => &mut self.core.sgregs.get_mut(SgReg::GS).cache,
+                MSRAddress::KernelGSBase      => &mut self.core.sgregs.get_mut(SgReg::KernelGS).cache,
+                MSRAddress::IA32_TSC_AUX      => &mut self.core.msr.tsc_aux,
             };
             return Some(v);
         }
@@ -79,6 +94,10 @@ fn access_msr_test() {
 
     ac.write_msr(0xc0000100, 0xdeadbeef).unwrap();
     assert_eq!(ac.core.sgregs.get(SgReg::FS).cache.base, 0xdeadbeef);
+
+    ac.write_msr(MSRAddress::IA32_TSC as u32, 0x1234).unwrap();
+    ac.core.tsc.tick();
+    assert_eq!(ac.read_msr(0x10).unwrap(), 0x1235);
 }
 
 #[cfg(test)]
@@ -89,5 +108,5 @@ fn access_msr_test_panic() {
     let (dev, _) = device::Device::new();
     let mut ac = access::Access::new(hw, dev);
 
-    ac.write_msr(0xc0000103, 0xdeadbeef).unwrap();
+    ac.write_msr(0xc0000104, 0xdeadbeef).unwrap();
 }
\ No newline at end of file
@@ -2,53 +2,107 @@ use crate::emulator::*;
 
 impl super::Access {
     pub fn in_8(&self, addr: u16) -> Result<u8, EmuException> {
-        if let access::CpuMode::Long | access::CpuMode::Protected = self.mode {
-            if self.get_cpl()? > self.core.rflags.get_iopl() { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        }
+        self.check_io(addr, 1)?;
         let mut data: [u8; 1] = [0; 1];
         self.dev.in_portio(addr, &mut data);
         Ok(data[0])
     }
 
     pub fn out_8(&mut self, addr: u16, v: u8) -> Result<(), EmuException> {
-        if let access::CpuMode::Long | access::CpuMode::Protected = self.mode {
-            if self.get_cpl()? > self.core.rflags.get_iopl() { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        }
+        self.check_io(addr, 1)?;
         self.dev.out_portio(addr, &[v]);
         Ok(())
     }
 
     pub fn in_16(&self, addr: u16) -> Result<u16, EmuException> {
-        if let access::CpuMode::Long | access::CpuMode::Protected = self.mode {
-            if self.get_cpl()? > self.core.rflags.get_iopl() { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        }
+        self.check_io(addr, 2)?;
         let mut data: [u8; 2] = [0; 2];
         self.dev.in_portio(addr, &mut data);
         Ok(u16::from_le_bytes(data))
     }
 
     pub fn out_16(&mut self, addr: u16, v: u16) -> Result<(), EmuException> {
-        if let access::CpuMode::Long | access::CpuMode::Protected = self.mode {
-            if self.get_cpl()? > self.core.rflags.get_iopl() { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        }
+        self.check_io(addr, 2)?;
         self.dev.out_portio(addr, &v.to_le_bytes());
         Ok(())
     }
 
     pub fn in_32(&self, addr: u16) -> Result<u32, EmuException> {
-        if let access::CpuMode::Long | access::CpuMode::Protected = self.mode {
-            if self.get_cpl()? > self.core.rflags.get_iopl() { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        }
+        self.check_io(addr, 4)?;
         let mut data: [u8; 4] = [0; 4];
         self.dev.in_portio(addr, &mut data);
         Ok(u32::from_le_bytes(data))
     }
 
     pub fn out_32(&mut self, addr: u16, v: u32) -> Result<(), EmuException> {
-        if let access::CpuMode::Long | access::CpuMode::Protected = self.mode {
-            if self.get_cpl()? > self.core.rflags.get_iopl() { return Err(EmuException::CPUException(CPUException::GP(None))) }
-        }
+        self.check_io(addr, 4)?;
         self.dev.out_portio(addr, &v.to_le_bytes());
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn check_io(&self, addr: u16, size: u16) -> Result<(), EmuException> {
+        let privileged = match self.mode {
+            access::CpuMode::Real => return Ok(()),
+            access::CpuMode::V8086 => false,
+            _ => self.get_cpl()? <= self.core.rflags.get_iopl(),
+        };
+
+        if !privileged && !self.test_io_bitmap(addr, size)? {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        Ok(())
+    }
+
+    fn test_io_bitmap(&self, addr: u16, size: u16) -> Result<bool, EmuException> {
+        let tss = self.core.dtregs.tr.cache;
+        if tss.limit < 0x67 { return Ok(false); }
+
+        let mut io_base: u16 = 0;
+        self.read_l_sys(&mut io_base as *mut u16 as *mut _, tss.base + 0x66, std::mem::size_of_val(&io_base))?;
+        let ofs = io_base as u64 + (addr >> 3) as u64;
+        if ofs + 1 > tss.limit as u64 { return Ok(false); }
+
+        let mut map: u16 = 0;
+        self.read_l_sys(&mut map as *mut u16 as *mut _, tss.base + ofs, std::mem::size_of_val(&map))?;
+        let mask = ((1 << size) - 1) << (addr & 7);
+        Ok(map & mask == 0)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn io_bitmap_test() {
+    use crate::hardware;
+    use crate::device;
+    use super::register::*;
+    use crate::hardware::processor::segment::SgDescCache;
+
+    let hw = hardware::Hardware::new(0x3000);
+    let (dev, _) = device::Device::new();
+    let mut ac = super::Access::new(hw, dev);
+
+    ac.core.dtregs.tr.cache.base = 0x1000;
+    ac.core.dtregs.tr.cache.limit = 0x68 + 0x10;
+    ac.set_data16((SgReg::DS, 0x1066), 0x68).unwrap();
+    ac.set_data8((SgReg::DS, 0x1068 + 4), 0x01).unwrap();
+    ac.check_io(0x20, 4).unwrap();
+
+    ac.mode = access::CpuMode::V8086;
+    assert!(ac.check_io(0x20, 1).is_err());
+    ac.check_io(0x21, 1).unwrap();
+    assert!(ac.check_io(0x1f, 2).is_err());
+    assert!(ac.check_io(0x80, 1).is_err());
+
+    ac.core.rflags.set_iopl(3);
+    assert!(ac.check_io(0x20, 1).is_err());
+
+    ac.mem.write().unwrap().write32(0x2000, 0x83);
+    ac.core.cregs.3.from_u64(0x2000);
+    ac.core.cregs.4.PSE = 1;
+    ac.pgmd = Some(super::PagingMode::Legacy);
+    ac.mode = access::CpuMode::Protected;
+    ac.core.rflags.set_iopl(0);
+    ac.set_sgreg(SgReg::CS, 0x1b, SgDescCache { Type: 0xb, DPL: 3, P: 1, DB: 1, ..Default::default() }).unwrap();
+    ac.check_io(0x21, 1).unwrap();
+    assert!(ac.check_io(0x20, 1).is_err());
+}
@@ -76,7 +76,7 @@ impl CregAccess<u32> for super::Access {
     fn set_creg(&mut self, r: usize, v: u32) -> Result<(), EmuException> {
         if let Some(cr) = self.core.cregs.get_mut(r) {
             cr.from_u32(v);
-            if r == 3 { self.tlb.borrow_mut().flush(); }
+            self.flush_tlb_creg(r, v as u64);
             Ok(())
         } else { Err(EmuException::UnexpectedError) }
     }
@@ -90,13 +90,22 @@ impl CregAccess<u64> for super::Access {
     fn set_creg(&mut self, r: usize, v: u64) -> Result<(), EmuException> {
         if let Some(cr) = self.core.cregs.get_mut(r) {
             cr.from_u64(v);
-            if r == 3 { self.tlb.borrow_mut().flush(); }
+            self.flush_tlb_creg(r, v);
             Ok(())
         } else { Err(EmuException::UnexpectedError) }
     }
 }
 
 impl super::Access {
+    fn flush_tlb_creg(&mut self, r: usize, v: u64) -> () {
+        match r {
+            0 | 4 => self.tlb.borrow_mut().flush(),
+            3 if self.core.cregs.4.PCIDE == 0 => self.tlb.borrow_mut().flush_nonglobal(Some(0)),
+            3 if v >> 63 == 0 => self.tlb.borrow_mut().flush_nonglobal(Some(self.get_pcid())),
+            _ => {},
+        }
+    }
+
     pub fn get_ip(&self) -> Result<u64, EmuException> {
         let ip = &self.core.ip;
         Ok(match self.oasz.ad {
@@ -146,6 +155,27 @@ impl super::Access {
     pub fn get_ldtr(&self) -> Result<u16, EmuException> { Ok(self.core.dtregs.ldtr.selector) }
     pub fn get_tr(&self) -> Result<u16, EmuException> { Ok(self.core.dtregs.tr.selector) }
 
+    pub fn get_dreg(&self, r: usize) -> Result<u64, EmuException> {
+        let dregs = &self.core.dregs;
+        match r {
+            0..=3 => Ok(dregs.dr[r]),
+            6 => Ok(dregs.dr6.to_u64()),
+            7 => Ok(dregs.dr7.to_u64()),
+            _ => Err(EmuException::UnexpectedError),
+        }
+    }
+
+    pub fn set_dreg(&mut self, r: usize, v: u64) -> Result<(), EmuException> {
+        let dregs = &mut self.core.dregs;
+        match r {
+            0..=3 => dregs.dr[r] = v,
+            6 => dregs.dr6.from_u64(v),
+            7 => dregs.dr7.from_u64(v),
+            _ => { return Err(EmuException::UnexpectedError); },
+        }
+        Ok(())
+    }
+
     pub fn set_gdtr(&mut self, base: u64, limit: u16) -> Result<(), EmuException> {
         let gdtr = &mut self.core.dtregs.gdtr;
         gdtr.base = base;
@@ -179,6 +209,7 @@ impl super::Access {
             if tssd.P == 0 { return Err(EmuException::CPUException(CPUException::NP)); }
 
             let tr = &mut self.core.dtregs.tr;
+            tr.attr        = tssd.attr() | 2;
             tr.cache       = DescTbl::from(tssd);
             tr.selector    = sel;
             self.set_busy_tssdesc(sel, true)?;
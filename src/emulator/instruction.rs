@@ -14,7 +14,11 @@ impl Instruction {
 
     pub fn fetch_exec(&mut self, ac: &mut access::Access) -> Result<(), EmuException> {
         let mut parse: parse::ParseInstr = Default::default();
+        let ip = ac.get_ip()?;
+        let resume = ac.core.rflags.is_resume();
 
+        ac.take_watch_hits();
+        ac.check_code_breakpoint()?;
         parse.parse_prefix(ac)?;
         let size = Instruction::opad_size(&ac.oasz, &parse.prefix);
 
@@ -23,8 +27,29 @@ impl Instruction {
         parse.parse_oprand(ac, op.flag(parse.instr.opcode), size.ad)?;
 
         ac.update_ip(parse.instr.len as i64)?;
-        op.exec(&mut exec::Exec::new(ac, &parse))?;
-        if ac.core.rflags.is_trap() { Err(EmuException::CPUException(CPUException::DB)) } else { Ok(()) }
+        let mut exe = exec::Exec::new(ac, &parse);
+
+        let bus = exe.ac.bus.clone();
+        let _lock = if parse.is_locked() && !exe.is_memio_operand() { Some(bus.lock().unwrap()) } else { None };
+
+        match op.exec(&mut exe) {
+            Err(EmuException::CPUException(e)) if e.is_fault() => {
+                ac.set_ip(ip)?;
+                ac.core.rflags.set_resume(true);
+                return Err(EmuException::CPUException(e));
+            },
+            r => r?,
+        }
+        ac.core.tsc.tick();
+        if resume { ac.core.rflags.set_resume(false); }
+
+        let (hits, trap) = (ac.take_watch_hits(), ac.core.rflags.is_trap());
+        if hits != 0 || trap {
+            let dr6 = &mut ac.core.dregs.dr6;
+            dr6.set_hits(hits);
+            dr6.set_bs(trap);
+            Err(EmuException::CPUException(CPUException::DB))
+        } else { Ok(()) }
     }
 
     pub fn opad_size(size: &access::OpAdSize, pdata: &parse::PrefixData) -> access::OpAdSize {
@@ -1,9 +1,18 @@
 mod regmem;
 mod basic;
 mod flag;
+mod bit;
+mod atomic;
+mod bcd;
 mod desc;
+mod system;
+mod v8086;
 mod string;
 mod misc;
+mod fpu;
+mod sse;
+mod avx;
+mod xsave;
 
 use super::parse;
 use crate::emulator::*;
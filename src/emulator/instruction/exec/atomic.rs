@@ -0,0 +1,97 @@
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+
+impl<'a> super::Exec<'a> {
+    pub fn is_memio_operand(&mut self) -> bool {
+        match self.get_m() {
+            Ok((sg, adr)) => matches!(self.ac.addr_v2p(sg, adr), Ok(paddr) if self.ac.is_memio(paddr)),
+            Err(_) => false,
+        }
+    }
+
+    pub fn cmpxchg8b(&mut self) -> Result<(), EmuException> {
+        if self.is_rexw() { return self.cmpxchg16b(); }
+
+        let m = self.get_m()?;
+        let v = self.ac.get_data64(m)?;
+        let cmp = ((self.get_edx()? as u64) << 32) + self.get_eax()? as u64;
+        debug!("cmpxchg8b: {:016x}, {:016x}", v, cmp);
+
+        if v == cmp {
+            let src = ((self.ac.get_gpreg(GpReg32::ECX)? as u64) << 32) + self.ac.get_gpreg(GpReg32::EBX)? as u64;
+            self.ac.set_data64(m, src)?;
+        } else {
+            self.ac.set_data64(m, v)?;
+            self.set_edx((v >> 32) as u32)?;
+            self.set_eax(v as u32)?;
+        }
+        self.ac.core.rflags.set_zero(v == cmp);
+        Ok(())
+    }
+
+    fn cmpxchg16b(&mut self) -> Result<(), EmuException> {
+        let (sg, adr) = self.get_m()?;
+        if self.ac.addr_v2l(sg, adr)? & 0xf != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let v = [self.ac.get_data64((sg, adr))?, self.ac.get_data64((sg, adr+8))?];
+        let cmp = [self.get_rax()?, self.get_rdx()?];
+        debug!("cmpxchg16b: {:016x}{:016x}, {:016x}{:016x}", v[1], v[0], cmp[1], cmp[0]);
+
+        let src = if v == cmp {
+            [self.ac.get_gpreg(GpReg64::RBX)?, self.ac.get_gpreg(GpReg64::RCX)?]
+        } else {
+            self.set_rax(v[0])?;
+            self.set_rdx(v[1])?;
+            v
+        };
+        self.ac.set_data64((sg, adr), src[0])?;
+        self.ac.set_data64((sg, adr+8), src[1])?;
+        self.ac.core.rflags.set_zero(v == cmp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn cmpxchg_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.rm = 6;
+    parse.instr.disp = 0x108;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_data64((SgReg::DS, 0x108), 0x1111_2222_3333_4444).unwrap();
+    exe.set_eax(0x3333_4444).unwrap();
+    exe.set_edx(0).unwrap();
+    exe.cmpxchg8b().unwrap();
+    assert!(!exe.ac.core.rflags.is_zero());
+    assert_eq!(exe.get_edx().unwrap(), 0x1111_2222);
+
+    exe.ac.set_gpreg(GpReg32::EBX, 0xdead).unwrap();
+    exe.ac.set_gpreg(GpReg32::ECX, 0xbeef).unwrap();
+    exe.cmpxchg8b().unwrap();
+    assert!(exe.ac.core.rflags.is_zero());
+    assert_eq!(exe.ac.get_data64((SgReg::DS, 0x108)).unwrap(), 0xbeef_0000_dead);
+
+    parse.prefix.rex = Some(parse::Rex { w: 1, ..Default::default() });
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.cmpxchg8b() {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        _ => panic!("expected #GP"),
+    }
+
+    let (sel, mut cache) = exe.ac.get_sgreg(SgReg::DS).unwrap();
+    cache.base = 8;
+    exe.ac.set_sgreg(SgReg::DS, sel, cache).unwrap();
+    exe.cmpxchg8b().unwrap();
+    assert_eq!(exe.get_rax().unwrap(), 0);
+    assert!(!exe.is_memio_operand());
+}
@@ -0,0 +1,329 @@
+use crate::emulator::*;
+use crate::hardware::processor::fpu::FPUException;
+use crate::hardware::processor::sse::XCR0;
+use super::sse::*;
+
+fn is_lig(op: u8, pfx: u8) -> bool {
+    match pfx {
+        0xf3 | 0xf2 => matches!(op, 0x10 | 0x11 | 0x2a | 0x2c | 0x2d | 0x51..=0x53 | 0x58..=0x5a | 0x5c..=0x5f | 0xc2),
+        _ => matches!(op, 0x2e | 0x2f),
+    }
+}
+
+fn lane256(v: [u128; 2], i: usize, bits: usize) -> u64 {
+    let n = 128 / bits;
+    lane(v[i / n], i % n, bits)
+}
+
+fn map256(bits: usize, mut f: impl FnMut(usize) -> u64) -> [u128; 2] {
+    let n = 128 / bits;
+    [map(0, bits, |i, _| f(i)), map(0, bits, |i, _| f(i + n))]
+}
+
+fn halves(a: [u128; 2], b: [u128; 2], mut f: impl FnMut(u128, u128) -> Option<u128>) -> Option<[u128; 2]> {
+    Some([f(a[0], b[0])?, f(a[1], b[1])?])
+}
+
+fn widen(v: u128, mut f: impl FnMut(u64) -> u64) -> [u128; 2] {
+    [map(0, 64, |i, _| f(lane(v, i, 32))), map(0, 64, |i, _| f(lane(v, i + 2, 32)))]
+}
+
+fn narrow(v: [u128; 2], mut f: impl FnMut(u64) -> u64) -> u128 {
+    map(0, 32, |i, _| if i < 4 { f(lane256(v, i, 64)) } else { 0 })
+}
+
+fn alignr(a: u128, b: u128, imm: usize) -> u128 {
+    match imm {
+        0 => b,
+        1..=15 => b >> (imm * 8) | a << (128 - imm * 8),
+        16..=31 => a >> ((imm - 16) * 8),
+        _ => 0,
+    }
+}
+
+impl<'a> super::Exec<'a> {
+    pub fn avx(&mut self) -> Result<(), EmuException> {
+        let vex = self.pdata.vex.ok_or(EmuException::CPUException(CPUException::UD))?;
+        self.avx_check()?;
+
+        let (op, pfx) = (self.idata.opcode as u8, self.simd_prefix());
+        match self.idata.opcode >> 8 {
+            1 if op == 0x77 => self.vzero(vex.l == 1),
+            1 if vex.l == 0 || is_lig(op, pfx) => self.sse_op(),
+            1 => self.avx256(op, pfx),
+            _ if pfx != 0x66 => Err(EmuException::CPUException(CPUException::UD)),
+            2 => self.avx_0f38(op, vex.l == 1),
+            _ => self.avx_0f3a(op, vex.l == 1),
+        }
+    }
+
+    fn avx_check(&self) -> Result<(), EmuException> {
+        let xcr0 = self.ac.core.sse.xcr0;
+        if self.ac.core.cregs.4.OSXSAVE == 0 || !xcr0.contains(XCR0::SSE | XCR0::AVX) {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.core.cregs.0.TS == 1 {
+            return Err(EmuException::CPUException(CPUException::NM));
+        }
+        Ok(())
+    }
+
+    fn vzero(&mut self, all: bool) -> Result<(), EmuException> {
+        for i in 0..self.xmm_count() {
+            let v = if all { 0 } else { self.ac.core.sse.get_xmm(i) };
+            self.ac.core.sse.set_ymm(i, [v, 0]);
+        }
+        Ok(())
+    }
+
+    fn avx256(&mut self, op: u8, pfx: u8) -> Result<(), EmuException> {
+        let reg = self.idata.modrm.mod_ == 3;
+        let align = matches!(op, 0x28 | 0x29 | 0x2b | 0xe7) || (matches!(op, 0x6f | 0x7f) && pfx == 0x66);
+        let rc = self.ac.core.sse.mxcsr.RC;
+        let mut ex = FPUException::empty();
+
+        let r = match (op, pfx) {
+            (0x10, 0) | (0x10, 0x66) | (0x28, 0) | (0x28, 0x66) | (0x6f, 0x66) | (0x6f, 0xf3) => self.get_ymm_rm(align)?,
+            (0x11, 0) | (0x11, 0x66) | (0x29, 0) | (0x29, 0x66) | (0x7f, 0x66) | (0x7f, 0xf3) => {
+                let v = self.get_ymm_r();
+                return self.set_ymm_rm(v, align);
+            },
+            (0x2b, 0) | (0x2b, 0x66) | (0xe7, 0x66) if !reg => {
+                let v = self.get_ymm_r();
+                return self.set_ymm_rm(v, align);
+            },
+            (0x50, 0) | (0x50, 0x66) if reg => {
+                let (v, bits) = (self.get_ymm_rm(false)?, if pfx == 0 { 32 } else { 64 });
+                return self.set_r32((0..256/bits).fold(0, |m, i| m | ((lane256(v, i, bits) >> (bits - 1)) as u32) << i));
+            },
+            (0xd7, 0x66) if reg => {
+                let v = self.get_ymm_rm(false)?;
+                return self.set_r32((0..32).fold(0, |m, i| m | ((lane256(v, i, 8) >> 7) as u32) << i));
+            },
+            (0x5a, 0) => widen(self.get_xmm_rm128(false)?, |x| fp_convert(x, 32, 64, &mut ex)),
+            (0x5a, 0x66) => [narrow(self.get_ymm_rm(false)?, |x| fp_convert(x, 64, 32, &mut ex)), 0],
+            (0xe6, 0xf3) => widen(self.get_xmm_rm128(false)?, |x| from_f(sext(x, 32) as f64, 64)),
+            (0xe6, 0x66) | (0xe6, 0xf2) => {
+                let rc = if pfx == 0xf2 { Some(rc) } else { None };
+                [narrow(self.get_ymm_rm(false)?, |x| fp_to_int(to_f(x, 64), 32, rc, &mut ex)), 0]
+            },
+            (0x5b, 0) | (0x5b, 0x66) | (0x5b, 0xf3) => {
+                let (b, rc) = (self.get_ymm_rm(false)?, if pfx == 0x66 { Some(rc) } else { None });
+                let mut f = |v| map(v, 32, |_, x| {
+                    if pfx == 0 { from_f(sext(x, 32) as f64, 32) } else { fp_to_int(to_f(x, 32), 32, rc, &mut ex) }
+                });
+                [f(b[0]), f(b[1])]
+            },
+            (0x71..=0x73, 0x66) if reg => {
+                let (kind, cnt) = (self.idata.modrm.reg, self.get_imm8()? as u64);
+                let v = self.get_ymm_rm(false)?;
+                let r = halves(v, v, |x, _| shift_imm(op, kind, x, cnt)).ok_or(EmuException::CPUException(CPUException::UD))?;
+                let n = self.pdata.vex.map_or(0, |vex| vex.vvvv as usize);
+                self.ac.core.sse.set_ymm(n, r);
+                return Ok(());
+            },
+            (0xd1..=0xd3, 0x66) | (0xe1, 0x66) | (0xe2, 0x66) | (0xf1..=0xf3, 0x66) => {
+                let (a, cnt) = (self.get_ymm_v(), self.get_xmm_rm128(false)?);
+                halves(a, a, |x, _| packed_int(op, x, cnt)).ok_or(EmuException::CPUException(CPUException::UD))?
+            },
+            _ if is_packed(op, pfx) => {
+                let (a, b) = (self.get_ymm_v(), self.get_ymm_rm(false)?);
+                let imm = match op {
+                    0x70 | 0xc6 => self.get_imm8()?,
+                    0xc2 => self.get_imm8()? & self.cmp_mask(),
+                    _ => 0,
+                };
+                halves(a, b, |x, y| packed_op(op, pfx, x, y, imm, &mut ex)).ok_or(EmuException::CPUException(CPUException::UD))?
+            },
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        };
+
+        if !matches!(op, 0x52 | 0x53) {
+            self.sse_raise(ex)?;
+        }
+        self.set_ymm_r(r);
+        Ok(())
+    }
+
+    fn avx_0f38(&mut self, op: u8, l: bool) -> Result<(), EmuException> {
+        let reg = self.idata.modrm.mod_ == 3;
+
+        let r = match op {
+            0x00 => {
+                let (a, b) = (self.get_vec_v(), self.get_vec_rm()?);
+                [0, 1].iter().fold([0; 2], |mut r, &h| {
+                    r[h] = map(0, 8, |i, _| {
+                        let s = lane(b[h], i, 8);
+                        if s & 0x80 != 0 { 0 } else { lane(a[h], (s & 0xf) as usize, 8) }
+                    });
+                    r
+                })
+            },
+            0x17 => {
+                let (a, b) = (self.get_vec_r(), self.get_vec_rm()?);
+                let rf = &mut self.ac.core.rflags;
+                rf.set_zero((a[0] & b[0]) | (a[1] & b[1]) == 0);
+                rf.set_carry((!a[0] & b[0]) | (!a[1] & b[1]) == 0);
+                rf.set_overflow(false);
+                rf.set_sign(false);
+                rf.set_parity(false);
+                return Ok(());
+            },
+            0x18 | 0x58 => { let v = self.get_xmm_rm_lane(32)?; map256(32, |_| v) },
+            0x19 | 0x59 if op == 0x59 || l => { let v = self.get_xmm_rm_lane(64)?; map256(64, |_| v) },
+            0x78 => { let v = self.get_xmm_rm_lane(8)?; map256(8, |_| v) },
+            0x79 => { let v = self.get_xmm_rm_lane(16)?; map256(16, |_| v) },
+            0x1a | 0x5a if l && !reg => { let v = self.get_m128(self.get_m()?, false)?; [v, v] },
+            0x29 | 0x37 => {
+                let (a, b) = (self.get_vec_v(), self.get_vec_rm()?);
+                let f = |x: u128, y: u128| map2(x, y, 64, |x, y| if (op == 0x29 && x == y) || (op == 0x37 && (x as i64) > (y as i64)) { !0 } else { 0 });
+                [f(a[0], b[0]), f(a[1], b[1])]
+            },
+            0x36 if l => {
+                let (a, b) = (self.get_vec_v(), self.get_vec_rm()?);
+                map256(32, |i| lane256(b, (lane256(a, i, 32) & 7) as usize, 32))
+            },
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        };
+
+        self.set_vec_r(r);
+        Ok(())
+    }
+
+    fn avx_0f3a(&mut self, op: u8, l: bool) -> Result<(), EmuException> {
+        let reg = self.idata.modrm.mod_ == 3;
+        let imm = self.get_imm8()? as usize;
+
+        let r = match op {
+            0x00 | 0x01 if l => {
+                let b = self.get_vec_rm()?;
+                map256(64, |i| lane256(b, (imm >> (i * 2)) & 3, 64))
+            },
+            0x06 | 0x46 if l => {
+                let (a, b) = (self.get_vec_v(), self.get_vec_rm()?);
+                let sel = |s: usize| if s & 8 != 0 { 0 } else { [a[0], a[1], b[0], b[1]][s & 3] };
+                [sel(imm), sel(imm >> 4)]
+            },
+            0x0f => {
+                let (a, b) = (self.get_vec_v(), self.get_vec_rm()?);
+                [alignr(a[0], b[0], imm), alignr(a[1], b[1], imm)]
+            },
+            0x18 | 0x38 if l => {
+                let (mut a, b) = (self.get_vec_v(), self.get_xmm_rm128(false)?);
+                a[imm & 1] = b;
+                a
+            },
+            0x19 | 0x39 if l => {
+                let v = self.get_ymm_r()[imm & 1];
+                if reg {
+                    let n = self.xmm_rm();
+                    self.ac.core.sse.set_ymm(n, [v, 0]);
+                    return Ok(());
+                }
+                return self.set_m128(self.get_m()?, v, false);
+            },
+            0x4c => {
+                let (a, b) = (self.get_vec_v(), self.get_vec_rm()?);
+                let m = self.ac.core.sse.get_ymm((imm >> 4) & (self.xmm_count() - 1));
+                map256(8, |i| if lane256(m, i, 8) & 0x80 != 0 { lane256(b, i, 8) } else { lane256(a, i, 8) })
+            },
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        };
+
+        self.set_vec_r(r);
+        Ok(())
+    }
+
+    fn is_vex256(&self) -> bool { matches!(self.pdata.vex, Some(vex) if vex.l == 1) }
+
+    fn get_ymm_v(&self) -> [u128; 2] { self.ac.core.sse.get_ymm(self.pdata.vex.map_or(0, |vex| vex.vvvv as usize)) }
+    fn get_ymm_r(&self) -> [u128; 2] { self.ac.core.sse.get_ymm(self.xmm_r()) }
+    fn set_ymm_r(&mut self, v: [u128; 2]) -> () { let n = self.xmm_r(); self.ac.core.sse.set_ymm(n, v); }
+
+    fn get_ymm_rm(&self, align: bool) -> Result<[u128; 2], EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            return Ok(self.ac.core.sse.get_ymm(self.xmm_rm()));
+        }
+
+        let (sg, adr) = self.get_m()?;
+        if align && adr & 0x1f != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        Ok([self.get_m128((sg, adr), false)?, self.get_m128((sg, adr + 16), false)?])
+    }
+
+    fn set_ymm_rm(&mut self, v: [u128; 2], align: bool) -> Result<(), EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            let n = self.xmm_rm();
+            self.ac.core.sse.set_ymm(n, v);
+            return Ok(());
+        }
+
+        let (sg, adr) = self.get_m()?;
+        if align && adr & 0x1f != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        self.set_m128((sg, adr), v[0], false)?;
+        self.set_m128((sg, adr + 16), v[1], false)
+    }
+
+    fn get_vec_v(&self) -> [u128; 2] { let v = self.get_ymm_v(); if self.is_vex256() { v } else { [v[0], 0] } }
+    fn get_vec_r(&self) -> [u128; 2] { let v = self.get_ymm_r(); if self.is_vex256() { v } else { [v[0], 0] } }
+
+    fn get_vec_rm(&self) -> Result<[u128; 2], EmuException> {
+        if self.is_vex256() {
+            self.get_ymm_rm(false)
+        } else {
+            Ok([self.get_xmm_rm128(false)?, 0])
+        }
+    }
+
+    fn set_vec_r(&mut self, v: [u128; 2]) -> () {
+        let v = if self.is_vex256() { v } else { [v[0], 0] };
+        self.set_ymm_r(v);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn avx_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    assert_eq!(alignr(0x0f0e0d0c0b0a09080706050403020100, 0x1f1e1d1c1b1a19181716151413121110, 4), 0x030201001f1e1d1c1b1a191817161514);
+    assert_eq!(alignr(1, 2, 16), 1);
+    assert_eq!(narrow([2f64.to_bits() as u128, 0], |x| (x >> 52) as u64), 0x400);
+    assert_eq!(lane256(map256(32, |i| i as u64), 5, 32), 5);
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.prefix.mandatory = Some(parse::Mandatory::P66);
+    parse.prefix.vex = Some(parse::Vex { map: 1, vvvv: 2, l: 1, w: 0 });
+    parse.instr.opcode = 0x1fe;
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.rm = 1;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.sse() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        _ => panic!("expected #UD"),
+    }
+    exe.ac.core.cregs.4.OSXSAVE = 1;
+    exe.ac.core.sse.xcr0 = XCR0::X87 | XCR0::SSE | XCR0::AVX;
+
+    exe.ac.core.sse.set_ymm(1, [0x1_00000001, 0x2_00000002]);
+    exe.ac.core.sse.set_ymm(2, [0x3_00000003, 0x4_00000004]);
+    exe.sse().unwrap();
+    assert_eq!(exe.ac.core.sse.get_ymm(0), [0x4_00000004, 0x6_00000006]);
+
+    exe.ac.core.sse.set_ymm(1, [0, 0x1234]);
+    parse.prefix.vex = Some(parse::Vex { map: 1, vvvv: 0, l: 0, w: 0 });
+    parse.instr.opcode = 0x177;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.sse().unwrap();
+    assert_eq!(exe.ac.core.sse.get_ymm(1), [0, 0]);
+    assert_eq!(exe.ac.core.sse.get_ymm(0)[0], 0x4_00000004);
+}
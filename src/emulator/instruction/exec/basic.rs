@@ -47,6 +47,10 @@ impl<'a> super::Exec<'a> {
         self.ac.get_gpreg(GpReg8::CL)
     }
 
+    pub fn get_one(&self) -> Result<u8, EmuException> {
+        Ok(1)
+    }
+
     pub fn get_dx(&self) -> Result<u16, EmuException> {
         self.ac.get_gpreg(GpReg16::DX)
     }
@@ -0,0 +1,123 @@
+use crate::emulator::*;
+
+impl<'a> super::Exec<'a> {
+    pub fn daa(&mut self) -> Result<(), EmuException> {
+        if self.is_mode64() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+        let (al, cf) = (self.get_al()?, self.ac.core.rflags.is_carry());
+        let af = (al & 0xf) > 9 || self.ac.core.rflags.is_adjust();
+        let v = if af { al.wrapping_add(6) } else { al };
+        let cf = al > 0x99 || cf;
+        let v = if cf { v.wrapping_add(0x60) } else { v };
+
+        debug!("daa: {:02x} -> {:02x}", al, v);
+        self.update_rflags_bcd(v, cf, af)?;
+        self.set_al(v)
+    }
+
+    pub fn das(&mut self) -> Result<(), EmuException> {
+        if self.is_mode64() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+        let (al, cf) = (self.get_al()?, self.ac.core.rflags.is_carry());
+        let af = (al & 0xf) > 9 || self.ac.core.rflags.is_adjust();
+        let v = if af { al.wrapping_sub(6) } else { al };
+        let cf = al > 0x99 || cf;
+        let v = if cf { v.wrapping_sub(0x60) } else { v };
+
+        debug!("das: {:02x} -> {:02x}", al, v);
+        self.update_rflags_bcd(v, cf, af)?;
+        self.set_al(v)
+    }
+
+    pub fn aaa(&mut self) -> Result<(), EmuException> {
+        if self.is_mode64() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+        let ax = self.get_ax()?;
+        let af = (ax & 0xf) > 9 || self.ac.core.rflags.is_adjust();
+        let v = if af { ax.wrapping_add(0x106) } else { ax } & 0xff0f;
+
+        debug!("aaa: {:04x} -> {:04x}", ax, v);
+        self.update_rflags_bcd(v as u8, af, af)?;
+        self.set_ax(v)
+    }
+
+    pub fn aas(&mut self) -> Result<(), EmuException> {
+        if self.is_mode64() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+        let ax = self.get_ax()?;
+        let af = (ax & 0xf) > 9 || self.ac.core.rflags.is_adjust();
+        let v = if af { ax.wrapping_sub(6).wrapping_sub(0x100) } else { ax } & 0xff0f;
+
+        debug!("aas: {:04x} -> {:04x}", ax, v);
+        self.update_rflags_bcd(v as u8, af, af)?;
+        self.set_ax(v)
+    }
+
+    pub fn aam(&mut self) -> Result<(), EmuException> {
+        if self.is_mode64() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+        let (al, base) = (self.get_al()?, self.get_imm8()?);
+        if base == 0 {
+            return Err(EmuException::CPUException(CPUException::DE));
+        }
+
+        debug!("aam: {:02x}, {:02x}", al, base);
+        self.update_rflags_bcd(al % base, false, false)?;
+        self.set_ax(((al / base) as u16) << 8 | (al % base) as u16)
+    }
+
+    pub fn aad(&mut self) -> Result<(), EmuException> {
+        if self.is_mode64() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+        let (ax, base) = (self.get_ax()?, self.get_imm8()?);
+        let v = ((ax >> 8) as u8).wrapping_mul(base).wrapping_add(ax as u8);
+
+        debug!("aad: {:04x}, {:02x}", ax, base);
+        self.update_rflags_bcd(v, false, false)?;
+        self.set_ax(v as u16)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn bcd_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.imm = Some(10);
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.set_al(0x79u8.wrapping_add(0x35)).unwrap();
+    exe.daa().unwrap();
+    assert_eq!(exe.get_al().unwrap(), 0x14);
+    assert!(exe.ac.core.rflags.is_carry());
+
+    exe.set_al(0x35u8.wrapping_sub(0x47)).unwrap();
+    exe.ac.core.rflags.set_carry(true);
+    exe.ac.core.rflags.set_adjust(true);
+    exe.das().unwrap();
+    assert_eq!(exe.get_al().unwrap(), 0x88);
+    assert!(exe.ac.core.rflags.is_carry());
+
+    exe.set_ax(0x000f).unwrap();
+    exe.aaa().unwrap();
+    assert_eq!(exe.get_ax().unwrap(), 0x0105);
+
+    exe.set_ax(0x004f).unwrap();
+    exe.aam().unwrap();
+    assert_eq!(exe.get_ax().unwrap(), 0x0709);
+    exe.aad().unwrap();
+    assert_eq!(exe.get_ax().unwrap(), 0x004f);
+
+    parse.instr.imm = Some(0);
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.aam() {
+        Err(EmuException::CPUException(CPUException::DE)) => {},
+        _ => panic!("expected #DE"),
+    }
+}
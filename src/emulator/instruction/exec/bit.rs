@@ -0,0 +1,180 @@
+use std::convert::TryFrom;
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+use crate::emulator::instruction::parse;
+
+macro_rules! bit_test {
+    ( $size:expr, $type:ty ) => { paste::item! {
+        pub fn [<bt_u $size>](&mut self, off: i64) -> Result<(), EmuException> {
+            self.[<bit_test_u $size>](off, None)
+        }
+
+        pub fn [<bts_u $size>](&mut self, off: i64) -> Result<(), EmuException> {
+            self.[<bit_test_u $size>](off, Some(|v, m| v | m))
+        }
+
+        pub fn [<btr_u $size>](&mut self, off: i64) -> Result<(), EmuException> {
+            self.[<bit_test_u $size>](off, Some(|v, m| v & !m))
+        }
+
+        pub fn [<btc_u $size>](&mut self, off: i64) -> Result<(), EmuException> {
+            self.[<bit_test_u $size>](off, Some(|v, m| v ^ m))
+        }
+
+        fn [<bit_test_u $size>](&mut self, off: i64, f: Option<fn($type, $type) -> $type>) -> Result<(), EmuException> {
+            let mask: $type = 1 << (off & ($size - 1));
+            let target = if self.idata.modrm.mod_ == 3 { None } else { Some(self.bit_addr(off, $size)?) };
+
+            let v = match target {
+                Some(m) => self.ac.[<get_data $size>](m)?,
+                None => self.[<get_rm $size>]()?,
+            };
+            debug!("bt: {:02x}, {:02x}", v, mask);
+            self.update_rflags_bt(v, mask)?;
+
+            match (f, target) {
+                (Some(f), Some(m)) => self.ac.[<set_data $size>](m, f(v, mask)),
+                (Some(f), None) => self.[<set_rm $size>](f(v, mask)),
+                (None, _) => Ok(()),
+            }
+        }
+    } };
+}
+
+impl<'a> super::Exec<'a> {
+    bit_test!(16, u16);
+    bit_test!(32, u32);
+    bit_test!(64, u64);
+
+    pub fn is_repz(&self) -> bool {
+        matches!(self.pdata.repeat, Some(parse::Rep::REPZ))
+    }
+
+    pub fn bmi1(&mut self) -> Result<(), EmuException> {
+        let vex = match self.pdata.vex {
+            Some(vex) if vex.l == 0 && self.simd_prefix() == 0 => vex,
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        };
+        let n = (if self.is_mode64() { vex.vvvv } else { vex.vvvv & 7 }) as usize;
+        let (bits, mask) = if self.is_rexw() { (64, !0) } else { (32, 0xffff_ffff) };
+        let src = if bits == 64 { self.get_rm64()? } else { self.get_rm32()? as u64 };
+        let v = self.ac.get_gpreg(GpReg64::try_from(n).unwrap())? & mask;
+
+        let (r, cf) = match (self.idata.opcode as u8, self.idata.modrm.reg) {
+            (0xf2, _) => (!v & src, false),
+            (0xf7, _) => {
+                let (start, len) = (v & 0xff, (v >> 8) & 0xff);
+                let x = if start < bits { src >> start } else { 0 };
+                (if len < bits { x & ((1 << len) - 1) } else { x }, false)
+            },
+            (0xf3, 1) => (src & src.wrapping_sub(1), src == 0),
+            (0xf3, 2) => (src ^ src.wrapping_sub(1), src == 0),
+            (0xf3, 3) => (src & src.wrapping_neg(), src != 0),
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        };
+        let r = r & mask;
+
+        let rf = &mut self.ac.core.rflags;
+        rf.set_carry(cf);
+        rf.set_zero(r == 0);
+        rf.set_sign(r >> (bits - 1) != 0);
+        rf.set_overflow(false);
+
+        if self.idata.opcode as u8 == 0xf3 {
+            self.ac.set_gpreg(GpReg64::try_from(n).unwrap(), r)
+        } else {
+            self.set_r64(r)
+        }
+    }
+
+    fn bit_addr(&self, off: i64, size: i64) -> Result<(SgReg, u64), EmuException> {
+        let (sg, adr) = self.get_m()?;
+        let adr = adr.wrapping_add((off.div_euclid(size) * size / 8) as u64);
+
+        Ok((sg, match self.idata.adsize {
+            access::AcsSize::BIT16 => adr as u16 as u64,
+            access::AcsSize::BIT32 => adr as u32 as u64,
+            access::AcsSize::BIT64 => adr,
+        }))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn bit_exec_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.rm = 6;
+    parse.instr.disp = 0x100;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_data32((SgReg::DS, 0xfc), 0x80000000).unwrap();
+    exe.bt_u32(-1).unwrap();
+    assert!(exe.ac.core.rflags.is_carry());
+    exe.bts_u32(35).unwrap();
+    assert!(!exe.ac.core.rflags.is_carry());
+    assert_eq!(exe.ac.get_data32((SgReg::DS, 0x104)).unwrap(), 8);
+    exe.btc_u16(-1).unwrap();
+    assert!(exe.ac.core.rflags.is_carry());
+    assert_eq!(exe.ac.get_data32((SgReg::DS, 0xfc)).unwrap(), 0);
+
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.rm = 0;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_gpreg(GpReg64::RAX, 0xff).unwrap();
+    exe.btr_u64(68).unwrap();
+    assert!(exe.ac.core.rflags.is_carry());
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RAX).unwrap(), 0xef);
+}
+
+#[cfg(test)]
+#[test]
+fn bmi1_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.prefix.vex = Some(parse::Vex { map: 2, vvvv: 2, l: 0, w: 0 });
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.rm = 1;
+
+    ac.core.gpregs.set64(GpReg64::RCX, 0xffff_0000_0000_3a58);
+    ac.core.gpregs.set64(GpReg64::RDX, 0x0000_0408);
+
+    parse.instr.opcode = 0x2f2;
+    super::Exec::new(&mut ac, &parse).bmi1().unwrap();
+    assert_eq!(ac.core.gpregs.get64(GpReg64::RAX), 0x3a50);
+
+    parse.instr.opcode = 0x2f7;
+    super::Exec::new(&mut ac, &parse).bmi1().unwrap();
+    assert_eq!(ac.core.gpregs.get64(GpReg64::RAX), 0xa);
+
+    parse.instr.opcode = 0x2f3;
+    for (reg, r) in [(1, 0x3a50), (2, 0xf), (3, 0x8)].iter() {
+        parse.instr.modrm.reg = *reg;
+        super::Exec::new(&mut ac, &parse).bmi1().unwrap();
+        assert_eq!(ac.core.gpregs.get64(GpReg64::RDX), *r);
+        assert_eq!(ac.core.rflags.is_carry(), *reg == 3);
+    }
+
+    ac.core.gpregs.set64(GpReg64::RCX, 0);
+    parse.instr.modrm.reg = 3;
+    parse.prefix.rex = Some(parse::Rex { w: 1, ..Default::default() });
+    super::Exec::new(&mut ac, &parse).bmi1().unwrap();
+    assert_eq!(ac.core.gpregs.get64(GpReg64::RDX), 0);
+    assert!(ac.core.rflags.is_zero() && !ac.core.rflags.is_carry());
+
+    parse.prefix.mandatory = Some(parse::Mandatory::P66);
+    match super::Exec::new(&mut ac, &parse).bmi1() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        _ => panic!("expected #UD"),
+    }
+}
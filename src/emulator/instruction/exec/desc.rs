@@ -1,12 +1,16 @@
 use crate::emulator::*;
 use crate::emulator::access::register::*;
 use crate::emulator::access::descriptor::*;
+use crate::emulator::instruction::parse;
+use crate::emulator::instruction::Instruction;
+use crate::hardware::processor::segment::SgDescCache;
+use crate::hardware::processor::model_specific::MSRAccess;
 
 macro_rules! jmp_far {
     ( $type:ty ) => { paste::item! {
         pub fn [<jmp_far_ $type>](&mut self, sel: u16, abs: $type) -> Result<(), EmuException> {
             match self.ac.mode {
-                access::CpuMode::Real => {
+                access::CpuMode::Real | access::CpuMode::V8086 => {
                     self.ac.load_segment(SgReg::CS, sel)?;
                     self.ac.set_ip(abs as u64)?;
                 },
@@ -33,7 +37,7 @@ macro_rules! jmp_far {
                         },
                         Some(DescType::System(SysDescType::Call(gate))) => {
                             if gate.DPL < cpl || gate.DPL < rpl { return Err(EmuException::CPUException(CPUException::GP(None))); }
-                            let ip = ((gate.offset_h as u32) << 16) + gate.offset_l as u32;
+                            let ip = gate.offset();
                             let (sel, desc) = self.ac.select_callgate(gate)?;
 
                             if CodeDescFlag::from(&desc).contains(CodeDescFlag::C) {
@@ -42,13 +46,13 @@ macro_rules! jmp_far {
                                 return Err(EmuException::CPUException(CPUException::GP(None)));
                             }
 
-                            if self.ac.oasz.op != access::AcsSize::BIT64 && abs as u32 > ((desc.limit_h as u32) << 16) + desc.limit_l as u32 {
+                            if !(self.ac.mode == access::CpuMode::Long && desc.L == 1) && ip > ((desc.limit_h as u64) << 16) + desc.limit_l as u64 {
                                 return Err(EmuException::CPUException(CPUException::GP(None)));
                             }
 
                             let cache = self.ac.select_segdesc(SgReg::CS, cpl, Some(SegDescType::Code(desc)))?;
                             self.ac.set_sgreg(SgReg::CS, (sel & 0xfff8) | cpl as u16, cache)?;
-                            self.ac.set_ip(ip as u64)?;
+                            self.ac.set_ip(ip)?;
                         },
                         Some(DescType::System(SysDescType::Task(gate))) => {
                             if gate.DPL < cpl || gate.DPL < rpl { return Err(EmuException::CPUException(CPUException::GP(None))); }
@@ -79,7 +83,7 @@ macro_rules! call_far {
             let ip = self.ac.get_ip()? as $type;
 
             match self.ac.mode {
-                access::CpuMode::Real => {
+                access::CpuMode::Real | access::CpuMode::V8086 => {
                     if abs as u32 > cs.1.limit {
                         return Err(EmuException::CPUException(CPUException::GP(None)));
                     }
@@ -115,25 +119,7 @@ macro_rules! call_far {
                         },
                         Some(DescType::System(SysDescType::Call(gate))) => {
                             if gate.DPL < cpl || gate.DPL < rpl { return Err(EmuException::CPUException(CPUException::GP(None))); }
-                            let new_ip = ((gate.offset_h as u32) << 16) + gate.offset_l as u32;
-                            let (sel, desc) = self.ac.select_callgate(gate)?;
-
-                            if desc.DPL > cpl { return Err(EmuException::CPUException(CPUException::GP(None))); }
-
-                            if !CodeDescFlag::from(&desc).contains(CodeDescFlag::C) && desc.DPL < cpl {
-                                return Err(EmuException::NotImplementedFunction);
-                            } else {
-                                if self.ac.oasz.op != access::AcsSize::BIT64 && new_ip as u32 > ((desc.limit_h as u32) << 16) + desc.limit_l as u32 {
-                                    return Err(EmuException::CPUException(CPUException::GP(None)));
-                                }
-
-                                let cache = self.ac.select_segdesc(SgReg::CS, cpl, Some(SegDescType::Code(desc)))?;
-
-                                self.ac.[<push_ $type>](cs_sel)?;
-                                self.ac.[<push_ $type>](ip)?;
-                                self.ac.set_sgreg(SgReg::CS, (sel & 0xfff8) | cpl as u16, cache)?;
-                                self.ac.set_ip(ip as u64)?;
-                            }
+                            self.call_gate(gate)?;
                         },
                         Some(DescType::System(SysDescType::Task(gate))) => {
                             if gate.DPL < cpl || gate.DPL < rpl { return Err(EmuException::CPUException(CPUException::GP(None))); }
@@ -158,12 +144,12 @@ macro_rules! call_far {
 
 macro_rules! ret_far {
     ( $type:ty ) => { paste::item! {
-        pub fn [<ret_far_ $type>](&mut self) -> Result<(), EmuException> {
+        pub fn [<ret_far_ $type>](&mut self, imm: u16) -> Result<(), EmuException> {
             let new_ip = self.ac.[<pop_ $type>]()?;
             let new_cs = self.ac.[<pop_ $type>]()? as u16;
 
             match self.ac.mode {
-                access::CpuMode::Real => {
+                access::CpuMode::Real | access::CpuMode::V8086 => {
                     self.ac.load_segment(SgReg::CS, new_cs)?;
                 },
                 access::CpuMode::Protected | access::CpuMode::Long => {
@@ -174,26 +160,33 @@ macro_rules! ret_far {
                         return Err(EmuException::CPUException(CPUException::GP(None)));
                     }
 
-                    if let Some(DescType::Segment(SegDescType::Code(cdesc))) = self.ac.obtain_gl_desc(new_cs)? {
+                    let long = if let Some(DescType::Segment(SegDescType::Code(cdesc))) = self.ac.obtain_gl_desc(new_cs)? {
                         if CodeDescFlag::from(&cdesc).contains(CodeDescFlag::C) && cdesc.DPL > rpl {
                             return Err(EmuException::CPUException(CPUException::GP(None)));
                         }
 
-                        if self.ac.oasz.op != access::AcsSize::BIT64 && new_ip as u32 > ((cdesc.limit_h as u32) << 16) + cdesc.limit_l as u32 {
+                        let long = self.ac.mode == access::CpuMode::Long && cdesc.L == 1;
+                        if !long && new_ip as u32 > ((cdesc.limit_h as u32) << 16) + cdesc.limit_l as u32 {
                             return Err(EmuException::CPUException(CPUException::GP(None)));
                         }
 
                         let cache = self.ac.select_segdesc(SgReg::CS, rpl, Some(SegDescType::Code(cdesc)))?;
                         self.ac.set_sgreg(SgReg::CS, new_cs, cache)?;
+                        long
                     } else {
                         return Err(EmuException::CPUException(CPUException::GP(None)));
-                    }
+                    };
 
                     if rpl > cpl {
+                        self.ac.release_stack(imm)?;
                         let new_sp = self.ac.[<pop_ $type>]()?;
                         let new_ss = self.ac.[<pop_ $type>]()? as u16;
                         self.ac.set_gpreg(GpReg64::RSP, new_sp as u64)?;
-                        self.ac.load_segment(SgReg::SS, new_ss)?;
+                        if long && rpl < 3 && new_ss & 0xfffc == 0 {
+                            self.ac.set_sgreg(SgReg::SS, new_ss, Default::default())?;
+                        } else {
+                            self.ac.load_segment(SgReg::SS, new_ss)?;
+                        }
 
                         for r in vec!(SgReg::ES, SgReg::FS, SgReg::GS, SgReg::DS).iter() {
                             if rpl > self.ac.get_sgreg(*r)?.1.DPL {
@@ -204,8 +197,10 @@ macro_rules! ret_far {
                 },
             }
 
-            self.ac.set_ip(new_ip as u64)?;
-            self.ac.update_opadsize()
+            self.ac.update_opadsize()?;
+            self.ac.update_stacksize()?;
+            self.ac.release_stack(imm)?;
+            self.ac.set_ip(new_ip as u64)
         }
     } };
 }
@@ -215,12 +210,16 @@ macro_rules! int_ret {
         pub fn [<int_ret_ $type>](&mut self) -> Result<(), EmuException> {
             let new_ip   = self.ac.[<pop_ $type>]()?;
             let new_cs   = self.ac.[<pop_ $type>]()? as u16;
-            let new_flag = self.ac.[<pop_ $type>]()? as u64;
+            let mut new_flag = self.ac.[<pop_ $type>]()? as u64;
 
             match self.ac.mode {
                 access::CpuMode::Real => {
                     self.ac.load_segment(SgReg::CS, new_cs)?;
                 },
+                access::CpuMode::V8086 => {
+                    new_flag = self.v86_flags(new_flag, std::mem::size_of::<$type>() == 2)?;
+                    self.ac.load_segment(SgReg::CS, new_cs)?;
+                },
                 access::CpuMode::Protected | access::CpuMode::Long => {
                     if self.ac.core.rflags.is_nesttask() {
                         self.ac.[<push_ $type>](new_flag as $type)?;
@@ -230,32 +229,43 @@ macro_rules! int_ret {
                     }
 
                     let cpl = self.ac.get_cpl()?;
+                    if self.ac.mode == access::CpuMode::Protected && std::mem::size_of::<$type>() == 4 && new_flag & (1<<17) != 0 && cpl == 0 {
+                        return self.iret_to_v86(new_ip as u32, new_cs, new_flag as u32);
+                    }
+                    new_flag &= !(1<<17);
                     let rpl = (new_cs & 3) as u8;
 
                     if rpl < cpl {
                         return Err(EmuException::CPUException(CPUException::GP(None)));
                     }
 
-                    if let Some(DescType::Segment(SegDescType::Code(cdesc))) = self.ac.obtain_gl_desc(new_cs)? {
+                    let mode64 = self.is_mode64();
+                    let long = if let Some(DescType::Segment(SegDescType::Code(cdesc))) = self.ac.obtain_gl_desc(new_cs)? {
                         if CodeDescFlag::from(&cdesc).contains(CodeDescFlag::C) && cdesc.DPL > rpl {
                             return Err(EmuException::CPUException(CPUException::GP(None)));
                         }
 
-                        if self.ac.oasz.op != access::AcsSize::BIT64 && new_ip as u32 > ((cdesc.limit_h as u32) << 16) + cdesc.limit_l as u32 {
+                        let long = self.ac.mode == access::CpuMode::Long && cdesc.L == 1;
+                        if !long && new_ip as u32 > ((cdesc.limit_h as u32) << 16) + cdesc.limit_l as u32 {
                             return Err(EmuException::CPUException(CPUException::GP(None)));
                         }
 
                         let cache = self.ac.select_segdesc(SgReg::CS, rpl, Some(SegDescType::Code(cdesc)))?;
                         self.ac.set_sgreg(SgReg::CS, new_cs, cache)?;
+                        long
                     } else {
                         return Err(EmuException::CPUException(CPUException::GP(None)));
-                    }
+                    };
 
-                    if rpl > cpl {
+                    if rpl > cpl || mode64 {
                         let new_sp = self.ac.[<pop_ $type>]()?;
                         let new_ss = self.ac.[<pop_ $type>]()? as u16;
                         self.ac.set_gpreg(GpReg64::RSP, new_sp as u64)?;
-                        self.ac.load_segment(SgReg::SS, new_ss)?;
+                        if long && rpl < 3 && new_ss & 0xfffc == 0 {
+                            self.ac.set_sgreg(SgReg::SS, new_ss, Default::default())?;
+                        } else {
+                            self.ac.load_segment(SgReg::SS, new_ss)?;
+                        }
 
                         for r in vec!(SgReg::ES, SgReg::FS, SgReg::GS, SgReg::DS).iter() {
                             if rpl > self.ac.get_sgreg(*r)?.1.DPL {
@@ -267,8 +277,9 @@ macro_rules! int_ret {
             }
 
             self.ac.core.rflags.from_u64(new_flag);
-            self.ac.set_ip(new_ip as u64)?;
-            self.ac.update_opadsize()
+            self.ac.update_opadsize()?;
+            self.ac.update_stacksize()?;
+            self.ac.set_ip(new_ip as u64)
         }
     } };
 }
@@ -301,4 +312,535 @@ impl<'a> super::Exec<'a> {
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn push_sgreg(&mut self, reg: SgReg) -> Result<(), EmuException> {
+        let sel = self.ac.get_sgreg(reg)?.0;
+        match self.sgreg_stack_size(reg)? {
+            access::AcsSize::BIT16 => self.ac.push_u16(sel),
+            access::AcsSize::BIT32 => self.ac.push_u32(sel as u32),
+            access::AcsSize::BIT64 => self.ac.push_u64(sel as u64),
+        }
+    }
+
+    pub fn pop_sgreg(&mut self, reg: SgReg) -> Result<(), EmuException> {
+        let sel = match self.sgreg_stack_size(reg)? {
+            access::AcsSize::BIT16 => self.ac.pop_u16()?,
+            access::AcsSize::BIT32 => self.ac.pop_u32()? as u16,
+            access::AcsSize::BIT64 => self.ac.pop_u64()? as u16,
+        };
+        self.mov_to_sreg(reg, sel)
+    }
+
+    pub fn push_ip(&mut self) -> Result<(), EmuException> {
+        let ip = self.ac.get_ip()?;
+        self.push_sized(self.near_opsize(), ip)
+    }
+
+    pub fn pop_ip(&mut self) -> Result<u64, EmuException> {
+        self.pop_sized(self.near_opsize())
+    }
+
+    pub fn syscall(&mut self) -> Result<(), EmuException> {
+        if self.ac.core.msr.efer.SCE == 0 || !self.is_mode64() {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+
+        let rip = self.ac.get_ip()?;
+        let rflags = self.ac.get_rflags()?;
+        self.ac.set_gpreg(GpReg64::RCX, rip)?;
+        self.ac.set_gpreg(GpReg64::R11, rflags)?;
+
+        let sel = self.ac.core.msr.star.cs & 0xfffc;
+        self.ac.set_sgreg(SgReg::CS, sel, flat_segment(true, 0, true))?;
+        self.ac.set_sgreg(SgReg::SS, sel + 8, flat_segment(false, 0, false))?;
+
+        let mask = self.ac.core.msr.fmask.mask as u64;
+        self.ac.set_rflags(rflags & !mask)?;
+        self.ac.core.rflags.set_resume(false);
+
+        self.ac.update_opadsize()?;
+        self.ac.update_stacksize()?;
+        let lstar = self.ac.core.msr.lstar.to_u64();
+        self.ac.set_ip(lstar)
+    }
+
+    pub fn sysret(&mut self) -> Result<(), EmuException> {
+        if self.ac.core.msr.efer.SCE == 0 || !self.is_mode64() {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(Some(0))));
+        }
+
+        let base = self.ac.core.msr.star.l_csss;
+        let rcx = self.ac.get_gpreg(GpReg64::RCX)?;
+        let r11 = self.ac.get_gpreg(GpReg64::R11)?;
+
+        let (sel, ip, long) = if self.is_rexw() {
+            if ((rcx as i64) << 16 >> 16) as u64 != rcx {
+                return Err(EmuException::CPUException(CPUException::GP(Some(0))));
+            }
+            (base + 16, rcx, true)
+        } else {
+            (base, rcx as u32 as u64, false)
+        };
+        self.ac.set_sgreg(SgReg::CS, sel | 3, flat_segment(true, 3, long))?;
+        self.ac.set_sgreg(SgReg::SS, (base + 8) | 3, flat_segment(false, 3, false))?;
+        self.ac.set_rflags((r11 & 0x3c7fd7) | 2)?;
+
+        self.ac.update_opadsize()?;
+        self.ac.update_stacksize()?;
+        self.ac.set_ip(ip)
+    }
+
+    pub fn sysenter(&mut self) -> Result<(), EmuException> {
+        let cs = self.ac.core.msr.sysenter_cs.to_u64() as u16;
+        if self.ac.mode == access::CpuMode::Real || cs & 0xfffc == 0 {
+            return Err(EmuException::CPUException(CPUException::GP(Some(0))));
+        }
+
+        let rflags = &mut self.ac.core.rflags;
+        rflags.set_v8086(false);
+        rflags.set_interrupt(false);
+        rflags.set_resume(false);
+        self.ac.update_cpumode()?;
+
+        let long = self.ac.core.msr.efer.LMA == 1;
+        let sel = cs & 0xfffc;
+        self.ac.set_sgreg(SgReg::CS, sel, flat_segment(true, 0, long))?;
+        self.ac.set_sgreg(SgReg::SS, sel + 8, flat_segment(false, 0, false))?;
+
+        self.ac.update_opadsize()?;
+        self.ac.update_stacksize()?;
+        let (esp, eip) = (self.ac.core.msr.sysenter_esp.to_u64(), self.ac.core.msr.sysenter_eip.to_u64());
+        if long {
+            self.ac.set_gpreg(GpReg64::RSP, esp)?;
+        } else {
+            self.ac.set_gpreg(GpReg32::ESP, esp as u32)?;
+        }
+        self.ac.set_ip(eip)
+    }
+
+    pub fn sysexit(&mut self) -> Result<(), EmuException> {
+        let cs = self.ac.core.msr.sysenter_cs.to_u64() as u16;
+        if self.ac.mode == access::CpuMode::Real || cs & 0xfffc == 0 || self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(Some(0))));
+        }
+
+        let rcx = self.ac.get_gpreg(GpReg64::RCX)?;
+        let rdx = self.ac.get_gpreg(GpReg64::RDX)?;
+
+        let (sel, long) = if self.is_rexw() { (cs + 32, true) } else { (cs + 16, false) };
+        if long && (((rdx as i64) << 16 >> 16) as u64 != rdx || ((rcx as i64) << 16 >> 16) as u64 != rcx) {
+            return Err(EmuException::CPUException(CPUException::GP(Some(0))));
+        }
+        self.ac.set_sgreg(SgReg::CS, sel | 3, flat_segment(true, 3, long))?;
+        self.ac.set_sgreg(SgReg::SS, (sel + 8) | 3, flat_segment(false, 3, false))?;
+
+        self.ac.update_opadsize()?;
+        self.ac.update_stacksize()?;
+        if long {
+            self.ac.set_gpreg(GpReg64::RSP, rcx)?;
+        } else {
+            self.ac.set_gpreg(GpReg32::ESP, rcx as u32)?;
+        }
+        self.ac.set_ip(if long { rdx } else { rdx as u32 as u64 })
+    }
+
+    pub fn is_mode64(&self) -> bool {
+        self.ac.mode == access::CpuMode::Long && self.ac.oasz.ad == access::AcsSize::BIT64
+    }
+
+    pub(super) fn is_rexw(&self) -> bool {
+        matches!(self.pdata.rex, Some(parse::Rex { w: 1, .. }))
+    }
+
+    fn call_gate(&mut self, gate: CallGateDesc) -> Result<(), EmuException> {
+        let long = self.ac.mode == access::CpuMode::Long;
+        let size = match (long, gate.D) {
+            (true, 1)  => access::AcsSize::BIT64,
+            (true, _)  => { return Err(EmuException::CPUException(CPUException::GP(None))); },
+            (false, 1) => access::AcsSize::BIT32,
+            (false, _) => access::AcsSize::BIT16,
+        };
+        let (new_ip, pc) = (gate.offset(), if long { 0 } else { gate.pc & 0x1f });
+        let (sel, desc) = self.ac.select_callgate(gate)?;
+
+        let cpl = self.ac.get_cpl()?;
+        if desc.DPL > cpl || (long && (desc.L == 0 || desc.DB == 1)) {
+            return Err(EmuException::CPUException(CPUException::GP(Some(sel))));
+        }
+        if !long && new_ip > ((desc.limit_h as u64) << 16) + desc.limit_l as u64 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let pl = if !CodeDescFlag::from(&desc).contains(CodeDescFlag::C) && desc.DPL < cpl { desc.DPL } else { cpl };
+        let cs = self.ac.get_sgreg(SgReg::CS)?.0;
+        let ss = self.ac.get_sgreg(SgReg::SS)?.0;
+        let (ip, sp) = (self.ac.get_ip()?, self.ac.get_gpreg(GpReg64::RSP)?);
+
+        let sys = self.ac.set_supervisor(pl < cpl);
+        let res = self.with_stack_restore(|exe| {
+            if pl < cpl {
+                let (step, mask) = (Self::sized_bytes(size), exe.ac.stack_mask());
+                let mut params = Vec::new();
+                for i in 0..pc as u64 {
+                    params.push(exe.get_stack_sized(size, sp.wrapping_add(i * step) & mask)?);
+                }
+
+                exe.ac.load_tss_stack(size, pl)?;
+                exe.push_sized(size, ss as u64)?;
+                exe.push_sized(size, sp)?;
+                for v in params.iter().rev() {
+                    exe.push_sized(size, *v)?;
+                }
+            }
+            exe.push_sized(size, cs as u64)?;
+            exe.push_sized(size, ip)?;
+
+            let cache = exe.ac.select_segdesc(SgReg::CS, pl, Some(SegDescType::Code(desc)))?;
+            exe.ac.set_sgreg(SgReg::CS, (sel & 0xfff8) | pl as u16, cache)?;
+            exe.ac.update_opadsize()?;
+            exe.ac.update_stacksize()
+        });
+        self.ac.set_supervisor(sys);
+        res?;
+        self.ac.set_ip(new_ip)
+    }
+
+    fn with_stack_restore(&mut self, f: impl FnOnce(&mut Self) -> Result<(), EmuException>) -> Result<(), EmuException> {
+        let (cs, ss) = (self.ac.get_sgreg(SgReg::CS)?, self.ac.get_sgreg(SgReg::SS)?);
+        let rsp = self.ac.get_gpreg(GpReg64::RSP)?;
+
+        f(self).or_else(|e| {
+            self.ac.set_sgreg(SgReg::CS, cs.0, cs.1)?;
+            self.ac.set_sgreg(SgReg::SS, ss.0, ss.1)?;
+            self.ac.set_gpreg(GpReg64::RSP, rsp)?;
+            self.ac.update_opadsize()?;
+            self.ac.update_stacksize()?;
+            Err(e)
+        })
+    }
+
+    fn sized_bytes(size: access::AcsSize) -> u64 {
+        match size {
+            access::AcsSize::BIT16 => 2,
+            access::AcsSize::BIT32 => 4,
+            access::AcsSize::BIT64 => 8,
+        }
+    }
+
+    fn get_stack_sized(&self, size: access::AcsSize, ofs: u64) -> Result<u64, EmuException> {
+        let target = (SgReg::SS, ofs);
+        Ok(match size {
+            access::AcsSize::BIT16 => self.ac.get_data16(target)? as u64,
+            access::AcsSize::BIT32 => self.ac.get_data32(target)? as u64,
+            access::AcsSize::BIT64 => self.ac.get_data64(target)?,
+        })
+    }
+
+    fn push_sized(&mut self, size: access::AcsSize, v: u64) -> Result<(), EmuException> {
+        match size {
+            access::AcsSize::BIT16 => self.ac.push_u16(v as u16),
+            access::AcsSize::BIT32 => self.ac.push_u32(v as u32),
+            access::AcsSize::BIT64 => self.ac.push_u64(v),
+        }
+    }
+
+    fn pop_sized(&mut self, size: access::AcsSize) -> Result<u64, EmuException> {
+        Ok(match size {
+            access::AcsSize::BIT16 => self.ac.pop_u16()? as u64,
+            access::AcsSize::BIT32 => self.ac.pop_u32()? as u64,
+            access::AcsSize::BIT64 => self.ac.pop_u64()?,
+        })
+    }
+
+    fn near_opsize(&self) -> access::AcsSize {
+        match Instruction::opad_size(&self.ac.oasz, self.pdata).op {
+            access::AcsSize::BIT32 if self.is_mode64() => access::AcsSize::BIT64,
+            op => op,
+        }
+    }
+
+    fn sgreg_stack_size(&self, reg: SgReg) -> Result<access::AcsSize, EmuException> {
+        if self.is_mode64() && !matches!(reg, SgReg::FS | SgReg::GS) {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        Ok(self.near_opsize())
+    }
+}
+
+fn flat_segment(code: bool, dpl: u8, long: bool) -> SgDescCache {
+    SgDescCache {
+        base: 0,
+        limit: 0xfffff,
+        Type: if code { 0xb } else { 0x3 },
+        DPL: dpl,
+        P: 1,
+        AVL: 0,
+        L: long as u8,
+        DB: (!long) as u8,
+        G: 1,
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn syscall_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    ac.mode = access::CpuMode::Long;
+    ac.core.msr.efer.LMA = 1;
+    ac.core.msr.efer.SCE = 1;
+    ac.set_sgreg(SgReg::CS, 0x33, flat_segment(true, 3, true)).unwrap();
+    ac.update_opadsize().unwrap();
+    ac.core.msr.star.from_u64(0x0023_0010_0000_0000);
+    ac.core.msr.lstar.from_u64(0xffff_8000_dead_0000);
+    ac.core.msr.fmask.from_u64(0x200);
+    ac.core.rflags.set_interrupt(true);
+    ac.set_ip(0x401000).unwrap();
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.syscall().unwrap();
+    assert_eq!(exe.ac.get_ip().unwrap(), 0xffff_8000_dead_0000);
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RCX).unwrap(), 0x401000);
+    assert_eq!(exe.ac.get_sgreg(SgReg::CS).unwrap().0, 0x10);
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().0, 0x18);
+    assert_eq!(exe.ac.get_cpl().unwrap(), 0);
+    assert!(!exe.ac.core.rflags.is_interrupt());
+
+    exe.sysret().unwrap();
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x401000);
+    assert_eq!(exe.ac.get_sgreg(SgReg::CS).unwrap().0, 0x23);
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().0, 0x2b);
+    assert_eq!(exe.ac.get_cpl().unwrap(), 3);
+    assert!(exe.ac.core.rflags.is_interrupt());
+    assert!(exe.sysret().is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn iretq_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    ac.mode = access::CpuMode::Long;
+    ac.core.msr.efer.LMA = 1;
+    ac.set_gdtr(0x100, 0x2f).unwrap();
+    ac.set_data64((SgReg::DS, 0x118), 0x00affa000000ffff).unwrap();
+    ac.set_data64((SgReg::DS, 0x120), 0x00cff2000000ffff).unwrap();
+    ac.set_sgreg(SgReg::CS, 0x08, flat_segment(true, 0, true)).unwrap();
+    ac.set_sgreg(SgReg::SS, 0, Default::default()).unwrap();
+    ac.update_opadsize().unwrap();
+    ac.update_stacksize().unwrap();
+    ac.set_gpreg(GpReg64::RSP, 0x800).unwrap();
+    for v in [0x23, 0xff8, 0x202, 0x1b, 0x401000].iter() {
+        ac.push_u64(*v).unwrap();
+    }
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.int_ret_u64().unwrap();
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x401000);
+    assert_eq!(exe.ac.get_cpl().unwrap(), 3);
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().0, 0x23);
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RSP).unwrap(), 0xff8);
+    assert!(exe.ac.core.rflags.is_interrupt());
+}
+
+#[cfg(test)]
+#[test]
+fn stack_transfer_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.rm = 6;
+    parse.instr.disp = 0x100;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_gpreg(GpReg16::SP, 0x800).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x100), 0x00201234).unwrap();
+    assert_eq!(exe.get_m16_16().unwrap(), (0x20, 0x1234));
+
+    exe.ac.set_ip(0x55).unwrap();
+    exe.push_ip().unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg16::SP).unwrap(), 0x7fe);
+    assert_eq!(exe.pop_ip().unwrap(), 0x55);
+
+    exe.ac.push_u16(0x30).unwrap();
+    exe.ac.push_u16(0x10).unwrap();
+    exe.ret_far_u16(4).unwrap();
+    assert_eq!(exe.ac.get_sgreg(SgReg::CS).unwrap().1.base, 0x300);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x10);
+    assert_eq!(exe.ac.get_gpreg(GpReg16::SP).unwrap(), 0x804);
+
+    exe.ac.load_segment(SgReg::ES, 0x40).unwrap();
+    exe.push_sgreg(SgReg::ES).unwrap();
+    exe.pop_sgreg(SgReg::DS).unwrap();
+    assert_eq!(exe.ac.get_sgreg(SgReg::DS).unwrap().1.base, 0x400);
+    assert_eq!(exe.ac.get_gpreg(GpReg16::SP).unwrap(), 0x804);
+
+    exe.ac.mode = access::CpuMode::Long;
+    exe.ac.core.msr.efer.LMA = 1;
+    exe.ac.set_sgreg(SgReg::CS, 0x08, flat_segment(true, 0, true)).unwrap();
+    exe.ac.update_opadsize().unwrap();
+    exe.ac.update_stacksize().unwrap();
+    exe.ac.set_gpreg(GpReg64::RSP, 0x800).unwrap();
+    exe.push_ip().unwrap();
+    exe.push_sgreg(SgReg::FS).unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RSP).unwrap(), 0x7f0);
+    match exe.push_sgreg(SgReg::ES) {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        _ => panic!("expected #UD"),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn call_gate_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x2000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_gdtr(0x800, 0x37).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x808), 0x00cf9a000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x810), 0x00cf92000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x818), 0x00cffa000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x820), 0x00cff2000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x828), 0x0000ec0200081234).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x1004), 0x1800).unwrap();
+    exe.ac.set_data16((SgReg::DS, 0x1008), 0x10).unwrap();
+    exe.ac.core.dtregs.tr.cache.base = 0x1000;
+    exe.ac.core.dtregs.tr.cache.limit = 0x67;
+    exe.ac.core.dtregs.tr.attr = 0x8b;
+
+    exe.ac.core.cregs.0.PE = 1;
+    exe.ac.update_cpumode().unwrap();
+    exe.ac.set_sgreg(SgReg::CS, 0x1b, flat_segment(true, 3, false)).unwrap();
+    exe.ac.set_sgreg(SgReg::SS, 0x23, flat_segment(false, 3, false)).unwrap();
+    exe.ac.update_opadsize().unwrap();
+    exe.ac.update_stacksize().unwrap();
+    exe.ac.set_gpreg(GpReg32::ESP, 0xc00).unwrap();
+    exe.ac.push_u32(0x22).unwrap();
+    exe.ac.push_u32(0x11).unwrap();
+    exe.ac.set_ip(0x55).unwrap();
+
+    exe.call_far_u32(0x2b, 0).unwrap();
+    assert_eq!(exe.ac.get_cpl().unwrap(), 0);
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().0, 0x10);
+    assert_eq!(exe.ac.get_gpreg(GpReg32::ESP).unwrap(), 0x17e8);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x1234);
+    assert_eq!(exe.ac.get_data32((SgReg::SS, 0x17f0)).unwrap(), 0x11);
+    assert_eq!(exe.ac.get_data32((SgReg::SS, 0x17f8)).unwrap(), 0xbf8);
+
+    exe.ret_far_u32(8).unwrap();
+    assert_eq!(exe.ac.get_cpl().unwrap(), 3);
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().0, 0x23);
+    assert_eq!(exe.ac.get_gpreg(GpReg32::ESP).unwrap(), 0xc00);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x55);
+
+    exe.ac.set_data64((SgReg::DS, 0x830), 0x0040920000000fff).unwrap();
+    exe.ac.set_data16((SgReg::DS, 0x1008), 0x30).unwrap();
+    match exe.call_far_u32(0x2b, 0) {
+        Err(EmuException::CPUException(CPUException::SS(_))) => {},
+        _ => panic!("expected #SS"),
+    }
+    assert_eq!(exe.ac.get_cpl().unwrap(), 3);
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().0, 0x23);
+    assert_eq!(exe.ac.get_gpreg(GpReg32::ESP).unwrap(), 0xc00);
+}
+
+#[cfg(test)]
+#[test]
+fn paged_call_gate_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x5000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_gdtr(0x800, 0x2f).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x808), 0x00cf9a000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x810), 0x00cf92000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x818), 0x00cffa000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x820), 0x00cff2000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x828), 0x0000ec0200081234).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x1004), 0x1800).unwrap();
+    exe.ac.set_data16((SgReg::DS, 0x1008), 0x10).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x2bf8), 0x11).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x2bfc), 0x22).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x3000), 0x4007).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x4000), 0x0003).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x4004), 0x1003).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x4008), 0x2007).unwrap();
+    exe.ac.core.dtregs.tr.cache.base = 0x1000;
+    exe.ac.core.dtregs.tr.cache.limit = 0x67;
+    exe.ac.core.dtregs.tr.attr = 0x8b;
+
+    exe.ac.core.cregs.0.PE = 1;
+    exe.ac.core.cregs.0.PG = 1;
+    exe.ac.core.cregs.3.from_u64(0x3000);
+    exe.ac.update_cpumode().unwrap();
+    exe.ac.update_pgmode().unwrap();
+    exe.ac.set_sgreg(SgReg::CS, 0x1b, flat_segment(true, 3, false)).unwrap();
+    exe.ac.set_sgreg(SgReg::SS, 0x23, flat_segment(false, 3, false)).unwrap();
+    exe.ac.update_opadsize().unwrap();
+    exe.ac.update_stacksize().unwrap();
+    exe.ac.set_gpreg(GpReg32::ESP, 0x2bf8).unwrap();
+    exe.ac.set_ip(0x55).unwrap();
+
+    exe.call_far_u32(0x2b, 0).unwrap();
+    assert_eq!(exe.ac.get_cpl().unwrap(), 0);
+    assert_eq!(exe.ac.get_gpreg(GpReg32::ESP).unwrap(), 0x17e8);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x1234);
+    assert_eq!(exe.ac.get_data32((SgReg::SS, 0x17f0)).unwrap(), 0x11);
+    assert_eq!(exe.ac.get_data32((SgReg::SS, 0x17f4)).unwrap(), 0x22);
+    assert_eq!(exe.ac.get_data32((SgReg::SS, 0x17f8)).unwrap(), 0x2bf8);
+}
+
+#[cfg(test)]
+#[test]
+fn sysenter_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.core.cregs.0.PE = 1;
+    exe.ac.core.rflags.set_v8086(true);
+    exe.ac.update_cpumode().unwrap();
+    assert!(exe.ac.test_cpumode(access::CpuMode::V8086));
+
+    exe.ac.core.msr.sysenter_cs.from_u64(0x08);
+    exe.ac.core.msr.sysenter_eip.from_u64(0x1234);
+    exe.sysenter().unwrap();
+    assert!(exe.ac.test_cpumode(access::CpuMode::Protected));
+    assert_eq!(exe.ac.get_cpl().unwrap(), 0);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x1234);
+}
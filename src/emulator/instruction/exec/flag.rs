@@ -120,12 +120,12 @@ impl<'a> super::Exec<'a> {
         Ok(())
     }
 
-    pub fn update_rflags_shl<T: Into<u64> + num::traits::WrappingShl + Copy>(&mut self, v: T, c: u32) -> Result<(), EmuException> {
+    pub fn update_rflags_shl<T: Into<u64> + num::traits::CheckedShl + num::traits::Zero + Copy>(&mut self, v: T, c: u32) -> Result<(), EmuException> {
         if c == 0 { return Ok(()); }
 
-        let result = v.wrapping_shl(c);
+        let result = v.checked_shl(c).unwrap_or_else(T::zero);
         let sf = Self::check_msb(result);
-        let cf = Self::check_msb(v.wrapping_shl(c-1));
+        let cf = Self::check_msb(v.checked_shl(c-1).unwrap_or_else(T::zero));
         let result = result.into();
 
         let rf = &mut self.ac.core.rflags;
@@ -139,12 +139,12 @@ impl<'a> super::Exec<'a> {
         Ok(())
     }
 
-    pub fn update_rflags_shr<T: Into<u64> + num::traits::WrappingShr + Copy>(&mut self, v: T, c: u32) -> Result<(), EmuException> {
+    pub fn update_rflags_shr<T: Into<u64> + num::traits::CheckedShr + num::traits::Zero + Copy>(&mut self, v: T, c: u32) -> Result<(), EmuException> {
         if c == 0 { return Ok(()); }
 
-        let result = v.wrapping_shr(c);
+        let result = v.checked_shr(c).unwrap_or_else(T::zero);
         let sf = Self::check_msb(result);
-        let cf = Self::check_lsb(v.wrapping_shr(c-1));
+        let cf = Self::check_lsb(v.checked_shr(c-1).unwrap_or_else(T::zero));
         let result = result.into();
 
         let rf = &mut self.ac.core.rflags;
@@ -161,9 +161,10 @@ impl<'a> super::Exec<'a> {
     pub fn update_rflags_sar<T: Into<i64> + num::traits::WrappingShr + Copy>(&mut self, v: T, c: u32) -> Result<(), EmuException> {
         if c == 0 { return Ok(()); }
 
-        let result = v.wrapping_shr(c).into();
+        let n = c.min(std::mem::size_of::<T>() as u32 * 8 - 1);
+        let result = v.wrapping_shr(n).into();
         let sf = result < 0;
-        let cf = v.wrapping_shr(c-1).into() & 1 != 0;
+        let cf = v.wrapping_shr(n.min(c-1)).into() & 1 != 0;
 
         let rf = &mut self.ac.core.rflags;
         rf.set_carry(cf);
@@ -176,6 +177,74 @@ impl<'a> super::Exec<'a> {
         Ok(())
     }
 
+    pub fn update_rflags_rot<T: Into<u64> + Copy>(&mut self, v: T, result: T, cf: bool, c: u32) -> Result<(), EmuException> {
+        if c == 0 { return Ok(()); }
+
+        let rf = &mut self.ac.core.rflags;
+        rf.set_carry(cf);
+        if c == 1 {
+            rf.set_overflow(Self::check_msb(v) ^ Self::check_msb(result));
+        }
+        Ok(())
+    }
+
+    pub fn update_rflags_shd<T: Into<u64> + Copy>(&mut self, v: T, result: T, cf: bool, c: u32) -> Result<(), EmuException> {
+        if c == 0 { return Ok(()); }
+
+        let sf = Self::check_msb(result);
+        let of = Self::check_msb(v) ^ sf;
+        let result = result.into();
+
+        let rf = &mut self.ac.core.rflags;
+        rf.set_carry(cf);
+        rf.set_parity(Self::check_parity(result as u8));
+        rf.set_zero(result == 0);
+        rf.set_sign(sf);
+        if c == 1 {
+            rf.set_overflow(of);
+        }
+        Ok(())
+    }
+
+    pub fn update_rflags_bt<T: Into<u64> + std::ops::BitAnd<Output = T> + Copy>(&mut self, v: T, mask: T) -> Result<(), EmuException> {
+        let cf = (v & mask).into() != 0;
+
+        self.ac.core.rflags.set_carry(cf);
+        Ok(())
+    }
+
+    pub fn update_rflags_bsf<T: Into<u64> + Copy>(&mut self, v: T) -> Result<(), EmuException> {
+        self.ac.core.rflags.set_zero(v.into() == 0);
+        Ok(())
+    }
+
+    pub fn update_rflags_cnt<T: Into<u64> + Copy>(&mut self, v: T, result: u32) -> Result<(), EmuException> {
+        let rf = &mut self.ac.core.rflags;
+        rf.set_carry(v.into() == 0);
+        rf.set_zero(result == 0);
+        Ok(())
+    }
+
+    pub fn update_rflags_popcnt<T: Into<u64> + Copy>(&mut self, v: T) -> Result<(), EmuException> {
+        let rf = &mut self.ac.core.rflags;
+        rf.set_carry(false);
+        rf.set_parity(false);
+        rf.set_zero(v.into() == 0);
+        rf.set_sign(false);
+        rf.set_overflow(false);
+        Ok(())
+    }
+
+    pub fn update_rflags_bcd(&mut self, v: u8, cf: bool, af: bool) -> Result<(), EmuException> {
+        let rf = &mut self.ac.core.rflags;
+        rf.set_carry(cf);
+        rf.set_parity(Self::check_parity(v));
+        rf.set_adjust(af);
+        rf.set_zero(v == 0);
+        rf.set_sign(Self::check_msb(v));
+        Ok(())
+    }
+
     pub fn check_rflags_o(&self) -> Result<bool, EmuException> {
         let rf = self.ac.core.rflags;
         Ok(rf.is_overflow())
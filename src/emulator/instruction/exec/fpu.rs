@@ -0,0 +1,813 @@
+use std::cmp::Ordering;
+use std::f64::consts;
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+use crate::emulator::instruction::Instruction;
+use crate::hardware::processor::fpu::*;
+
+const INDEFINITE: f64 = -f64::NAN;
+const INDEFINITE_RAW: [u8; 10] = [0, 0, 0, 0, 0, 0, 0, 0xc0, 0xff, 0xff];
+
+pub(super) fn round_rc(v: f64, rc: u8) -> f64 {
+    match rc {
+        0 if (v - v.trunc()).abs() == 0.5 => 2.0 * (v / 2.0).round(),
+        0 => v.round(),
+        1 => v.floor(),
+        2 => v.ceil(),
+        _ => v.trunc(),
+    }
+}
+
+pub(super) fn round_dir(v: f64, err: Ordering, rc: u8) -> f64 {
+    match (rc, err) {
+        (1, Ordering::Less) => next_toward(v, false),
+        (2, Ordering::Greater) => next_toward(v, true),
+        (3, Ordering::Less) if v > 0.0 => next_toward(v, false),
+        (3, Ordering::Greater) if v < 0.0 => next_toward(v, true),
+        _ => v,
+    }
+}
+
+fn next_toward(v: f64, up: bool) -> f64 {
+    if v.is_nan() || (v.is_infinite() && (v > 0.0) == up) { return v; }
+    if v == 0.0 {
+        let t = f64::from_bits(1);
+        return if up { t } else { -t };
+    }
+    let bits = v.to_bits();
+    f64::from_bits(if (v > 0.0) == up { bits + 1 } else { bits - 1 })
+}
+
+fn round_precision(v: f64, bits: i32, rc: u8) -> f64 {
+    if !v.is_finite() || v == 0.0 { return v; }
+    let s = bits - 1 - exponent(v);
+    ldexp(round_rc(ldexp(v, s), rc), -s)
+}
+
+fn sum_err(a: f64, b: f64, v: f64) -> Ordering {
+    let bb = v - a;
+    ((a - (v - bb)) + (b - bb)).partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+}
+
+fn div_err(a: f64, b: f64, v: f64) -> Ordering {
+    let r = -v.mul_add(b, -a);
+    if b < 0.0 { 0.0.partial_cmp(&r) } else { r.partial_cmp(&0.0) }.unwrap_or(Ordering::Equal)
+}
+
+fn exponent(v: f64) -> i32 {
+    let bits = v.to_bits();
+    match ((bits >> 52) & 0x7ff) as i32 {
+        0 => exponent(v * 2f64.powi(64)) - 64,
+        e => e - 1023,
+    }
+}
+
+impl<'a> super::Exec<'a> {
+    pub fn fpu_wait(&mut self) -> Result<(), EmuException> {
+        let cr0 = &self.ac.core.cregs.0;
+        if cr0.MP == 1 && cr0.TS == 1 {
+            return Err(EmuException::CPUException(CPUException::NM));
+        }
+        self.fpu_check_pending()
+    }
+
+    pub fn fpu_esc(&mut self, esc: u8) -> Result<(), EmuException> {
+        let modrm = self.idata.modrm;
+        let (reg, rm, mem) = (modrm.reg, modrm.rm as usize, modrm.mod_ != 3);
+
+        let cr0 = &self.ac.core.cregs.0;
+        if cr0.EM == 1 || cr0.TS == 1 {
+            return Err(EmuException::CPUException(CPUException::NM));
+        }
+
+        let nowait = match (esc, reg, mem) {
+            (0xd9, 4, true) | (0xd9, 6, true) | (0xd9, 7, true) |
+            (0xdd, 4, true) | (0xdd, 6, true) | (0xdd, 7, true) => true,
+            (0xdb, 4, false) => rm == 2 || rm == 3,
+            (0xdf, 4, false) => rm == 0,
+            _ => false,
+        };
+        if !nowait {
+            self.fpu_check_pending()?;
+            self.fpu_update_last(esc, mem)?;
+        }
+
+        if mem { self.fpu_mem(esc, reg) } else { self.fpu_reg(esc, reg, rm) }
+    }
+
+    fn fpu_check_pending(&mut self) -> Result<(), EmuException> {
+        if self.ac.core.fpu.status.is_pending() && self.ac.core.cregs.0.NE == 1 {
+            return Err(EmuException::CPUException(CPUException::MF));
+        }
+        Ok(())
+    }
+
+    fn fpu_update_last(&mut self, esc: u8, mem: bool) -> Result<(), EmuException> {
+        let modrm = self.idata.modrm;
+        let fip = self.ac.get_ip()? - self.idata.len;
+        let fcs = self.ac.get_sgreg(SgReg::CS)?.0;
+        let dp = if mem {
+            let (sg, adr) = self.get_m()?;
+            Some((adr, self.ac.get_sgreg(sg)?.0))
+        } else { None };
+
+        let fpu = &mut self.ac.core.fpu;
+        fpu.fip = fip;
+        fpu.fcs = fcs;
+        fpu.fop = (((esc & 7) as u16) << 8) + ((modrm.mod_ as u16) << 6) + ((modrm.reg as u16) << 3) + modrm.rm as u16;
+        if let Some((fdp, fds)) = dp {
+            fpu.fdp = fdp;
+            fpu.fds = fds;
+        }
+        Ok(())
+    }
+
+    fn fpu_mem(&mut self, esc: u8, reg: u8) -> Result<(), EmuException> {
+        let m = self.get_m()?;
+
+        match (esc, reg) {
+            (0xd8, _) => { let v = f32::from_bits(self.ac.get_data32(m)?) as f64; self.fpu_arith(reg, Some(v), 0, false) },
+            (0xda, _) => { let v = self.ac.get_data32(m)? as i32 as f64; self.fpu_arith(reg, Some(v), 0, false) },
+            (0xdc, _) => { let v = f64::from_bits(self.ac.get_data64(m)?); self.fpu_arith(reg, Some(v), 0, false) },
+            (0xde, _) => { let v = self.ac.get_data16(m)? as i16 as f64; self.fpu_arith(reg, Some(v), 0, false) },
+
+            (0xd9, 0) => { let v = f32::from_bits(self.ac.get_data32(m)?) as f64; self.fpu_load(v) },
+            (0xdd, 0) => { let v = f64::from_bits(self.ac.get_data64(m)?); self.fpu_load(v) },
+            (0xdb, 5) => { let raw = self.fpu_get_m80(m)?; self.fpu_load_raw(raw) },
+            (0xdf, 0) => { let v = self.ac.get_data16(m)? as i16 as f64; self.fpu_load(v) },
+            (0xdb, 0) => { let v = self.ac.get_data32(m)? as i32 as f64; self.fpu_load(v) },
+            (0xdf, 5) => { let v = self.ac.get_data64(m)? as i64 as f64; self.fpu_load(v) },
+            (0xdf, 4) => { let v = self.fpu_get_bcd(m)?; self.fpu_load(v) },
+
+            (0xd9, 2) | (0xd9, 3) => {
+                if let Some(raw) = self.fpu_store_raw()? {
+                    let rc = self.ac.core.fpu.control.RC;
+                    let v = round_precision(self.fpu_narrow(raw), 24, rc);
+                    self.ac.set_data32(m, (v as f32).to_bits())?;
+                    if reg == 3 { self.ac.core.fpu.pop(); }
+                }
+                Ok(())
+            },
+            (0xdd, 2) | (0xdd, 3) => {
+                if let Some(raw) = self.fpu_store_raw()? {
+                    let v = self.fpu_narrow(raw);
+                    self.ac.set_data64(m, v.to_bits())?;
+                    if reg == 3 { self.ac.core.fpu.pop(); }
+                }
+                Ok(())
+            },
+            (0xdb, 7) => {
+                if let Some(raw) = self.fpu_store_raw()? {
+                    self.fpu_set_m80(m, raw)?;
+                    self.ac.core.fpu.pop();
+                }
+                Ok(())
+            },
+
+            (0xdf, 1) | (0xdf, 2) | (0xdf, 3) => {
+                if let Some(v) = self.fpu_store_int(i16::MIN as f64, i16::MAX as f64, reg == 1)? {
+                    self.ac.set_data16(m, v as u16)?;
+                    if reg != 2 { self.ac.core.fpu.pop(); }
+                }
+                Ok(())
+            },
+            (0xdb, 1) | (0xdb, 2) | (0xdb, 3) => {
+                if let Some(v) = self.fpu_store_int(i32::MIN as f64, i32::MAX as f64, reg == 1)? {
+                    self.ac.set_data32(m, v as u32)?;
+                    if reg != 2 { self.ac.core.fpu.pop(); }
+                }
+                Ok(())
+            },
+            (0xdd, 1) | (0xdf, 7) => {
+                if let Some(v) = self.fpu_store_int(i64::MIN as f64, i64::MAX as f64, reg == 1)? {
+                    self.ac.set_data64(m, v as u64)?;
+                    self.ac.core.fpu.pop();
+                }
+                Ok(())
+            },
+            (0xdf, 6) => {
+                let fpu = &mut self.ac.core.fpu;
+                match fpu.get_st(0).map(|v| round_rc(v, fpu.control.RC)) {
+                    Some(v) if v.abs() <= 999999999999999999.0 => self.fpu_set_bcd(m, v as i64)?,
+                    st0 => {
+                        let ex = if st0.is_none() { FPUException::IE | FPUException::SF } else { FPUException::IE };
+                        if fpu.raise(ex) { return Ok(()); }
+                        self.fpu_set_m80(m, INDEFINITE_RAW)?;
+                    },
+                }
+                self.ac.core.fpu.pop();
+                Ok(())
+            },
+
+            (0xd9, 4) => { self.fpu_load_env(m)?; Ok(()) },
+            (0xd9, 5) => { let v = self.ac.get_data16(m)?; self.ac.core.fpu.load_control(v); Ok(()) },
+            (0xd9, 6) => { self.fpu_store_env(m)?; Ok(()) },
+            (0xd9, 7) => { let v = self.ac.core.fpu.control.to_u16(); self.ac.set_data16(m, v) },
+            (0xdd, 4) => {
+                let size = self.fpu_load_env(m)?;
+                for i in 0..8 {
+                    let raw = self.fpu_get_m80((m.0, m.1 + size + i*10))?;
+                    self.ac.core.fpu.set_raw(i as usize, raw);
+                }
+                let tw = self.ac.core.fpu.tag_word();
+                self.ac.core.fpu.set_tag_word(tw);
+                Ok(())
+            },
+            (0xdd, 6) => {
+                let size = self.fpu_store_env(m)?;
+                for i in 0..8 {
+                    let raw = self.ac.core.fpu.get_raw(i as usize);
+                    self.fpu_set_m80((m.0, m.1 + size + i*10), raw)?;
+                }
+                self.ac.core.fpu.init();
+                Ok(())
+            },
+            (0xdd, 7) => { let v = self.ac.core.fpu.status.to_u16(); self.ac.set_data16(m, v) },
+
+            _ => Err(EmuException::CPUException(CPUException::UD)),
+        }
+    }
+
+    fn fpu_reg(&mut self, esc: u8, reg: u8, rm: usize) -> Result<(), EmuException> {
+        let sti = self.ac.core.fpu.get_st(rm);
+
+        match (esc, reg) {
+            (0xd8, _) => self.fpu_arith(reg, sti, 0, false),
+            (0xdc, 0) | (0xdc, 1) | (0xdc, 4..=7) => self.fpu_arith(reg, sti, rm, false),
+            (0xde, 0) | (0xde, 1) | (0xde, 4..=7) => self.fpu_arith(reg, sti, rm, true),
+
+            (0xd9, 0) => match self.ac.core.fpu.get_st_raw(rm) {
+                Some(raw) => self.fpu_load_raw(raw),
+                None => self.fpu_underflow(None, false),
+            },
+            (0xd9, 1) => {
+                let fpu = &mut self.ac.core.fpu;
+                let (st0, sti) = (fpu.get_st_raw(0), fpu.get_st_raw(rm));
+                if let (Some(a), Some(b)) = (st0, sti) {
+                    fpu.set_st_raw(0, b);
+                    fpu.set_st_raw(rm, a);
+                } else if fpu.raise(FPUException::IE | FPUException::SF) {
+                    fpu.status.C1 = 0;
+                } else {
+                    fpu.set_st_raw(0, sti.unwrap_or(INDEFINITE_RAW));
+                    fpu.set_st_raw(rm, st0.unwrap_or(INDEFINITE_RAW));
+                }
+                Ok(())
+            },
+            (0xd9, 2) if rm == 0 => Ok(()),
+            (0xd9, 4) => self.fpu_d9_4(rm),
+            (0xd9, 5) => {
+                let v = match rm {
+                    0 => 1.0,
+                    1 => consts::LOG2_10,
+                    2 => consts::LOG2_E,
+                    3 => consts::PI,
+                    4 => consts::LOG10_2,
+                    5 => consts::LN_2,
+                    6 => 0.0,
+                    _ => return Err(EmuException::CPUException(CPUException::UD)),
+                };
+                self.fpu_load(v)
+            },
+            (0xd9, 6) => self.fpu_d9_6(rm),
+            (0xd9, 7) => self.fpu_d9_7(rm),
+
+            (0xda, 0..=3) | (0xdb, 0..=3) => {
+                let rf = &self.ac.core.rflags;
+                let cond = match reg {
+                    0 => rf.is_carry(),
+                    1 => rf.is_zero(),
+                    2 => rf.is_carry() || rf.is_zero(),
+                    _ => rf.is_parity(),
+                };
+                if let Some(raw) = self.ac.core.fpu.get_st_raw(rm) {
+                    if cond ^ (esc == 0xdb) { self.ac.core.fpu.set_st_raw(0, raw); }
+                    Ok(())
+                } else {
+                    self.fpu_underflow(None, false)
+                }
+            },
+            (0xda, 5) if rm == 1 => { self.fpu_compare(sti, true)?; self.fpu_pop(2) },
+            (0xde, 3) if rm == 1 => { self.fpu_compare(sti, false)?; self.fpu_pop(2) },
+            (0xdc, 2) | (0xdd, 4) => self.fpu_compare(sti, reg == 4),
+            (0xdc, 3) | (0xdd, 5) => { self.fpu_compare(sti, reg == 5)?; self.fpu_pop(1) },
+            (0xdb, 5) | (0xdb, 6) => self.fpu_compare_eflags(sti, reg == 5),
+            (0xdf, 5) | (0xdf, 6) => { self.fpu_compare_eflags(sti, reg == 5)?; self.fpu_pop(1) },
+
+            (0xdb, 4) => {
+                match rm {
+                    0 | 1 | 4 => {},
+                    2 => self.ac.core.fpu.clear_exceptions(),
+                    3 => self.ac.core.fpu.init(),
+                    _ => return Err(EmuException::CPUException(CPUException::UD)),
+                }
+                Ok(())
+            },
+            (0xdd, 0) => { self.ac.core.fpu.free_st(rm); Ok(()) },
+            (0xdd, 2) | (0xdd, 3) => {
+                match self.ac.core.fpu.get_st_raw(0) {
+                    Some(raw) => self.ac.core.fpu.set_st_raw(rm, raw),
+                    None => {
+                        if self.ac.core.fpu.raise(FPUException::IE | FPUException::SF) { return Ok(()); }
+                        self.ac.core.fpu.set_st(rm, INDEFINITE);
+                    },
+                }
+                if reg == 3 { self.ac.core.fpu.pop(); }
+                Ok(())
+            },
+            (0xdf, 4) if rm == 0 => { let v = self.ac.core.fpu.status.to_u16(); self.set_ax(v) },
+
+            _ => Err(EmuException::CPUException(CPUException::UD)),
+        }
+    }
+
+    fn fpu_d9_4(&mut self, rm: usize) -> Result<(), EmuException> {
+        let st0 = self.ac.core.fpu.get_st(0);
+        let v = match st0 {
+            Some(v) => v,
+            None => {
+                if rm == 5 {
+                    self.ac.core.fpu.status.set_cc(true, false, false, true);
+                    return Ok(());
+                }
+                return self.fpu_underflow(Some(0), false);
+            },
+        };
+
+        match rm {
+            0 | 1 => {
+                let fpu = &mut self.ac.core.fpu;
+                let mut raw = fpu.get_raw(0);
+                raw[9] = if rm == 0 { raw[9] ^ 0x80 } else { raw[9] & 0x7f };
+                fpu.set_st_raw(0, raw);
+            },
+            4 => self.fpu_compare(Some(0.0), false)?,
+            5 => {
+                let (c3, c2, c0) = if v.is_nan() { (false, false, true) }
+                    else if v.is_infinite() { (false, true, true) }
+                    else if v == 0.0 { (true, false, false) }
+                    else if !v.is_normal() { (true, true, false) }
+                    else { (false, true, false) };
+                self.ac.core.fpu.status.set_cc(c3, c2, v.is_sign_negative(), c0);
+            },
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        }
+        Ok(())
+    }
+
+    fn fpu_d9_6(&mut self, rm: usize) -> Result<(), EmuException> {
+        let (st0, st1) = (self.ac.core.fpu.get_st(0), self.ac.core.fpu.get_st(1));
+
+        match rm {
+            6 => { self.ac.core.fpu.dec_top(); return Ok(()); },
+            7 => { self.ac.core.fpu.inc_top(); return Ok(()); },
+            _ => {},
+        }
+
+        let x = match (rm, st0, st1) {
+            (0, Some(x), _) | (2, Some(x), _) | (4, Some(x), _) => x,
+            (1, Some(x), Some(_)) | (3, Some(x), Some(_)) | (5, Some(x), Some(_)) => x,
+            (0, ..) | (2, ..) | (4, ..) => return self.fpu_underflow(Some(0), false),
+            (5, ..) => return self.fpu_underflow(Some(0), false),
+            _ => return self.fpu_underflow(Some(1), true),
+        };
+
+        match rm {
+            0 => self.fpu_result(0, (x * consts::LN_2).exp_m1(), FPUException::empty(), false),
+            1 => {
+                let y = st1.unwrap();
+                let ex = if x < 0.0 || x.is_nan() { FPUException::IE } else if x == 0.0 { FPUException::ZE } else { FPUException::empty() };
+                self.fpu_result(1, y * x.log2(), ex, true)
+            },
+            2 => {
+                if x.abs() >= 2f64.powi(63) {
+                    self.ac.core.fpu.status.C2 = 1;
+                    return Ok(());
+                }
+                self.ac.core.fpu.status.C2 = 0;
+                self.fpu_result(0, x.tan(), FPUException::empty(), false)?;
+                self.fpu_load(1.0)
+            },
+            3 => self.fpu_result(1, st1.unwrap().atan2(x), FPUException::empty(), true),
+            4 => {
+                if x == 0.0 {
+                    if self.ac.core.fpu.raise(FPUException::ZE) { return Ok(()); }
+                    self.ac.core.fpu.set_st(0, f64::NEG_INFINITY);
+                    return self.fpu_load(x);
+                }
+                if !x.is_finite() {
+                    return self.fpu_load(x);
+                }
+                let e = exponent(x);
+                self.ac.core.fpu.set_st(0, e as f64);
+                self.fpu_load(ldexp(x, -e))
+            },
+            5 => self.fpu_partial_rem(x, st1, true),
+            _ => Err(EmuException::CPUException(CPUException::UD)),
+        }
+    }
+
+    fn fpu_d9_7(&mut self, rm: usize) -> Result<(), EmuException> {
+        let (st0, st1) = (self.ac.core.fpu.get_st(0), self.ac.core.fpu.get_st(1));
+
+        let x = match (rm, st0, st1) {
+            (0, Some(x), Some(_)) | (1, Some(x), Some(_)) | (5, Some(x), Some(_)) => x,
+            (1, ..) => return self.fpu_underflow(Some(1), true),
+            (0, ..) | (5, ..) => return self.fpu_underflow(Some(0), false),
+            (_, Some(x), _) => x,
+            _ => return self.fpu_underflow(Some(0), false),
+        };
+        let trig = rm == 3 || rm == 6 || rm == 7;
+        if trig && x.abs() >= 2f64.powi(63) {
+            self.ac.core.fpu.status.C2 = 1;
+            return Ok(());
+        }
+        self.ac.core.fpu.status.C2 = 0;
+
+        match rm {
+            0 => self.fpu_partial_rem(x, st1, false),
+            1 => {
+                let ex = if x <= -1.0 || x.is_nan() { FPUException::IE } else { FPUException::empty() };
+                self.fpu_result(1, st1.unwrap() * x.ln_1p() / consts::LN_2, ex, true)
+            },
+            2 => {
+                let ex = if x < 0.0 || x.is_nan() { FPUException::IE } else { FPUException::empty() };
+                let v = x.sqrt();
+                let err = (-v.mul_add(v, -x)).partial_cmp(&0.0).unwrap_or(Ordering::Equal);
+                self.fpu_result(0, round_dir(v, err, self.ac.core.fpu.control.RC), ex, false)
+            },
+            3 => {
+                self.fpu_result(0, x.sin(), FPUException::empty(), false)?;
+                self.fpu_load(x.cos())
+            },
+            4 => { let rc = self.ac.core.fpu.control.RC; self.fpu_result(0, round_rc(x, rc), FPUException::empty(), false) },
+            5 => {
+                let s = st1.unwrap().trunc().max(i32::MIN as f64).min(i32::MAX as f64);
+                self.fpu_result(0, ldexp(x, s as i32), FPUException::empty(), false)
+            },
+            6 => self.fpu_result(0, x.sin(), FPUException::empty(), false),
+            7 => self.fpu_result(0, x.cos(), FPUException::empty(), false),
+            _ => Err(EmuException::CPUException(CPUException::UD)),
+        }
+    }
+
+    fn fpu_partial_rem(&mut self, x: f64, st1: Option<f64>, ieee: bool) -> Result<(), EmuException> {
+        let y = st1.unwrap();
+        if y == 0.0 || x.is_infinite() || x.is_nan() || y.is_nan() {
+            return self.fpu_result(0, INDEFINITE, FPUException::IE, false);
+        }
+
+        let mut r = x % y;
+        let mut q = ((x - r) / y).abs().round();
+        if ieee && (2.0 * r.abs() > y.abs() || (2.0 * r.abs() == y.abs() && q % 2.0 == 1.0)) {
+            r -= y.abs().copysign(x);
+            q += 1.0;
+        }
+        let q = q as u64;
+
+        self.ac.core.fpu.status.set_cc(q & 2 != 0, false, q & 1 != 0, q & 4 != 0);
+        self.fpu_result(0, r, FPUException::empty(), false)
+    }
+
+    fn fpu_arith(&mut self, op: u8, src: Option<f64>, dst: usize, pop: bool) -> Result<(), EmuException> {
+        let (a, b) = match (self.ac.core.fpu.get_st(0), src) {
+            (Some(a), Some(b)) => (a, b),
+            _ if op == 2 || op == 3 => {
+                self.fpu_compare(src, false)?;
+                return if op == 3 { self.fpu_pop(1) } else { Ok(()) };
+            },
+            _ => return self.fpu_underflow(Some(dst), pop),
+        };
+
+        let (v, err) = match op {
+            0 => { let v = a + b; (v, sum_err(a, b, v)) },
+            1 => { let v = a * b; (v, a.mul_add(b, -v).partial_cmp(&0.0).unwrap_or(Ordering::Equal)) },
+            2 => return self.fpu_compare(Some(b), false),
+            3 => { self.fpu_compare(Some(b), false)?; return self.fpu_pop(1); },
+            4 => { let v = a - b; (v, sum_err(a, -b, v)) },
+            5 => { let v = b - a; (v, sum_err(b, -a, v)) },
+            6 => { let v = a / b; (v, div_err(a, b, v)) },
+            _ => { let v = b / a; (v, div_err(b, a, v)) },
+        };
+        let v = if a.is_finite() && b.is_finite() { round_dir(v, err, self.ac.core.fpu.control.RC) } else { v };
+
+        let ex = if v.is_nan() && !a.is_nan() && !b.is_nan() {
+            FPUException::IE
+        } else if (op == 6 && b == 0.0 || op == 7 && a == 0.0) && v.is_infinite() && a.is_finite() && b.is_finite() {
+            FPUException::ZE
+        } else if v.is_infinite() && a.is_finite() && b.is_finite() {
+            FPUException::OE | FPUException::PE
+        } else {
+            FPUException::empty()
+        };
+        self.fpu_result(dst, v, ex, pop)
+    }
+
+    fn fpu_result(&mut self, dst: usize, v: f64, ex: FPUException, pop: bool) -> Result<(), EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        if !ex.is_empty() && fpu.raise(ex) {
+            return Ok(());
+        }
+        // results are computed as f64, so extended precision (PC=3) behaves as double precision
+        let v = if fpu.control.PC == 0 { round_precision(v, 24, fpu.control.RC) } else { v };
+        fpu.set_st(dst, if ex.contains(FPUException::IE) { INDEFINITE } else { v });
+        if pop { fpu.pop(); }
+        Ok(())
+    }
+
+    fn fpu_underflow(&mut self, dst: Option<usize>, pop: bool) -> Result<(), EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        fpu.status.C1 = 0;
+        if fpu.raise(FPUException::IE | FPUException::SF) {
+            return Ok(());
+        }
+        match dst {
+            Some(i) => fpu.set_st(i, INDEFINITE),
+            None => { fpu.push(INDEFINITE); },
+        }
+        if pop { fpu.pop(); }
+        Ok(())
+    }
+
+    fn fpu_pop(&mut self, n: usize) -> Result<(), EmuException> {
+        for _ in 0..n { self.ac.core.fpu.pop(); }
+        Ok(())
+    }
+
+    fn fpu_load(&mut self, v: f64) -> Result<(), EmuException> {
+        self.fpu_load_raw(f64_to_f80(v))
+    }
+
+    fn fpu_load_raw(&mut self, raw: [u8; 10]) -> Result<(), EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        if fpu.get_st(7).is_some() {
+            fpu.status.C1 = 1;
+            if fpu.raise(FPUException::IE | FPUException::SF) { return Ok(()); }
+            fpu.push_raw(INDEFINITE_RAW);
+        } else {
+            fpu.status.C1 = 0;
+            fpu.push_raw(raw);
+        }
+        Ok(())
+    }
+
+    fn fpu_store_raw(&mut self) -> Result<Option<[u8; 10]>, EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        match fpu.get_st_raw(0) {
+            Some(raw) => Ok(Some(raw)),
+            None => {
+                fpu.status.C1 = 0;
+                Ok(if fpu.raise(FPUException::IE | FPUException::SF) { None } else { Some(INDEFINITE_RAW) })
+            },
+        }
+    }
+
+    fn fpu_narrow(&self, raw: [u8; 10]) -> f64 {
+        let v = f80_to_f64(raw);
+        round_dir(v, cmp_f80(raw, v), self.ac.core.fpu.control.RC)
+    }
+
+    fn fpu_store_int(&mut self, min: f64, max: f64, trunc: bool) -> Result<Option<i64>, EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        let (v, ex) = match fpu.get_st(0) {
+            Some(v) => (round_rc(v, if trunc { 3 } else { fpu.control.RC }), FPUException::IE),
+            None => (f64::NAN, FPUException::IE | FPUException::SF),
+        };
+
+        if v.is_nan() || v < min || v > max {
+            if fpu.raise(ex) { return Ok(None); }
+            return Ok(Some(min as i64));
+        }
+        Ok(Some(v as i64))
+    }
+
+    fn fpu_compare(&mut self, src: Option<f64>, quiet: bool) -> Result<(), EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        let (a, b) = match (fpu.get_st(0), src) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                if !fpu.raise(FPUException::IE | FPUException::SF) {
+                    fpu.status.set_cc(true, true, false, true);
+                }
+                return Ok(());
+            },
+        };
+
+        if a.is_nan() || b.is_nan() {
+            if !quiet && fpu.raise(FPUException::IE) { return Ok(()); }
+            fpu.status.set_cc(true, true, false, true);
+        } else {
+            fpu.status.set_cc(a == b, false, false, a < b);
+        }
+        Ok(())
+    }
+
+    fn fpu_compare_eflags(&mut self, src: Option<f64>, quiet: bool) -> Result<(), EmuException> {
+        let fpu = &mut self.ac.core.fpu;
+        let (zf, pf, cf) = match (fpu.get_st(0), src) {
+            (Some(a), Some(b)) if !a.is_nan() && !b.is_nan() => (a == b, false, a < b),
+            (Some(_), Some(_)) if quiet => (true, true, true),
+            (Some(_), Some(_)) => {
+                if fpu.raise(FPUException::IE) { return Ok(()); }
+                (true, true, true)
+            },
+            _ => {
+                if fpu.raise(FPUException::IE | FPUException::SF) { return Ok(()); }
+                (true, true, true)
+            },
+        };
+        fpu.status.C1 = 0;
+
+        let rf = &mut self.ac.core.rflags;
+        rf.set_zero(zf);
+        rf.set_parity(pf);
+        rf.set_carry(cf);
+        rf.set_overflow(false);
+        rf.set_sign(false);
+        Ok(())
+    }
+
+    fn fpu_get_m80(&self, m: (SgReg, u64)) -> Result<[u8; 10], EmuException> {
+        let mut raw = [0; 10];
+        raw[..8].copy_from_slice(&self.ac.get_data64(m)?.to_le_bytes());
+        raw[8..].copy_from_slice(&self.ac.get_data16((m.0, m.1 + 8))?.to_le_bytes());
+        Ok(raw)
+    }
+
+    fn fpu_set_m80(&mut self, m: (SgReg, u64), raw: [u8; 10]) -> Result<(), EmuException> {
+        let mut lo = [0; 8];
+        lo.copy_from_slice(&raw[..8]);
+        self.ac.set_data64(m, u64::from_le_bytes(lo))?;
+        self.ac.set_data16((m.0, m.1 + 8), u16::from_le_bytes([raw[8], raw[9]]))
+    }
+
+    fn fpu_get_bcd(&self, m: (SgReg, u64)) -> Result<f64, EmuException> {
+        let raw = self.fpu_get_m80(m)?;
+        let v = raw[..9].iter().rev().fold(0u64, |v, b| v * 100 + ((b >> 4) * 10 + (b & 0xf)) as u64) as f64;
+        Ok(if raw[9] & 0x80 != 0 { -v } else { v })
+    }
+
+    fn fpu_set_bcd(&mut self, m: (SgReg, u64), v: i64) -> Result<(), EmuException> {
+        let mut raw = [0; 10];
+        let mut n = v.unsigned_abs();
+        for b in raw[..9].iter_mut() {
+            *b = (((n / 10) % 10) << 4) as u8 + (n % 10) as u8;
+            n /= 100;
+        }
+        raw[9] = if v < 0 { 0x80 } else { 0 };
+        self.fpu_set_m80(m, raw)
+    }
+
+    fn fpu_env_size16(&self) -> bool {
+        Instruction::opad_size(&self.ac.oasz, self.pdata).op == access::AcsSize::BIT16
+    }
+
+    fn fpu_store_env(&mut self, m: (SgReg, u64)) -> Result<u64, EmuException> {
+        let fpu = &self.ac.core.fpu;
+        let env = [
+            fpu.control.to_u16() as u32, fpu.status.to_u16() as u32, fpu.tag_word() as u32,
+            fpu.fip as u32, fpu.fcs as u32 + ((fpu.fop as u32) << 16), fpu.fdp as u32, fpu.fds as u32,
+        ];
+
+        let size = if self.fpu_env_size16() {
+            for (i, v) in env.iter().enumerate() {
+                self.ac.set_data16((m.0, m.1 + i as u64 * 2), *v as u16)?;
+            }
+            14
+        } else {
+            for (i, v) in env.iter().enumerate() {
+                self.ac.set_data32((m.0, m.1 + i as u64 * 4), *v)?;
+            }
+            28
+        };
+
+        let fpu = &mut self.ac.core.fpu;
+        let cw = fpu.control.to_u16();
+        fpu.control.from_u16(cw | 0x3f);
+        Ok(size)
+    }
+
+    fn fpu_load_env(&mut self, m: (SgReg, u64)) -> Result<u64, EmuException> {
+        let mut env = [0u32; 7];
+        let size = if self.fpu_env_size16() {
+            for (i, v) in env.iter_mut().enumerate() {
+                *v = self.ac.get_data16((m.0, m.1 + i as u64 * 2))? as u32;
+            }
+            14
+        } else {
+            for (i, v) in env.iter_mut().enumerate() {
+                *v = self.ac.get_data32((m.0, m.1 + i as u64 * 4))?;
+            }
+            28
+        };
+
+        let fpu = &mut self.ac.core.fpu;
+        fpu.control.from_u16(env[0] as u16);
+        fpu.status.from_u16(env[1] as u16);
+        fpu.set_tag_word(env[2] as u16);
+        fpu.fip = env[3] as u64;
+        fpu.fcs = env[4] as u16;
+        fpu.fop = ((env[4] >> 16) & 0x7ff) as u16;
+        fpu.fdp = env[5] as u64;
+        fpu.fds = env[6] as u16;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn fpu_exec_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+
+    ac.core.cregs.0.NE = 1;
+
+    {
+        let mut exe = super::Exec::new(&mut ac, &parse);
+        exe.fpu_load(2.0).unwrap();
+        exe.fpu_load(3.0).unwrap();
+        exe.fpu_arith(1, exe.ac.core.fpu.get_st(1), 1, true).unwrap();
+        assert_eq!(exe.ac.core.fpu.get_st(0), Some(6.0));
+        assert_eq!(exe.ac.core.fpu.get_st(1), None);
+
+        exe.fpu_load(4.0).unwrap();
+        exe.fpu_arith(4, exe.ac.core.fpu.get_st(1), 1, true).unwrap();
+        assert_eq!(exe.ac.core.fpu.get_st(0), Some(-2.0));
+
+        exe.fpu_d9_7(2).unwrap();
+        assert!(exe.ac.core.fpu.get_st(0).unwrap().is_nan());
+        assert!(!exe.ac.core.fpu.status.is_pending());
+
+        exe.fpu_compare(Some(0.0), true).unwrap();
+        assert_eq!((exe.ac.core.fpu.status.C3, exe.ac.core.fpu.status.C2, exe.ac.core.fpu.status.C0), (1, 1, 1));
+
+        exe.ac.core.fpu.set_st(0, 2.0);
+        exe.fpu_load(7.5).unwrap();
+        exe.fpu_d9_7(0).unwrap();
+        assert_eq!(exe.ac.core.fpu.get_st(0), Some(1.5));
+        assert_eq!((exe.ac.core.fpu.status.C3, exe.ac.core.fpu.status.C1, exe.ac.core.fpu.status.C0), (1, 1, 0));
+
+        exe.ac.core.fpu.load_control(0x037b);
+        exe.fpu_load(0.0).unwrap();
+        exe.fpu_arith(7, Some(1.0), 0, false).unwrap();
+        assert_eq!(exe.ac.core.fpu.get_st(0), Some(0.0));
+        assert!(exe.ac.core.fpu.status.is_pending());
+    }
+
+    parse.instr.opcode = 0xd8;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.fpu_esc(0xd8) {
+        Err(EmuException::CPUException(CPUException::MF)) => {},
+        _ => panic!("expected #MF"),
+    }
+    exe.ac.core.cregs.0.TS = 1;
+    match exe.fpu_esc(0xd8) {
+        Err(EmuException::CPUException(CPUException::NM)) => {},
+        _ => panic!("expected #NM"),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn fpu_round_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+    let mut exe = super::Exec::new(&mut ac, &parse);
+
+    let raw = [0x01, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f];
+    exe.fpu_load_raw(raw).unwrap();
+    assert_eq!(exe.fpu_store_raw().unwrap(), Some(raw));
+    assert_eq!(exe.fpu_narrow(raw), 1.0);
+    exe.fpu_d9_4(0).unwrap();
+    assert_eq!(exe.ac.core.fpu.get_raw(0)[9], 0xbf);
+
+    exe.ac.core.fpu.load_control(0x0b7f);
+    assert_eq!(exe.fpu_narrow(raw), next_toward(1.0, true));
+    exe.fpu_load(3.0).unwrap();
+    exe.fpu_arith(7, Some(1.0), 0, false).unwrap();
+    let up = exe.ac.core.fpu.get_st(0).unwrap();
+
+    exe.ac.core.fpu.load_control(0x077f);
+    exe.fpu_load(3.0).unwrap();
+    exe.fpu_arith(7, Some(1.0), 0, false).unwrap();
+    let down = exe.ac.core.fpu.get_st(0).unwrap();
+    assert_eq!(next_toward(down, true), up);
+
+    exe.ac.core.fpu.load_control(0x007f);
+    exe.fpu_load(3.0).unwrap();
+    exe.fpu_arith(7, Some(1.0), 0, false).unwrap();
+    assert_eq!(exe.ac.core.fpu.get_st(0), Some((1.0f32 / 3.0) as f64));
+}
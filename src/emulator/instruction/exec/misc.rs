@@ -1,17 +1,32 @@
 use std::convert::TryFrom;
 use crate::emulator::*;
 use crate::emulator::access::register::*;
+use crate::emulator::instruction::parse;
+use crate::hardware::processor::model_specific::MSRAccess;
+use crate::hardware::processor::cpuid::Leaf01Ecx;
+use crate::hardware::processor::sse::XCR0;
 
 impl<'a> super::Exec<'a> {
     pub fn cr_to_reg(&mut self) -> Result<(), EmuException> {
-        let cr = self.ac.get_creg(self.idata.modrm.reg as usize)?;
-        self.ac.set_gpreg(GpReg32::try_from(self.idata.modrm.rm as usize).unwrap(), cr)
+        let (r, rm) = self.check_creg()?;
+        if self.ac.mode == access::CpuMode::Long {
+            let cr: u64 = self.ac.get_creg(r)?;
+            self.ac.set_gpreg(GpReg64::try_from(rm).unwrap(), cr)
+        } else {
+            let cr: u32 = self.ac.get_creg(r)?;
+            self.ac.set_gpreg(GpReg32::try_from(rm).unwrap(), cr)
+        }
     }
 
     pub fn cr_from_reg(&mut self) -> Result<(), EmuException> {
-        let r = self.idata.modrm.reg as usize;
-        let v = self.ac.get_gpreg(GpReg32::try_from(self.idata.modrm.rm as usize).unwrap())?;
-        self.ac.set_creg(r, v)?;
+        let (r, rm) = self.check_creg()?;
+        if self.ac.mode == access::CpuMode::Long {
+            let v = self.ac.get_gpreg(GpReg64::try_from(rm).unwrap())?;
+            self.ac.set_creg(r, v)?;
+        } else {
+            let v = self.ac.get_gpreg(GpReg32::try_from(rm).unwrap())?;
+            self.ac.set_creg(r, v)?;
+        }
 
         match r {
             0 => {
@@ -26,6 +41,67 @@ impl<'a> super::Exec<'a> {
         Ok(())
     }
 
+    pub fn dr_to_reg(&mut self) -> Result<(), EmuException> {
+        let (r, rm) = (self.check_dreg()?, self.modrm_regs().1);
+        let dr = self.ac.get_dreg(r)?;
+        if self.ac.mode == access::CpuMode::Long {
+            self.ac.set_gpreg(GpReg64::try_from(rm).unwrap(), dr)
+        } else {
+            self.ac.set_gpreg(GpReg32::try_from(rm).unwrap(), dr as u32)
+        }
+    }
+
+    pub fn dr_from_reg(&mut self) -> Result<(), EmuException> {
+        let (r, rm) = (self.check_dreg()?, self.modrm_regs().1);
+        let v = if self.ac.mode == access::CpuMode::Long {
+            let v = self.ac.get_gpreg(GpReg64::try_from(rm).unwrap())?;
+            if r >= 6 && v >> 32 != 0 {
+                return Err(EmuException::CPUException(CPUException::GP(None)));
+            }
+            v
+        } else {
+            self.ac.get_gpreg(GpReg32::try_from(rm).unwrap())? as u64
+        };
+        self.ac.set_dreg(r, v)
+    }
+
+    fn modrm_regs(&self) -> (usize, usize) {
+        let (r, b) = if let Some(rex) = self.pdata.rex { (rex.r << 3, rex.b << 3) } else { (0, 0) };
+        ((r + self.idata.modrm.reg) as usize, (b + self.idata.modrm.rm) as usize)
+    }
+
+    fn check_creg(&self) -> Result<(usize, usize), EmuException> {
+        let (r, rm) = self.modrm_regs();
+        if self.ac.core.cregs.get(r).is_none() {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        Ok((r, rm))
+    }
+
+    fn check_dreg(&mut self) -> Result<usize, EmuException> {
+        if let Some(parse::Rex { r: 1, .. }) = self.pdata.rex {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let r = match (self.idata.modrm.reg as usize, self.ac.core.cregs.4.DE) {
+            (4, 0) | (5, 0) => self.idata.modrm.reg as usize + 2,
+            (4, _) | (5, _) => { return Err(EmuException::CPUException(CPUException::UD)); },
+            (r, _) => r,
+        };
+
+        let dregs = &mut self.ac.core.dregs;
+        if dregs.dr7.is_gd() {
+            dregs.dr7.clear_gd();
+            dregs.dr6.set_bd(true);
+            self.ac.update_ip(-(self.idata.len as i64))?;
+            return Err(EmuException::CPUException(CPUException::DB));
+        }
+        Ok(r)
+    }
+
     pub fn msr_to_reg(&mut self) -> Result<(), EmuException> {
         let addr = self.ac.get_gpreg(GpReg32::ECX)?;
         let v = self.ac.read_msr(addr)?;
@@ -42,4 +118,212 @@ impl<'a> super::Exec<'a> {
         self.ac.write_msr(addr, v)
     }
 
-}
\ No newline at end of file
+    pub fn cpuid(&mut self) -> Result<(), EmuException> {
+        let leaf = self.get_eax()?;
+        let subleaf = self.ac.get_gpreg(GpReg32::ECX)?;
+        let mut regs = self.ac.cpuid.get(leaf, subleaf);
+        match (leaf, subleaf) {
+            (0x1, _) if self.ac.core.cregs.4.OSXSAVE == 1 => regs.ecx |= Leaf01Ecx::OSXSAVE.bits(),
+            (0xd, 0) if self.ac.core.sse.xcr0.contains(XCR0::AVX) => regs.ebx = regs.ecx,
+            _ => {},
+        }
+
+        self.set_eax(regs.eax)?;
+        self.ac.set_gpreg(GpReg32::EBX, regs.ebx)?;
+        self.ac.set_gpreg(GpReg32::ECX, regs.ecx)?;
+        self.set_edx(regs.edx)
+    }
+
+    pub fn tsc_to_reg(&mut self) -> Result<(), EmuException> {
+        if self.ac.core.cregs.4.TSD == 1 && self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        let v = self.ac.core.tsc.to_u64();
+
+        self.set_edx((v >> 32) as u32)?;
+        self.set_eax(v as u32)
+    }
+
+    pub fn tscp_to_reg(&mut self) -> Result<(), EmuException> {
+        self.tsc_to_reg()?;
+        let aux = self.ac.core.msr.tsc_aux.to_u64();
+        self.ac.set_gpreg(GpReg32::ECX, aux as u32)
+    }
+
+    pub fn pid_to_reg(&mut self) -> Result<(), EmuException> {
+        if !matches!(self.pdata.repeat, Some(parse::Rep::REPZ)) {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        let aux = self.ac.core.msr.tsc_aux.to_u64();
+        let rm = self.modrm_regs().1;
+        if self.ac.mode == access::CpuMode::Long {
+            self.ac.set_gpreg(GpReg64::try_from(rm).unwrap(), aux)
+        } else {
+            self.ac.set_gpreg(GpReg32::try_from(rm).unwrap(), aux as u32)
+        }
+    }
+
+    pub fn invpcid(&mut self) -> Result<(), EmuException> {
+        if !self.pdata.size.contains(parse::OverrideSize::OP) || self.idata.modrm.mod_ == 3 {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let ty = if self.ac.mode == access::CpuMode::Long { self.get_r64()? } else { self.get_r32()? as u64 };
+        let (sg, adr) = self.get_m()?;
+        let pcid  = self.ac.get_data64((sg, adr))?;
+        let laddr = self.ac.get_data64((sg, adr+8))?;
+
+        if pcid > 0xfff || (ty < 2 && pcid != 0 && self.ac.core.cregs.4.PCIDE == 0) {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        if ty == 0 && ((laddr as i64) << 16 >> 16) as u64 != laddr {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        self.ac.invalidate_pcid(ty, pcid as u16, laddr)
+    }
+
+}
+#[cfg(test)]
+#[test]
+fn dreg_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.reg = 4;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.dr_to_reg().unwrap();
+    assert_eq!(exe.get_eax().unwrap(), 0xffff0ff0);
+    exe.ac.core.cregs.4.DE = 1;
+    match exe.dr_to_reg() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        _ => panic!("expected #UD"),
+    }
+
+    parse.instr.modrm.reg = 7;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.set_eax(0x000d2001).unwrap();
+    exe.dr_from_reg().unwrap();
+    exe.ac.set_dreg(0, 0x102).unwrap();
+    match exe.dr_to_reg() {
+        Err(EmuException::CPUException(CPUException::DB)) => {},
+        _ => panic!("expected #DB"),
+    }
+    assert_eq!(exe.ac.get_dreg(6).unwrap() & 0x2000, 0x2000);
+
+    exe.ac.get_data32((SgReg::DS, 0x100)).unwrap();
+    assert_eq!(exe.ac.take_watch_hits(), 0);
+    exe.ac.set_data16((SgReg::DS, 0x102), 0).unwrap();
+    assert_eq!(exe.ac.take_watch_hits(), 1);
+    exe.ac.set_data8((SgReg::DS, 0xff), 0).unwrap();
+    assert_eq!(exe.ac.take_watch_hits(), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn creg_rex_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.reg = 3;
+    parse.instr.modrm.rm = 1;
+    parse.prefix.rex = Some(parse::Rex { b: 1, ..Default::default() });
+
+    ac.mode = access::CpuMode::Long;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.set_gpreg(GpReg64::R9, 0x5000).unwrap();
+    exe.cr_from_reg().unwrap();
+    assert_eq!(exe.ac.core.cregs.3.to_u64() & !0xfff, 0x5000);
+    exe.ac.set_gpreg(GpReg64::R9, 0).unwrap();
+    exe.cr_to_reg().unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg64::R9).unwrap(), 0x5000);
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RCX).unwrap(), 0);
+
+    parse.prefix.rex = Some(parse::Rex { r: 1, b: 1, ..Default::default() });
+    parse.instr.modrm.reg = 0;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.cr_to_reg() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        r => panic!("{:?}", r),
+    }
+    match exe.cr_from_reg() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        r => panic!("{:?}", r),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn dreg_rex_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.reg = 7;
+    parse.instr.modrm.rm = 0;
+    parse.prefix.rex = Some(parse::Rex { b: 1, ..Default::default() });
+
+    ac.mode = access::CpuMode::Long;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.dr_to_reg().unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg64::R8).unwrap(), 0x400);
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RAX).unwrap(), 0);
+
+    parse.prefix.rex = Some(parse::Rex { r: 1, b: 1, ..Default::default() });
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.dr_to_reg() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        r => panic!("{:?}", r),
+    }
+    match exe.dr_from_reg() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        r => panic!("{:?}", r),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tsc_aux_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.rm = 1;
+    parse.prefix.rex = Some(parse::Rex { b: 1, ..Default::default() });
+    parse.prefix.repeat = Some(parse::Rep::REPZ);
+
+    ac.mode = access::CpuMode::Long;
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.core.msr.tsc_aux.from_u64(3);
+    exe.pid_to_reg().unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg64::R9).unwrap(), 3);
+    assert_eq!(exe.ac.get_gpreg(GpReg64::RCX).unwrap(), 0);
+
+    exe.ac.core.cregs.4.TSD = 1;
+    exe.ac.set_sgreg(SgReg::CS, 0x1b, Default::default()).unwrap();
+    match exe.tscp_to_reg() {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        r => panic!("{:?}", r),
+    }
+}
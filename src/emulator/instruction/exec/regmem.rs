@@ -1,7 +1,7 @@
 use std::convert::TryFrom;
 use crate::emulator::access;
 use crate::emulator::access::register::*;
-use crate::emulator::EmuException;
+use crate::emulator::{EmuException, CPUException};
 
 macro_rules! get_gpreg { ($self:expr, $type:ty, $reg:expr) => { $self.ac.get_gpreg(<$type>::try_from($reg as usize).unwrap()) } }
 macro_rules! set_gpreg { ($self:expr, $type:ty, $reg:expr, $val:expr) => { $self.ac.set_gpreg(<$type>::try_from($reg as usize).unwrap(), $val); } }
@@ -233,6 +233,28 @@ impl<'a> super::Exec<'a> {
         Ok(Self::addr_modrm(self)?)
     }
 
+    pub fn get_m16_16(&self) -> Result<(u16, u16), EmuException> {
+        let (sg, adr) = self.get_farptr()?;
+        Ok((self.ac.get_data16((sg, adr+2))?, self.ac.get_data16((sg, adr))?))
+    }
+
+    pub fn get_m16_32(&self) -> Result<(u16, u32), EmuException> {
+        let (sg, adr) = self.get_farptr()?;
+        Ok((self.ac.get_data16((sg, adr+4))?, self.ac.get_data32((sg, adr))?))
+    }
+
+    pub fn get_m16_64(&self) -> Result<(u16, u64), EmuException> {
+        let (sg, adr) = self.get_farptr()?;
+        Ok((self.ac.get_data16((sg, adr+8))?, self.ac.get_data64((sg, adr))?))
+    }
+
+    fn get_farptr(&self) -> Result<(SgReg, u64), EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        self.get_m()
+    }
+
     fn addr_modrm(&self) -> Result<(SgReg, u64), EmuException> {
         let modrm = self.idata.modrm;
         let (mod_, rm) = (modrm.mod_, modrm.rm);
@@ -0,0 +1,688 @@
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+use crate::emulator::instruction::parse::Mandatory;
+use crate::hardware::processor::fpu::FPUException;
+use crate::hardware::processor::sse::MXCSR_MASK;
+use super::fpu::round_rc;
+
+pub(super) fn mask(bits: usize) -> u64 { if bits == 64 { !0 } else { (1 << bits) - 1 } }
+pub(super) fn lane(v: u128, i: usize, bits: usize) -> u64 { (v >> (i * bits)) as u64 & mask(bits) }
+pub(super) fn sext(v: u64, bits: usize) -> i64 { ((v << (64 - bits)) as i64) >> (64 - bits) }
+pub(super) fn merge(v: u128, x: u64, bits: usize) -> u128 { v & !(mask(bits) as u128) | x as u128 }
+
+pub(super) fn map(v: u128, bits: usize, mut f: impl FnMut(usize, u64) -> u64) -> u128 {
+    (0..128/bits).fold(0, |r, i| r | ((f(i, lane(v, i, bits)) & mask(bits)) as u128) << (i * bits))
+}
+
+pub(super) fn map2(a: u128, b: u128, bits: usize, mut f: impl FnMut(u64, u64) -> u64) -> u128 {
+    map(a, bits, |i, x| f(x, lane(b, i, bits)))
+}
+
+fn saturate(v: i64, bits: usize, signed: bool) -> u64 {
+    let (min, max) = if signed { (-1 << (bits - 1), (1 << (bits - 1)) - 1) } else { (0, (1 << bits) - 1) };
+    v.max(min).min(max) as u64
+}
+
+pub(super) fn unpack(a: u128, b: u128, bits: usize, hi: bool) -> u128 {
+    let base = if hi { 64 / bits } else { 0 };
+    map(0, bits, |i, _| lane(if i % 2 == 0 { a } else { b }, base + i / 2, bits))
+}
+
+fn pack(a: u128, b: u128, bits: usize, signed: bool) -> u128 {
+    let n = 128 / bits;
+    map(0, bits / 2, |i, _| {
+        let v = if i < n { lane(a, i, bits) } else { lane(b, i - n, bits) };
+        saturate(sext(v, bits), bits / 2, signed)
+    })
+}
+
+pub(super) fn shift(v: u128, bits: usize, cnt: u64, kind: u8) -> u128 {
+    map(v, bits, |_, x| match kind {
+        2 if cnt < bits as u64 => x >> cnt,
+        4 => (sext(x, bits) >> cnt.min(bits as u64 - 1)) as u64,
+        6 if cnt < bits as u64 => x << cnt,
+        _ => 0,
+    })
+}
+
+fn addsub(a: u128, b: u128, bits: usize, sub: bool, sat: Option<bool>) -> u128 {
+    map2(a, b, bits, |x, y| match sat {
+        None => if sub { x.wrapping_sub(y) } else { x.wrapping_add(y) },
+        Some(signed) => {
+            let (x, y) = if signed { (sext(x, bits), sext(y, bits)) } else { (x as i64, y as i64) };
+            saturate(if sub { x - y } else { x + y }, bits, signed)
+        },
+    })
+}
+
+pub(super) fn packed_int(op: u8, a: u128, b: u128) -> Option<u128> {
+    let cnt = b as u64;
+    let r = match op {
+        0x60..=0x62 => unpack(a, b, 8 << (op - 0x60), false),
+        0x68..=0x6a => unpack(a, b, 8 << (op - 0x68), true),
+        0x6c | 0x6d => unpack(a, b, 64, op == 0x6d),
+        0x63 => pack(a, b, 16, true),
+        0x67 => pack(a, b, 16, false),
+        0x6b => pack(a, b, 32, true),
+        0x64..=0x66 => {
+            let bits = 8 << (op - 0x64);
+            map2(a, b, bits, |x, y| if sext(x, bits) > sext(y, bits) { !0 } else { 0 })
+        },
+        0x74..=0x76 => map2(a, b, 8 << (op - 0x74), |x, y| if x == y { !0 } else { 0 }),
+        0xd1..=0xd3 => shift(a, 8 << (op - 0xd0), cnt, 2),
+        0xe1 | 0xe2 => shift(a, 8 << (op - 0xe0), cnt, 4),
+        0xf1..=0xf3 => shift(a, 8 << (op - 0xf0), cnt, 6),
+        0xd4 => addsub(a, b, 64, false, None),
+        0xfc..=0xfe => addsub(a, b, 8 << (op - 0xfc), false, None),
+        0xf8..=0xfb => addsub(a, b, 8 << (op - 0xf8), true, None),
+        0xd8 | 0xd9 => addsub(a, b, 8 << (op - 0xd8), true, Some(false)),
+        0xdc | 0xdd => addsub(a, b, 8 << (op - 0xdc), false, Some(false)),
+        0xe8 | 0xe9 => addsub(a, b, 8 << (op - 0xe8), true, Some(true)),
+        0xec | 0xed => addsub(a, b, 8 << (op - 0xec), false, Some(true)),
+        0xd5 => map2(a, b, 16, |x, y| x * y),
+        0xe4 => map2(a, b, 16, |x, y| (x * y) >> 16),
+        0xe5 => map2(a, b, 16, |x, y| ((sext(x, 16) * sext(y, 16)) >> 16) as u64),
+        0xf4 => map2(a, b, 64, |x, y| (x & 0xffffffff) * (y & 0xffffffff)),
+        0xf5 => map2(a, b, 32, |x, y| (sext(x & 0xffff, 16) * sext(y & 0xffff, 16) + sext(x >> 16, 16) * sext(y >> 16, 16)) as u64),
+        0xf6 => map2(a, b, 64, |x, y| (0..8).map(|i| ((x >> (i * 8)) as u8 as i64 - (y >> (i * 8)) as u8 as i64).unsigned_abs()).sum()),
+        0xda => map2(a, b, 8, |x, y| x.min(y)),
+        0xde => map2(a, b, 8, |x, y| x.max(y)),
+        0xea => map2(a, b, 16, |x, y| if sext(x, 16) < sext(y, 16) { x } else { y }),
+        0xee => map2(a, b, 16, |x, y| if sext(x, 16) > sext(y, 16) { x } else { y }),
+        0xe0 => map2(a, b, 8, |x, y| (x + y + 1) >> 1),
+        0xe3 => map2(a, b, 16, |x, y| (x + y + 1) >> 1),
+        0xdb => a & b,
+        0xdf => !a & b,
+        0xeb => a | b,
+        0xef => a ^ b,
+        _ => return None,
+    };
+    Some(r)
+}
+
+fn qbit(bits: usize) -> u64 { if bits == 32 { 1 << 22 } else { 1 << 51 } }
+fn indefinite(bits: usize) -> u64 { if bits == 32 { 0xffc00000 } else { 0xfff8000000000000 } }
+pub(super) fn to_f(v: u64, bits: usize) -> f64 { if bits == 32 { f32::from_bits(v as u32) as f64 } else { f64::from_bits(v) } }
+pub(super) fn from_f(v: f64, bits: usize) -> u64 { if bits == 32 { (v as f32).to_bits() as u64 } else { v.to_bits() } }
+fn is_snan(v: u64, bits: usize) -> bool { to_f(v, bits).is_nan() && v & qbit(bits) == 0 }
+
+pub(super) fn fp_arith(op: u8, a: u64, b: u64, bits: usize, ex: &mut FPUException) -> u64 {
+    let a = if let 0x51..=0x53 = op { b } else { a };
+    let (x, y) = (to_f(a, bits), to_f(b, bits));
+    if is_snan(a, bits) || is_snan(b, bits) {
+        *ex |= FPUException::IE;
+    }
+
+    if let 0x5d | 0x5f = op {
+        if x.is_nan() || y.is_nan() {
+            *ex |= FPUException::IE;
+            return b;
+        }
+        return if (op == 0x5d && x < y) || (op == 0x5f && x > y) { a } else { b };
+    }
+    if x.is_nan() { return a | qbit(bits); }
+    if y.is_nan() { return b | qbit(bits); }
+
+    let r = match op {
+        0x51 => y.sqrt(),
+        0x52 => 1.0 / y.sqrt(),
+        0x53 => 1.0 / y,
+        0x58 => x + y,
+        0x59 => x * y,
+        0x5c => x - y,
+        _    => x / y,
+    };
+    if op == 0x5e && y == 0.0 && x != 0.0 && x.is_finite() {
+        *ex |= FPUException::ZE;
+    }
+    if r.is_nan() {
+        *ex |= FPUException::IE;
+        return indefinite(bits);
+    }
+    from_f(r, bits)
+}
+
+pub(super) fn fp_compare(pred: u8, a: u64, b: u64, bits: usize, ex: &mut FPUException) -> bool {
+    let (x, y) = (to_f(a, bits), to_f(b, bits));
+    let unord = x.is_nan() || y.is_nan();
+    let signal = matches!(pred & 3, 1 | 2) ^ (pred & 0x10 != 0);
+    if is_snan(a, bits) || is_snan(b, bits) || (unord && signal) {
+        *ex |= FPUException::IE;
+    }
+
+    match pred & 0xf {
+        0 => x == y,
+        1 => x < y,
+        2 => x <= y,
+        3 => unord,
+        4 => x != y,
+        5 => !(x < y),
+        6 => !(x <= y),
+        7 => !unord,
+        8 => unord || x == y,
+        9 => !(x >= y),
+        10 => !(x > y),
+        11 => false,
+        12 => !unord && x != y,
+        13 => x >= y,
+        14 => x > y,
+        _ => true,
+    }
+}
+
+pub(super) fn fp_convert(v: u64, from: usize, to: usize, ex: &mut FPUException) -> u64 {
+    if is_snan(v, from) {
+        *ex |= FPUException::IE;
+    }
+    let r = from_f(to_f(v, from), to);
+    if to_f(r, to).is_nan() { r | qbit(to) } else { r }
+}
+
+pub(super) fn fp_to_int(v: f64, bits: usize, rc: Option<u8>, ex: &mut FPUException) -> u64 {
+    let r = rc.map_or(v.trunc(), |rc| round_rc(v, rc));
+    let lim = 2f64.powi(bits as i32 - 1);
+    if r.is_nan() || r >= lim || r < -lim {
+        *ex |= FPUException::IE;
+        return 1 << (bits - 1);
+    }
+    r as i64 as u64 & mask(bits)
+}
+
+pub(super) fn shift_imm(op: u8, kind: u8, v: u128, cnt: u64) -> Option<u128> {
+    let r = match (op, kind) {
+        (0x71, 2) | (0x71, 4) | (0x71, 6) => shift(v, 16, cnt, kind),
+        (0x72, 2) | (0x72, 4) | (0x72, 6) => shift(v, 32, cnt, kind),
+        (0x73, 2) | (0x73, 6) => shift(v, 64, cnt, kind),
+        (0x73, 3) => if cnt < 16 { v >> (cnt * 8) } else { 0 },
+        (0x73, 7) => if cnt < 16 { v << (cnt * 8) } else { 0 },
+        _ => return None,
+    };
+    Some(r)
+}
+
+pub(super) fn is_packed(op: u8, pfx: u8) -> bool {
+    match pfx {
+        0 => matches!(op, 0x14 | 0x15 | 0x51..=0x59 | 0x5c..=0x5f | 0xc2 | 0xc6),
+        0x66 => matches!(op, 0x14 | 0x15 | 0x51 | 0x54..=0x59 | 0x5c..=0x5f | 0xc2 | 0xc6 | 0x60..=0x6d | 0x70 | 0x74..=0x76 | 0xd1..=0xfe),
+        _ => op == 0x70,
+    }
+}
+
+pub(super) fn packed_op(op: u8, pfx: u8, a: u128, b: u128, imm: u8, ex: &mut FPUException) -> Option<u128> {
+    let (bits, imm) = (if pfx == 0x66 { 64 } else { 32 }, imm as usize);
+    let r = match op {
+        0x14 | 0x15 => unpack(a, b, bits, op == 0x15),
+        0x54 => a & b,
+        0x55 => !a & b,
+        0x56 => a | b,
+        0x57 => a ^ b,
+        0x51..=0x5f => map2(a, b, bits, |x, y| fp_arith(op, x, y, bits, ex)),
+        0x70 => match pfx {
+            0x66 => map(0, 32, |i, _| lane(b, (imm >> (i * 2)) & 3, 32)),
+            0xf3 => map(b, 16, |i, x| if i < 4 { x } else { lane(b, 4 + ((imm >> ((i - 4) * 2)) & 3), 16) }),
+            _    => map(b, 16, |i, x| if i < 4 { lane(b, (imm >> (i * 2)) & 3, 16) } else { x }),
+        },
+        0xc2 => map2(a, b, bits, |x, y| if fp_compare(imm as u8, x, y, bits, ex) { !0 } else { 0 }),
+        0xc6 if pfx == 0 => map(0, 32, |i, _| lane(if i < 2 { a } else { b }, (imm >> (i * 2)) & 3, 32)),
+        0xc6 => map(0, 64, |i, _| lane(if i == 0 { a } else { b }, (imm >> i) & 1, 64)),
+        _ => return packed_int(op, a, b),
+    };
+    Some(r)
+}
+
+impl<'a> super::Exec<'a> {
+    pub fn sse(&mut self) -> Result<(), EmuException> {
+        if self.pdata.vex.is_some() {
+            return self.avx();
+        }
+        self.sse_check()?;
+        self.sse_op()
+    }
+
+    pub(super) fn sse_op(&mut self) -> Result<(), EmuException> {
+        let (op, pfx) = (self.idata.opcode as u8, self.simd_prefix());
+        let (bits, scalar) = match pfx { 0 => (32, false), 0x66 => (64, false), 0xf3 => (32, true), _ => (64, true) };
+        let reg = self.idata.modrm.mod_ == 3;
+
+        match (op, pfx) {
+            (0x10, 0) | (0x10, 0x66) | (0x6f, 0xf3) => {
+                let v = self.get_xmm_rm128(false)?;
+                self.set_xmm_r(v);
+            },
+            (0x28, 0) | (0x28, 0x66) | (0x6f, 0x66) => {
+                let v = self.get_xmm_rm128(true)?;
+                self.set_xmm_r(v);
+            },
+            (0x11, 0) | (0x11, 0x66) | (0x7f, 0xf3) => {
+                let v = self.get_xmm_r();
+                self.set_xmm_rm128(v, false)?;
+            },
+            (0x29, 0) | (0x29, 0x66) | (0x7f, 0x66) => {
+                let v = self.get_xmm_r();
+                self.set_xmm_rm128(v, true)?;
+            },
+            (0x2b, 0) | (0x2b, 0x66) | (0xe7, 0x66) if !reg => {
+                let v = self.get_xmm_r();
+                self.set_xmm_rm128(v, true)?;
+            },
+            (0x10, _) => {
+                let v = self.get_xmm_rm_lane(bits)?;
+                self.set_xmm_r(if reg { merge(self.get_xmm_v(), v, bits) } else { v as u128 });
+            },
+            (0x11, _) => {
+                let v = lane(self.get_xmm_r(), 0, bits);
+                self.set_xmm_rm_lane(v, bits)?;
+            },
+            (0x12, 0) if reg => {
+                let v = lane(self.get_xmm_rm(), 1, 64);
+                self.set_xmm_r(merge(self.get_xmm_v(), v, 64));
+            },
+            (0x12, 0) | (0x12, 0x66) if !reg => {
+                let v = self.get_xmm_rm_lane(64)?;
+                self.set_xmm_r(merge(self.get_xmm_v(), v, 64));
+            },
+            (0x16, 0) | (0x16, 0x66) if !reg || pfx == 0 => {
+                let v = self.get_xmm_rm_lane(64)?;
+                self.set_xmm_r(lane(self.get_xmm_v(), 0, 64) as u128 | (v as u128) << 64);
+            },
+            (0x13, 0) | (0x13, 0x66) | (0x17, 0) | (0x17, 0x66) if !reg => {
+                let v = lane(self.get_xmm_r(), (op == 0x17) as usize, 64);
+                self.set_xmm_rm_lane(v, 64)?;
+            },
+            (0x2a, 0xf3) | (0x2a, 0xf2) => {
+                let v = if self.is_rexw() { self.get_rm64()? as i64 as f64 } else { self.get_rm32()? as i32 as f64 };
+                self.set_xmm_r(merge(self.get_xmm_v(), from_f(v, bits), bits));
+            },
+            (0x2c, 0xf3) | (0x2c, 0xf2) | (0x2d, 0xf3) | (0x2d, 0xf2) => {
+                let v = to_f(self.get_xmm_rm_lane(bits)?, bits);
+                let rc = if op == 0x2d { Some(self.ac.core.sse.mxcsr.RC) } else { None };
+                let mut ex = FPUException::empty();
+                let size = if self.is_rexw() { 64 } else { 32 };
+                let r = fp_to_int(v, size, rc, &mut ex);
+                self.sse_raise(ex)?;
+                if size == 64 { self.set_r64(r)?; } else { self.set_r32(r as u32)?; }
+            },
+            (0x2e, 0) | (0x2e, 0x66) | (0x2f, 0) | (0x2f, 0x66) => {
+                let (a, b) = (lane(self.get_xmm_r(), 0, bits), self.get_xmm_rm_lane(bits)?);
+                self.sse_comis(a, b, bits, op == 0x2f)?;
+            },
+            (0x50, 0) | (0x50, 0x66) if reg => {
+                let v = self.get_xmm_rm();
+                self.set_r32((0..128/bits).fold(0, |m, i| m | ((lane(v, i, bits) >> (bits - 1)) as u32) << i))?;
+            },
+            (0x51, _) | (0x58, _) | (0x59, _) | (0x5c..=0x5f, _) if scalar => self.sse_arith(op, bits, true)?,
+            (0x52, 0xf3) | (0x53, 0xf3) => self.sse_arith(op, bits, false)?,
+            (0x5a, _) => self.sse_cvt_float(pfx)?,
+            (0x5b, 0) | (0x5b, 0x66) | (0x5b, 0xf3) | (0xe6, 0x66) | (0xe6, 0xf3) | (0xe6, 0xf2) => self.sse_cvt_int(op, pfx)?,
+            (0x6e, 0x66) => {
+                let v = if self.is_rexw() { self.get_rm64()? } else { self.get_rm32()? as u64 };
+                self.set_xmm_r(v as u128);
+            },
+            (0x7e, 0x66) => {
+                let v = lane(self.get_xmm_r(), 0, 64);
+                if self.is_rexw() { self.set_rm64(v)?; } else { self.set_rm32(v as u32)?; }
+            },
+            (0x7e, 0xf3) => {
+                let v = self.get_xmm_rm_lane(64)?;
+                self.set_xmm_r(v as u128);
+            },
+            (0xd6, 0x66) => {
+                let v = lane(self.get_xmm_r(), 0, 64);
+                if reg { self.set_xmm_rm(v as u128); } else { self.set_xmm_rm_lane(v, 64)?; }
+            },
+            (0x71..=0x73, 0x66) if reg => {
+                let (kind, cnt) = (self.idata.modrm.reg, self.get_imm8()? as u64);
+                let r = shift_imm(op, kind, self.get_xmm_rm(), cnt).ok_or(EmuException::CPUException(CPUException::UD))?;
+                let n = self.pdata.vex.map_or(self.xmm_rm(), |vex| vex.vvvv as usize);
+                self.write_xmm(n, r);
+            },
+            (0xc2, _) if scalar => {
+                let (a, b, pred) = (self.get_xmm_v(), self.get_xmm_rm_lane(bits)?, self.get_imm8()? & self.cmp_mask());
+                let mut ex = FPUException::empty();
+                let r = merge(a, if fp_compare(pred, lane(a, 0, bits), b, bits, &mut ex) { mask(bits) } else { 0 }, bits);
+                self.sse_raise(ex)?;
+                self.set_xmm_r(r);
+            },
+            (0xc3, 0) if !reg && self.pdata.vex.is_none() => {
+                if self.is_rexw() { self.set_rm64(self.get_r64()?)?; } else { self.set_rm32(self.get_r32()?)?; }
+            },
+            (0xc4, 0x66) => {
+                let (v, i) = (self.get_rm16()? as u64, (self.get_imm8()? & 7) as usize);
+                self.set_xmm_r(map(self.get_xmm_v(), 16, |n, x| if n == i { v } else { x }));
+            },
+            (0xc5, 0x66) if reg => {
+                let i = (self.get_imm8()? & 7) as usize;
+                self.set_r32(lane(self.get_xmm_rm(), i, 16) as u32)?;
+            },
+            (0xd7, 0x66) if reg => {
+                let v = self.get_xmm_rm();
+                self.set_r32((0..16).fold(0, |m, i| m | ((lane(v, i, 8) >> 7) as u32) << i))?;
+            },
+            (0xf7, 0x66) if reg => self.sse_maskmov()?,
+            _ if is_packed(op, pfx) => {
+                let (a, b) = (self.get_xmm_v(), self.get_xmm_rm128(self.sse_align())?);
+                let imm = match op {
+                    0x70 | 0xc6 => self.get_imm8()?,
+                    0xc2 => self.get_imm8()? & self.cmp_mask(),
+                    _ => 0,
+                };
+                let mut ex = FPUException::empty();
+                let r = packed_op(op, pfx, a, b, imm, &mut ex).ok_or(EmuException::CPUException(CPUException::UD))?;
+                if !matches!(op, 0x52 | 0x53) {
+                    self.sse_raise(ex)?;
+                }
+                self.set_xmm_r(r);
+            },
+            _ => return Err(EmuException::CPUException(CPUException::UD)),
+        }
+        Ok(())
+    }
+
+    pub fn ldmxcsr(&mut self) -> Result<(), EmuException> {
+        self.sse_check()?;
+
+        let v = self.ac.get_data32(self.get_m()?)?;
+        if v & !MXCSR_MASK != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        self.ac.core.sse.mxcsr.from_u32(v);
+        Ok(())
+    }
+
+    pub fn stmxcsr(&mut self) -> Result<(), EmuException> {
+        self.sse_check()?;
+
+        let v = self.ac.core.sse.mxcsr.to_u32();
+        self.ac.set_data32(self.get_m()?, v)
+    }
+
+    pub(super) fn simd_prefix(&self) -> u8 {
+        match self.pdata.mandatory {
+            None => 0,
+            Some(Mandatory::P66) => 0x66,
+            Some(Mandatory::PF3) => 0xf3,
+            Some(Mandatory::PF2) => 0xf2,
+        }
+    }
+
+    pub(super) fn sse_align(&self) -> bool { self.pdata.vex.is_none() }
+    pub(super) fn cmp_mask(&self) -> u8 { if self.pdata.vex.is_some() { 0x1f } else { 7 } }
+
+    fn sse_check(&self) -> Result<(), EmuException> {
+        let (cr0, cr4) = (&self.ac.core.cregs.0, &self.ac.core.cregs.4);
+        if cr0.EM == 1 || cr4.OSFXSR == 0 {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if cr0.TS == 1 {
+            return Err(EmuException::CPUException(CPUException::NM));
+        }
+        Ok(())
+    }
+
+    pub(super) fn sse_raise(&mut self, ex: FPUException) -> Result<(), EmuException> {
+        if self.ac.core.sse.raise(ex) {
+            let e = if self.ac.core.cregs.4.OSXMMEXCPT == 1 { CPUException::XF } else { CPUException::UD };
+            return Err(EmuException::CPUException(e));
+        }
+        Ok(())
+    }
+
+    fn sse_arith(&mut self, op: u8, bits: usize, signal: bool) -> Result<(), EmuException> {
+        let (a, b) = (self.get_xmm_v(), self.get_xmm_rm_lane(bits)?);
+        let mut ex = FPUException::empty();
+        let r = merge(a, fp_arith(op, lane(a, 0, bits), b, bits, &mut ex), bits);
+        if signal {
+            self.sse_raise(ex)?;
+        }
+        self.set_xmm_r(r);
+        Ok(())
+    }
+
+    fn sse_comis(&mut self, a: u64, b: u64, bits: usize, signal: bool) -> Result<(), EmuException> {
+        let (x, y) = (to_f(a, bits), to_f(b, bits));
+        let unord = x.is_nan() || y.is_nan();
+        if is_snan(a, bits) || is_snan(b, bits) || (signal && unord) {
+            self.sse_raise(FPUException::IE)?;
+        }
+
+        let rf = &mut self.ac.core.rflags;
+        rf.set_zero(unord || x == y);
+        rf.set_parity(unord);
+        rf.set_carry(unord || x < y);
+        rf.set_overflow(false);
+        rf.set_sign(false);
+        Ok(())
+    }
+
+    fn sse_cvt_float(&mut self, pfx: u8) -> Result<(), EmuException> {
+        let a = self.get_xmm_v();
+        let mut ex = FPUException::empty();
+        let r = match pfx {
+            0 => {
+                let b = self.get_xmm_rm_lane(64)? as u128;
+                map(0, 64, |i, _| fp_convert(lane(b, i, 32), 32, 64, &mut ex))
+            },
+            0x66 => {
+                let b = self.get_xmm_rm128(self.sse_align())?;
+                map(0, 32, |i, _| if i < 2 { fp_convert(lane(b, i, 64), 64, 32, &mut ex) } else { 0 })
+            },
+            0xf3 => merge(a, fp_convert(self.get_xmm_rm_lane(32)?, 32, 64, &mut ex), 64),
+            _    => merge(a, fp_convert(self.get_xmm_rm_lane(64)?, 64, 32, &mut ex), 32),
+        };
+        self.sse_raise(ex)?;
+        self.set_xmm_r(r);
+        Ok(())
+    }
+
+    fn sse_cvt_int(&mut self, op: u8, pfx: u8) -> Result<(), EmuException> {
+        let rc = match (op, pfx) {
+            (0x5b, 0x66) | (0xe6, 0xf2) => Some(self.ac.core.sse.mxcsr.RC),
+            _ => None,
+        };
+        let mut ex = FPUException::empty();
+        let r = match (op, pfx) {
+            (0x5b, 0) => map(self.get_xmm_rm128(self.sse_align())?, 32, |_, x| from_f(sext(x, 32) as f64, 32)),
+            (0x5b, _) => map(self.get_xmm_rm128(self.sse_align())?, 32, |_, x| fp_to_int(to_f(x, 32), 32, rc, &mut ex)),
+            (_, 0xf3) => {
+                let b = self.get_xmm_rm_lane(64)? as u128;
+                map(0, 64, |i, _| from_f(sext(lane(b, i, 32), 32) as f64, 64))
+            },
+            _ => {
+                let b = self.get_xmm_rm128(self.sse_align())?;
+                map(0, 32, |i, _| if i < 2 { fp_to_int(to_f(lane(b, i, 64), 64), 32, rc, &mut ex) } else { 0 })
+            },
+        };
+        self.sse_raise(ex)?;
+        self.set_xmm_r(r);
+        Ok(())
+    }
+
+    fn sse_maskmov(&mut self) -> Result<(), EmuException> {
+        let (v, m) = (self.get_xmm_r(), self.get_xmm_rm());
+        let sg = self.pdata.segment.unwrap_or(SgReg::DS);
+        let rdi = self.ac.get_gpreg(GpReg64::RDI)?;
+        let adr = match self.idata.adsize {
+            access::AcsSize::BIT16 => rdi as u16 as u64,
+            access::AcsSize::BIT32 => rdi as u32 as u64,
+            access::AcsSize::BIT64 => rdi,
+        };
+
+        for i in (0..16).filter(|i| lane(m, *i, 8) & 0x80 != 0) {
+            self.ac.set_data8((sg, adr + i as u64), lane(v, i, 8) as u8)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn xmm_count(&self) -> usize { if self.is_mode64() { 16 } else { 8 } }
+
+    pub(super) fn xmm_r(&self) -> usize {
+        let r = if let Some(rex) = self.pdata.rex { rex.r << 3 } else { 0 };
+        (r + self.idata.modrm.reg) as usize
+    }
+
+    pub(super) fn xmm_rm(&self) -> usize {
+        let b = if let Some(rex) = self.pdata.rex { rex.b << 3 } else { 0 };
+        (b + self.idata.modrm.rm) as usize
+    }
+
+    pub(super) fn write_xmm(&mut self, n: usize, v: u128) -> () {
+        if self.pdata.vex.is_some() {
+            self.ac.core.sse.set_ymm(n, [v, 0]);
+        } else {
+            self.ac.core.sse.set_xmm(n, v);
+        }
+    }
+
+    fn get_xmm_v(&self) -> u128 {
+        match self.pdata.vex {
+            Some(vex) => self.ac.core.sse.get_xmm(vex.vvvv as usize),
+            None => self.get_xmm_r(),
+        }
+    }
+
+    fn get_xmm_r(&self) -> u128 { self.ac.core.sse.get_xmm(self.xmm_r()) }
+    fn set_xmm_r(&mut self, v: u128) -> () { let n = self.xmm_r(); self.write_xmm(n, v); }
+    fn get_xmm_rm(&self) -> u128 { self.ac.core.sse.get_xmm(self.xmm_rm()) }
+    fn set_xmm_rm(&mut self, v: u128) -> () { let n = self.xmm_rm(); self.write_xmm(n, v); }
+
+    pub(super) fn get_xmm_rm128(&self, align: bool) -> Result<u128, EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            Ok(self.get_xmm_rm())
+        } else {
+            self.get_m128(self.get_m()?, align)
+        }
+    }
+
+    fn set_xmm_rm128(&mut self, v: u128, align: bool) -> Result<(), EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            self.set_xmm_rm(v);
+            Ok(())
+        } else {
+            self.set_m128(self.get_m()?, v, align)
+        }
+    }
+
+    pub(super) fn get_xmm_rm_lane(&self, bits: usize) -> Result<u64, EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            return Ok(lane(self.get_xmm_rm(), 0, bits));
+        }
+
+        let m = self.get_m()?;
+        match bits {
+            8  => Ok(self.ac.get_data8(m)? as u64),
+            16 => Ok(self.ac.get_data16(m)? as u64),
+            32 => Ok(self.ac.get_data32(m)? as u64),
+            _  => self.ac.get_data64(m),
+        }
+    }
+
+    fn set_xmm_rm_lane(&mut self, v: u64, bits: usize) -> Result<(), EmuException> {
+        if self.idata.modrm.mod_ == 3 {
+            let base = self.pdata.vex.map_or(self.get_xmm_rm(), |vex| self.ac.core.sse.get_xmm(vex.vvvv as usize));
+            self.set_xmm_rm(merge(base, v, bits));
+            Ok(())
+        } else if bits == 32 {
+            self.ac.set_data32(self.get_m()?, v as u32)
+        } else {
+            self.ac.set_data64(self.get_m()?, v)
+        }
+    }
+
+    pub(super) fn get_m128(&self, m: (SgReg, u64), align: bool) -> Result<u128, EmuException> {
+        if align && m.1 & 0xf != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        let lo = self.ac.get_data64(m)?;
+        let hi = self.ac.get_data64((m.0, m.1 + 8))?;
+        Ok((hi as u128) << 64 | lo as u128)
+    }
+
+    pub(super) fn set_m128(&mut self, m: (SgReg, u64), v: u128, align: bool) -> Result<(), EmuException> {
+        if align && m.1 & 0xf != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        self.ac.set_data64(m, v as u64)?;
+        self.ac.set_data64((m.0, m.1 + 8), (v >> 64) as u64)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn sse_exec_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    assert_eq!(packed_int(0xfc, 0xff_01, 0x01_ff), Some(0x00_00));
+    assert_eq!(packed_int(0xdc, 0xff_01, 0x01_ff), Some(0xff_ff));
+    assert_eq!(packed_int(0xe8, 0x80_7f, 0x01_ff), Some(0x80_7f));
+    assert_eq!(packed_int(0x63, 0x7fff_0100_ff80_0001, 0), Some(0x7f_7f_80_01));
+    assert_eq!(packed_int(0x60, 0x0201, 0x0403), Some(0x04_02_03_01));
+    assert_eq!(packed_int(0xe2, 0x80000000, 40), Some(0xffffffff));
+    assert_eq!(packed_int(0xf6, 0x0a_01, 0x01_0a), Some(18));
+    assert_eq!(packed_int(0x77, 0, 0), None);
+
+    let mut ex = FPUException::empty();
+    let one = 1f32.to_bits() as u64;
+    assert_eq!(fp_arith(0x58, one, one, 32, &mut ex), 2f32.to_bits() as u64);
+    assert_eq!(fp_arith(0x5e, one, 0, 32, &mut ex), f32::INFINITY.to_bits() as u64);
+    assert_eq!(ex, FPUException::ZE);
+    assert_eq!(fp_arith(0x5d, f32::NAN.to_bits() as u64, one, 32, &mut ex), one);
+    assert_eq!(fp_arith(0x51, 0, (-1f64).to_bits(), 64, &mut ex), indefinite(64));
+    assert!(ex.contains(FPUException::IE));
+    assert_eq!(fp_to_int(2.5, 32, Some(0), &mut ex), 2);
+    assert_eq!(fp_to_int(-2.5, 32, None, &mut ex), 0xfffffffe);
+    assert_eq!(fp_to_int(1e10, 32, None, &mut ex), 0x80000000);
+    let nan = f32::NAN.to_bits() as u64;
+    assert!(fp_compare(8, nan, one, 32, &mut ex));
+    assert!(!fp_compare(12, nan, one, 32, &mut ex));
+    assert!(fp_compare(13, one, one, 32, &mut ex));
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.prefix.mandatory = Some(parse::Mandatory::P66);
+    parse.instr.opcode = 0x158;
+    parse.instr.modrm.mod_ = 3;
+    parse.instr.modrm.rm = 1;
+
+    {
+        let mut exe = super::Exec::new(&mut ac, &parse);
+        match exe.sse() {
+            Err(EmuException::CPUException(CPUException::UD)) => {},
+            _ => panic!("expected #UD"),
+        }
+        exe.ac.core.cregs.4.OSFXSR = 1;
+        exe.ac.core.sse.set_xmm(0, 0x3ff0000000000000_4000000000000000);
+        exe.ac.core.sse.set_xmm(1, 0x4008000000000000_3ff8000000000000);
+        exe.sse().unwrap();
+        assert_eq!(exe.ac.core.sse.get_xmm(0), 0x4010000000000000_400c000000000000);
+    }
+
+    parse.prefix.mandatory = Some(parse::Mandatory::PF2);
+    parse.instr.opcode = 0x12f;
+    {
+        let mut exe = super::Exec::new(&mut ac, &parse);
+        match exe.sse() {
+            Err(EmuException::CPUException(CPUException::UD)) => {},
+            _ => panic!("expected #UD"),
+        }
+    }
+
+    parse.prefix.mandatory = Some(parse::Mandatory::P66);
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.sse().unwrap();
+    assert!(!exe.ac.core.rflags.is_carry());
+    assert!(!exe.ac.core.rflags.is_zero());
+    assert!(!exe.ac.core.rflags.is_parity());
+
+    exe.ac.core.sse.set_xmm(1, f64::NAN.to_bits() as u128);
+    exe.ac.core.cregs.4.OSXMMEXCPT = 1;
+    exe.ac.core.sse.mxcsr.from_u32(0x1f00);
+    match exe.sse() {
+        Err(EmuException::CPUException(CPUException::XF)) => {},
+        _ => panic!("expected #XM"),
+    }
+    assert_eq!(exe.ac.core.sse.mxcsr.to_u32(), 0x1f01);
+}
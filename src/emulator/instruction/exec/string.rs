@@ -66,6 +66,31 @@ macro_rules! scan_str {
     } };
 }
 
+macro_rules! in_str {
+    ( $size:expr ) => { paste::item! {
+        pub fn [<in_str $size>](&mut self) -> Result<(), EmuException> {
+            let di = self.get_di_addr(if self.ac.core.rflags.is_direction() { -$size/8 } else { $size/8 })?;
+            self.ac.check_writable(di, $size/8)?;
+
+            let port = self.get_dx()?;
+            let src = self.ac.[<in_ $size>](port)?;
+            self.ac.[<set_data $size>](di, src)
+        }
+    } };
+}
+
+macro_rules! out_str {
+    ( $size:expr ) => { paste::item! {
+        pub fn [<out_str $size>](&mut self) -> Result<(), EmuException> {
+            let si = self.get_si_addr(if self.ac.core.rflags.is_direction() { -$size/8 } else { $size/8 })?;
+
+            let src = self.ac.[<get_data $size>](si)?;
+            let port = self.get_dx()?;
+            self.ac.[<out_ $size>](port, src)
+        }
+    } };
+}
+
 macro_rules! repeat_reg {
     ( $size:expr, $reg:ty ) => { paste::item! {
         pub fn [<repeat_ $size>](&mut self) -> Result<(), EmuException> {
@@ -80,6 +105,10 @@ macro_rules! repeat_reg {
             if rep { self.ac.update_ip(-(self.idata.len as i64))?; }
             Ok(())
         }
+
+        pub fn [<rep_empty_ $size>](&self) -> Result<bool, EmuException> {
+            Ok(self.pdata.repeat.is_some() && self.ac.get_gpreg($reg)? == 0)
+        }
     } };
 }
 
@@ -109,6 +138,44 @@ impl<'a> super::Exec<'a> {
     scan_str!(32, eax);
     scan_str!(64, rax);
 
+    in_str!(8);
+    in_str!(16);
+    in_str!(32);
+
+    out_str!(8);
+    out_str!(16);
+    out_str!(32);
+
+    pub fn xlat(&mut self) -> Result<(), EmuException> {
+        let seg = self.pdata.segment.unwrap_or(SgReg::DS);
+        let al = self.get_al()? as u64;
+
+        let addr = match self.idata.adsize {
+            AcsSize::BIT16 => self.ac.get_gpreg(GpReg16::BX)?.wrapping_add(al as u16) as u64,
+            AcsSize::BIT32 => self.ac.get_gpreg(GpReg32::EBX)?.wrapping_add(al as u32) as u64,
+            AcsSize::BIT64 => self.ac.get_gpreg(GpReg64::RBX)?.wrapping_add(al),
+        };
+        let v = self.ac.get_data8((seg, addr))?;
+        self.set_al(v)
+    }
+
+    pub fn get_counter(&self) -> Result<u64, EmuException> {
+        Ok(match self.idata.adsize {
+            AcsSize::BIT16 => self.ac.get_gpreg(GpReg16::CX)? as u64,
+            AcsSize::BIT32 => self.ac.get_gpreg(GpReg32::ECX)? as u64,
+            AcsSize::BIT64 => self.ac.get_gpreg(GpReg64::RCX)?,
+        })
+    }
+
+    pub fn dec_counter(&mut self) -> Result<u64, EmuException> {
+        match self.idata.adsize {
+            AcsSize::BIT16 => self.ac.update_gpreg(GpReg16::CX, -1)?,
+            AcsSize::BIT32 => self.ac.update_gpreg(GpReg32::ECX, -1)?,
+            AcsSize::BIT64 => self.ac.update_gpreg(GpReg64::RCX, -1)?,
+        }
+        self.get_counter()
+    }
+
     fn get_si_addr(&mut self, step: i64) -> Result<(SgReg, u64), EmuException> {
         let seg = self.pdata.segment.unwrap_or(SgReg::DS);
         let addr = self.ac.get_gpreg(GpReg64::RSI)?;
@@ -153,4 +220,27 @@ impl<'a> super::Exec<'a> {
     repeat_reg!(16, GpReg16::CX);
     repeat_reg!(32, GpReg32::ECX);
     repeat_reg!(64, GpReg64::RCX);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+#[test]
+fn in_str_test() {
+    use crate::hardware;
+    use crate::hardware::processor::segment::SgDescCache;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    ac.mode = CpuMode::Protected;
+    ac.set_sgreg(SgReg::ES, 0x10, SgDescCache { limit: 0xff, ..Default::default() }).unwrap();
+    ac.set_gpreg(GpReg16::DI, 0x100).unwrap();
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.in_str8() {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        _ => panic!("expected #GP"),
+    }
+}
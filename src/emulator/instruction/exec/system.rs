@@ -0,0 +1,205 @@
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+use crate::emulator::instruction::Instruction;
+
+impl<'a> super::Exec<'a> {
+    pub fn check_umip(&self) -> Result<(), EmuException> {
+        if self.ac.core.cregs.4.UMIP == 1 && self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        Ok(())
+    }
+
+    pub fn ldtr_to_rm(&mut self) -> Result<(), EmuException> {
+        self.check_protected()?;
+        self.check_umip()?;
+        let sel = self.ac.get_ldtr()?;
+        self.sysreg_to_rm(sel as u64)
+    }
+
+    pub fn tr_to_rm(&mut self) -> Result<(), EmuException> {
+        self.check_protected()?;
+        self.check_umip()?;
+        let sel = self.ac.get_tr()?;
+        self.sysreg_to_rm(sel as u64)
+    }
+
+    pub fn msw_to_rm(&mut self) -> Result<(), EmuException> {
+        self.check_umip()?;
+        let cr0: u64 = self.ac.get_creg(0)?;
+        self.sysreg_to_rm(cr0)
+    }
+
+    pub fn msw_from_rm(&mut self) -> Result<(), EmuException> {
+        if self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let msw = self.get_rm16()? as u32;
+        let cr0: u32 = self.ac.get_creg(0)?;
+        self.ac.set_creg(0, (cr0 & !0xe) | (cr0 & 1) | (msw & 0xf))?;
+        self.ac.update_cpumode()?;
+        self.ac.update_pgmode()
+    }
+
+    pub fn clts(&mut self) -> Result<(), EmuException> {
+        if self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        self.ac.core.cregs.0.TS = 0;
+        Ok(())
+    }
+
+    pub fn swapgs(&mut self) -> Result<(), EmuException> {
+        if !self.is_mode64() {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.get_cpl()? > 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let sgregs = &mut self.ac.core.sgregs;
+        let base = sgregs.get(SgReg::GS).cache.base;
+        sgregs.get_mut(SgReg::GS).cache.base = sgregs.get(SgReg::KernelGS).cache.base;
+        sgregs.get_mut(SgReg::KernelGS).cache.base = base;
+        Ok(())
+    }
+
+    pub fn verify_segment(&mut self, write: bool) -> Result<(), EmuException> {
+        self.check_protected()?;
+
+        let sel = self.get_rm16()?;
+        let ok = match self.probe_descriptor(sel)? {
+            Some(raw) if (raw >> 44) & 1 == 1 => {
+                let ty = (raw >> 40) as u8 & 0xf;
+                match (ty & 8 == 0, write) {
+                    (true, _) => !write || ty & 2 != 0,
+                    (false, false) => ty & 2 != 0,
+                    (false, true) => false,
+                }
+            },
+            _ => false,
+        };
+        self.ac.core.rflags.set_zero(ok);
+        Ok(())
+    }
+
+    pub fn load_access_rights(&mut self, sel: u16) -> Result<Option<u32>, EmuException> {
+        self.check_protected()?;
+
+        let long = self.ac.mode == access::CpuMode::Long;
+        let raw = self.probe_descriptor(sel)?.filter(|raw| {
+            (raw >> 44) & 1 == 1 || match (raw >> 40) & 0xf {
+                2 | 9 | 0xb | 0xc => true,
+                1 | 3 | 4 | 5 => !long,
+                _ => false,
+            }
+        });
+        self.ac.core.rflags.set_zero(raw.is_some());
+        Ok(raw.map(|raw| (raw >> 32) as u32 & 0x00f0ff00))
+    }
+
+    pub fn load_segment_limit(&mut self, sel: u16) -> Result<Option<u32>, EmuException> {
+        self.check_protected()?;
+
+        let long = self.ac.mode == access::CpuMode::Long;
+        let raw = self.probe_descriptor(sel)?.filter(|raw| {
+            (raw >> 44) & 1 == 1 || match (raw >> 40) & 0xf {
+                2 | 9 | 0xb => true,
+                1 | 3 => !long,
+                _ => false,
+            }
+        });
+        self.ac.core.rflags.set_zero(raw.is_some());
+        Ok(raw.map(|raw| {
+            let limit = (raw & 0xffff) as u32 | (raw >> 32) as u32 & 0xf0000;
+            if (raw >> 55) & 1 == 1 { (limit << 12) | 0xfff } else { limit }
+        }))
+    }
+
+    fn check_protected(&self) -> Result<(), EmuException> {
+        if self.ac.test_cpumode(access::CpuMode::Real) || self.ac.test_cpumode(access::CpuMode::V8086) {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        Ok(())
+    }
+
+    fn probe_descriptor(&self, sel: u16) -> Result<Option<u64>, EmuException> {
+        let raw = match self.ac.obtain_gl_desc_raw(sel)? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+
+        let (ty, dpl) = ((raw >> 40) as u8 & 0x1f, (raw >> 45) as u8 & 3);
+        let conforming = ty & 0x1c == 0x1c;
+        if !conforming && (dpl < self.ac.get_cpl()? || dpl < (sel & 3) as u8) {
+            return Ok(None);
+        }
+        Ok(Some(raw))
+    }
+
+    fn sysreg_to_rm(&mut self, v: u64) -> Result<(), EmuException> {
+        if self.idata.modrm.mod_ != 3 {
+            return self.set_rm16(v as u16);
+        }
+
+        match Instruction::opad_size(&self.ac.oasz, self.pdata).op {
+            access::AcsSize::BIT16 => self.set_rm16(v as u16),
+            access::AcsSize::BIT32 => self.set_rm32(v as u32),
+            access::AcsSize::BIT64 => self.set_rm64(v),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn system_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.ldtr_to_rm() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        _ => panic!("expected #UD"),
+    }
+
+    exe.ac.set_gdtr(0x800, 0x1f).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x808), 0x00cf9a000000ffff).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x810), 0x0040f2001000007f).unwrap();
+    exe.ac.set_data64((SgReg::DS, 0x818), 0x0000e40000080000).unwrap();
+    exe.ac.core.cregs.0.PE = 1;
+    exe.ac.update_cpumode().unwrap();
+
+    assert_eq!(exe.load_access_rights(0x08).unwrap(), Some(0x00c09a00));
+    assert!(exe.ac.core.rflags.is_zero());
+    assert_eq!(exe.load_segment_limit(0x08).unwrap(), Some(0xffffffff));
+    assert_eq!(exe.load_segment_limit(0x13).unwrap(), Some(0x7f));
+    assert_eq!(exe.load_segment_limit(0x18).unwrap(), None);
+    assert!(!exe.ac.core.rflags.is_zero());
+    assert_eq!(exe.load_access_rights(0x18).unwrap(), Some(0x0000e400));
+    assert_eq!(exe.load_access_rights(0x20).unwrap(), None);
+
+    exe.set_rm16(0x10).unwrap();
+    exe.verify_segment(true).unwrap();
+    assert!(exe.ac.core.rflags.is_zero());
+    exe.set_rm16(0x08).unwrap();
+    exe.verify_segment(true).unwrap();
+    assert!(!exe.ac.core.rflags.is_zero());
+    exe.verify_segment(false).unwrap();
+    assert!(exe.ac.core.rflags.is_zero());
+
+    exe.ac.core.cregs.0.TS = 1;
+    exe.set_rm16(0xfff0).unwrap();
+    exe.msw_from_rm().unwrap();
+    assert_eq!(exe.ac.core.cregs.0.PE, 1);
+    assert_eq!(exe.ac.core.cregs.0.TS, 0);
+    exe.msw_to_rm().unwrap();
+    assert_eq!(exe.get_rm16().unwrap() & 0xf, 0x0001);
+}
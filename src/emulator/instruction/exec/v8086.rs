@@ -0,0 +1,253 @@
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+
+const TF: u64   = 1<<8;
+const IF: u64   = 1<<9;
+const IOPL: u64 = 3<<12;
+const RF: u64   = 1<<16;
+const VM: u64   = 1<<17;
+const VIF: u64  = 1<<19;
+const VIP: u64  = 1<<20;
+
+impl<'a> super::Exec<'a> {
+    pub fn clear_interrupt(&mut self) -> Result<(), EmuException> {
+        if self.is_virtual_interrupt()? {
+            self.ac.core.rflags.set_vinterrupt(false);
+        } else {
+            self.ac.core.rflags.set_interrupt(false);
+        }
+        Ok(())
+    }
+
+    pub fn set_interrupt(&mut self) -> Result<(), EmuException> {
+        if self.is_virtual_interrupt()? {
+            if self.ac.core.rflags.is_vinterrupt_pending() {
+                return Err(EmuException::CPUException(CPUException::GP(None)));
+            }
+            self.ac.core.rflags.set_vinterrupt(true);
+        } else {
+            self.ac.core.rflags.set_interrupt(true);
+        }
+        Ok(())
+    }
+
+    pub fn get_pushf(&self, op16: bool) -> Result<u64, EmuException> {
+        let flags = self.ac.get_rflags()?;
+
+        if self.ac.mode == access::CpuMode::V8086 && self.ac.core.rflags.get_iopl() < 3 {
+            if self.ac.core.cregs.4.VME == 0 || !op16 {
+                return Err(EmuException::CPUException(CPUException::GP(None)));
+            }
+            let vif = if self.ac.core.rflags.is_vinterrupt() { IF } else { 0 };
+            return Ok((flags & !IF) | IOPL | vif);
+        }
+        Ok(flags & !(RF | VM))
+    }
+
+    pub fn set_popf(&mut self, v: u64, op16: bool) -> Result<(), EmuException> {
+        let keep = match self.ac.mode {
+            access::CpuMode::V8086 => {
+                let flags = self.v86_flags(v, op16)?;
+                return self.ac.set_rflags(flags);
+            },
+            access::CpuMode::Real => VM | VIF | VIP,
+            _ => {
+                let (cpl, iopl) = (self.ac.get_cpl()?, self.ac.core.rflags.get_iopl());
+                (VM | VIF | VIP) | if cpl > 0 { IOPL } else { 0 } | if cpl > iopl { IF } else { 0 }
+            },
+        };
+
+        let cur = self.ac.get_rflags()?;
+        let v = if op16 { (cur & !0xffff) | (v & 0xffff) } else { v };
+        self.ac.set_rflags((v & !keep) | (cur & keep))
+    }
+
+    pub fn int_n(&mut self, n: u8) -> Result<(), EmuException> {
+        if self.ac.mode == access::CpuMode::V8086 {
+            let iopl3 = self.ac.core.rflags.get_iopl() == 3;
+            if self.ac.core.cregs.4.VME == 1 && !self.is_int_redirected(n)? {
+                return self.v86_int(n, iopl3);
+            }
+            if !iopl3 {
+                return Err(EmuException::CPUException(CPUException::GP(None)));
+            }
+        }
+        Err(EmuException::Interrupt(n))
+    }
+
+    pub fn v86_flags(&self, v: u64, op16: bool) -> Result<u64, EmuException> {
+        let cur = self.ac.get_rflags()?;
+        let v = if op16 { (cur & !0xffff) | (v & 0xffff) } else { v };
+
+        if self.ac.core.rflags.get_iopl() == 3 {
+            let keep = IOPL | VM | VIF | VIP;
+            return Ok((v & !keep) | (cur & keep));
+        }
+        if self.ac.core.cregs.4.VME == 0 || !op16 || v & TF != 0 || (v & IF != 0 && cur & VIP != 0) {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let vif = if v & IF != 0 { VIF } else { 0 };
+        Ok((v & !(IF | IOPL | VIF)) | (cur & (IF | IOPL)) | vif)
+    }
+
+    pub fn iret_to_v86(&mut self, ip: u32, cs: u16, flags: u32) -> Result<(), EmuException> {
+        let esp = self.ac.pop_u32()?;
+        let ss = self.ac.pop_u32()? as u16;
+        let mut sgs = [0u16; 4];
+        for sg in sgs.iter_mut() {
+            *sg = self.ac.pop_u32()? as u16;
+        }
+
+        self.ac.set_rflags(flags as u64)?;
+        self.ac.update_cpumode()?;
+
+        self.ac.load_segment(SgReg::CS, cs)?;
+        self.ac.load_segment(SgReg::SS, ss)?;
+        for (r, sg) in [SgReg::ES, SgReg::DS, SgReg::FS, SgReg::GS].iter().zip(sgs.iter()) {
+            self.ac.load_segment(*r, *sg)?;
+        }
+        self.ac.update_opadsize()?;
+        self.ac.update_stacksize()?;
+
+        self.ac.set_gpreg(GpReg32::ESP, esp)?;
+        self.ac.set_ip(ip as u64)
+    }
+
+    fn is_virtual_interrupt(&self) -> Result<bool, EmuException> {
+        let iopl = self.ac.core.rflags.get_iopl();
+        let cr4 = &self.ac.core.cregs.4;
+
+        match self.ac.mode {
+            access::CpuMode::Real => Ok(false),
+            access::CpuMode::V8086 if iopl == 3 => Ok(false),
+            access::CpuMode::V8086 if cr4.VME == 1 => Ok(true),
+            access::CpuMode::V8086 => Err(EmuException::CPUException(CPUException::GP(None))),
+            _ => {
+                let cpl = self.ac.get_cpl()?;
+                if cpl <= iopl {
+                    Ok(false)
+                } else if cpl == 3 && cr4.PVI == 1 {
+                    Ok(true)
+                } else {
+                    Err(EmuException::CPUException(CPUException::GP(None)))
+                }
+            },
+        }
+    }
+
+    fn is_int_redirected(&self, n: u8) -> Result<bool, EmuException> {
+        let tss = self.ac.core.dtregs.tr.cache;
+
+        let mut io_base: u16 = 0;
+        self.ac.read_l_sys(&mut io_base as *mut u16 as *mut _, tss.base + 0x66, std::mem::size_of_val(&io_base))?;
+        let ofs = (io_base as u64).wrapping_sub(32) + (n >> 3) as u64;
+        if io_base < 32 || ofs > tss.limit as u64 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let mut map: u8 = 0;
+        self.ac.read_l_sys(&mut map as *mut u8 as *mut _, tss.base + ofs, std::mem::size_of_val(&map))?;
+        Ok((map >> (n & 7)) & 1 != 0)
+    }
+
+    fn v86_int(&mut self, n: u8, iopl3: bool) -> Result<(), EmuException> {
+        let mut ivt = [0u16; 2];
+        self.ac.read_l(ivt.as_mut_ptr() as *mut _, (n as u64) << 2, std::mem::size_of_val(&ivt))?;
+
+        let flags = self.ac.get_rflags()?;
+        let flags = if iopl3 { flags } else {
+            let vif = if self.ac.core.rflags.is_vinterrupt() { IF } else { 0 };
+            (flags & !IF) | IOPL | vif
+        };
+        let cs = self.ac.get_sgreg(SgReg::CS)?.0;
+        let ip = self.ac.get_ip()? as u16;
+        self.ac.push_u16(flags as u16)?;
+        self.ac.push_u16(cs)?;
+        self.ac.push_u16(ip)?;
+
+        let rf = &mut self.ac.core.rflags;
+        if iopl3 { rf.set_interrupt(false); } else { rf.set_vinterrupt(false); }
+        rf.set_trap(false);
+
+        self.ac.load_segment(SgReg::CS, ivt[1])?;
+        self.ac.set_ip(ivt[0] as u64)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn v8086_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x4000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let parse: parse::ParseInstr = Default::default();
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    exe.ac.core.cregs.0.PE = 1;
+    exe.ac.update_cpumode().unwrap();
+    exe.ac.core.dtregs.tr.cache.base = 0x1000;
+    exe.ac.core.dtregs.tr.cache.limit = 0x88 + 32;
+    exe.ac.set_data16((SgReg::DS, 0x1066), 0x88).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x106c), 0x00000001).unwrap();
+    exe.ac.set_data32((SgReg::DS, 0x21 * 4), 0x02001234).unwrap();
+
+    exe.ac.set_gpreg(GpReg32::ESP, 0x3000).unwrap();
+    for v in [0x40u32, 0x30, 0x20, 0x10, 0x50, 0x2ff0, 0x20202, 0x100, 0x80].iter() {
+        exe.ac.push_u32(*v).unwrap();
+    }
+    let (ip, cs, flags) = (exe.ac.pop_u32().unwrap(), exe.ac.pop_u32().unwrap() as u16, exe.ac.pop_u32().unwrap());
+    exe.iret_to_v86(ip, cs, flags).unwrap();
+    assert!(exe.ac.test_cpumode(access::CpuMode::V8086));
+    assert_eq!(exe.ac.get_cpl().unwrap(), 3);
+    assert_eq!(exe.ac.get_sgreg(SgReg::DS).unwrap().1.base, 0x200);
+    assert_eq!(exe.ac.get_gpreg(GpReg16::SP).unwrap(), 0x2ff0);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x80);
+
+    match exe.clear_interrupt() {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        _ => panic!("expected #GP"),
+    }
+    exe.ac.core.cregs.4.VME = 1;
+    exe.clear_interrupt().unwrap();
+    assert!(exe.ac.core.rflags.is_interrupt());
+    assert!(!exe.ac.core.rflags.is_vinterrupt());
+    assert_eq!(exe.get_pushf(true).unwrap() & (IF | IOPL), IOPL);
+    exe.set_popf(IF, true).unwrap();
+    assert!(exe.ac.core.rflags.is_vinterrupt());
+    assert!(exe.get_pushf(false).is_err());
+
+    match exe.int_n(0x20) {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        _ => panic!("expected #GP"),
+    }
+    exe.int_n(0x21).unwrap();
+    assert_eq!(exe.ac.get_sgreg(SgReg::CS).unwrap().0, 0x200);
+    assert_eq!(exe.ac.get_ip().unwrap(), 0x1234);
+    assert!(!exe.ac.core.rflags.is_vinterrupt());
+    assert_eq!(exe.ac.pop_u16().unwrap(), 0x80);
+    assert_eq!(exe.ac.pop_u16().unwrap(), 0x100);
+    assert_eq!(exe.ac.pop_u16().unwrap() as u64 & IF, IF);
+
+    exe.ac.set_gdtr(0x800, 0x17).unwrap();
+    exe.ac.core.dtregs.tr.attr = 0x8b;
+    {
+        let mut mem = exe.ac.mem.write().unwrap();
+        mem.write64(0x810, 0x0040920000000000);
+        mem.write32(0x1004, 0x1800);
+        mem.write32(0x1008, 0x10);
+    }
+    let (ss, sp) = (exe.ac.get_sgreg(SgReg::SS).unwrap().1.base, exe.ac.get_gpreg(GpReg32::ESP).unwrap());
+    match exe.ac.save_regs(access::AcsSize::BIT32, Some(0)) {
+        Err(EmuException::CPUException(CPUException::SS(None))) => {},
+        r => panic!("{:?}", r),
+    }
+    assert!(exe.ac.test_cpumode(access::CpuMode::V8086));
+    assert_eq!(exe.ac.get_sgreg(SgReg::SS).unwrap().1.base, ss);
+    assert_eq!(exe.ac.get_gpreg(GpReg32::ESP).unwrap(), sp);
+    assert_eq!(exe.ac.get_sgreg(SgReg::DS).unwrap().1.base, 0x200);
+}
@@ -0,0 +1,285 @@
+use crate::emulator::*;
+use crate::emulator::access::register::*;
+use crate::hardware::processor::sse::{MXCSR_MASK, XCR0};
+
+impl<'a> super::Exec<'a> {
+    pub fn fxsave(&mut self) -> Result<(), EmuException> {
+        let m = self.fxsave_area()?;
+
+        self.save_x87(m)?;
+        self.save_mxcsr(m)?;
+        self.save_xmm(m)
+    }
+
+    pub fn fxrstor(&mut self) -> Result<(), EmuException> {
+        let m = self.fxsave_area()?;
+
+        self.load_mxcsr(m)?;
+        self.load_x87(m)?;
+        self.load_xmm(m)
+    }
+
+    pub fn xsave(&mut self) -> Result<(), EmuException> {
+        let (m, rfbm) = self.xsave_area()?;
+
+        if rfbm.contains(XCR0::X87) {
+            self.save_x87(m)?;
+        }
+        if rfbm.intersects(XCR0::SSE | XCR0::AVX) {
+            self.save_mxcsr(m)?;
+        }
+        if rfbm.contains(XCR0::SSE) {
+            self.save_xmm(m)?;
+        }
+        if rfbm.contains(XCR0::AVX) {
+            self.save_ymmh(m)?;
+        }
+
+        let bv = self.ac.get_data64((m.0, m.1 + 512))?;
+        self.ac.set_data64((m.0, m.1 + 512), bv | rfbm.bits())
+    }
+
+    pub fn xrstor(&mut self) -> Result<(), EmuException> {
+        let (m, rfbm) = self.xsave_area()?;
+
+        let bv = self.ac.get_data64((m.0, m.1 + 512))?;
+        let comp = self.ac.get_data64((m.0, m.1 + 520))?;
+        if comp != 0 || bv & !self.ac.core.sse.xcr0.bits() != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        let bv = XCR0::from_bits_truncate(bv);
+
+        if rfbm.intersects(XCR0::SSE | XCR0::AVX) {
+            self.load_mxcsr(m)?;
+        }
+        if rfbm.contains(XCR0::X87) {
+            if bv.contains(XCR0::X87) { self.load_x87(m)?; } else { self.ac.core.fpu.init(); }
+        }
+        if rfbm.contains(XCR0::SSE) {
+            if bv.contains(XCR0::SSE) {
+                self.load_xmm(m)?;
+            } else {
+                for i in 0..self.xmm_count() {
+                    self.ac.core.sse.set_xmm(i, 0);
+                }
+            }
+        }
+        if rfbm.contains(XCR0::AVX) {
+            if bv.contains(XCR0::AVX) {
+                self.load_ymmh(m)?;
+            } else {
+                for i in 0..self.xmm_count() {
+                    let v = self.ac.core.sse.get_xmm(i);
+                    self.ac.core.sse.set_ymm(i, [v, 0]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn xgetbv(&mut self) -> Result<(), EmuException> {
+        if self.ac.core.cregs.4.OSXSAVE == 0 {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.get_gpreg(GpReg32::ECX)? != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let v = self.ac.core.sse.xcr0.bits();
+        self.set_edx((v >> 32) as u32)?;
+        self.set_eax(v as u32)
+    }
+
+    pub fn xsetbv(&mut self) -> Result<(), EmuException> {
+        if self.ac.core.cregs.4.OSXSAVE == 0 {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.get_cpl()? > 0 || self.ac.get_gpreg(GpReg32::ECX)? != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+
+        let v = ((self.get_edx()? as u64) << 32) + self.get_eax()? as u64;
+        match XCR0::from_bits(v) {
+            Some(xcr0) if xcr0.contains(XCR0::X87) && (xcr0.contains(XCR0::SSE) || !xcr0.contains(XCR0::AVX)) => {
+                self.ac.core.sse.xcr0 = xcr0;
+                Ok(())
+            },
+            _ => Err(EmuException::CPUException(CPUException::GP(None))),
+        }
+    }
+
+    fn fxsave_area(&self) -> Result<(SgReg, u64), EmuException> {
+        let cr0 = &self.ac.core.cregs.0;
+        if cr0.EM == 1 || cr0.TS == 1 {
+            return Err(EmuException::CPUException(CPUException::NM));
+        }
+
+        let (sg, adr) = self.get_m()?;
+        if adr & 0xf != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        Ok((sg, adr))
+    }
+
+    fn xsave_area(&self) -> Result<((SgReg, u64), XCR0), EmuException> {
+        if self.ac.core.cregs.4.OSXSAVE == 0 {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+        if self.ac.core.cregs.0.TS == 1 {
+            return Err(EmuException::CPUException(CPUException::NM));
+        }
+
+        let (sg, adr) = self.get_m()?;
+        if adr & 0x3f != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        let mask = ((self.get_edx()? as u64) << 32) + self.get_eax()? as u64;
+        Ok(((sg, adr), self.ac.core.sse.xcr0 & XCR0::from_bits_truncate(mask)))
+    }
+
+    fn save_x87(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        let rexw = self.is_rexw();
+
+        let fpu = &self.ac.core.fpu;
+        let tw = fpu.tag_word();
+        let ftw = (0..8).fold(0u64, |t, i| if (tw >> (i * 2)) & 3 != 3 { t | 1 << i } else { t });
+        let head = [
+            fpu.control.to_u16() as u64 | (fpu.status.to_u16() as u64) << 16 | ftw << 32 | (fpu.fop as u64) << 48,
+            if rexw { fpu.fip } else { fpu.fip as u32 as u64 | (fpu.fcs as u64) << 32 },
+            if rexw { fpu.fdp } else { fpu.fdp as u32 as u64 | (fpu.fds as u64) << 32 },
+        ];
+        let st: Vec<[u8; 10]> = (0..8).map(|i| fpu.get_raw(i)).collect();
+
+        for (i, v) in head.iter().enumerate() {
+            self.ac.set_data64((sg, adr + i as u64 * 8), *v)?;
+        }
+        for (i, raw) in st.iter().enumerate() {
+            let mut v = [0; 16];
+            v[..10].copy_from_slice(raw);
+            self.set_m128((sg, adr + 32 + i as u64 * 16), u128::from_le_bytes(v), false)?;
+        }
+        Ok(())
+    }
+
+    fn load_x87(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        let rexw = self.is_rexw();
+
+        let mut head = [0u64; 3];
+        for (i, v) in head.iter_mut().enumerate() {
+            *v = self.ac.get_data64((sg, adr + i as u64 * 8))?;
+        }
+        let mut st = [[0u8; 10]; 8];
+        for (i, raw) in st.iter_mut().enumerate() {
+            raw.copy_from_slice(&self.get_m128((sg, adr + 32 + i as u64 * 16), false)?.to_le_bytes()[..10]);
+        }
+
+        let fpu = &mut self.ac.core.fpu;
+        fpu.control.from_u16(head[0] as u16);
+        fpu.status.from_u16((head[0] >> 16) as u16);
+        for (i, raw) in st.iter().enumerate() {
+            fpu.set_raw(i, *raw);
+        }
+        let ftw = (head[0] >> 32) as u8;
+        fpu.set_tag_word((0..8).fold(0, |t, i| if (ftw >> i) & 1 == 0 { t | 3 << (i * 2) } else { t }));
+        fpu.fop = (head[0] >> 48) as u16 & 0x7ff;
+        if rexw {
+            fpu.fip = head[1];
+            fpu.fdp = head[2];
+        } else {
+            fpu.fip = head[1] as u32 as u64;
+            fpu.fcs = (head[1] >> 32) as u16;
+            fpu.fdp = head[2] as u32 as u64;
+            fpu.fds = (head[2] >> 32) as u16;
+        }
+        Ok(())
+    }
+
+    fn save_mxcsr(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        let v = self.ac.core.sse.mxcsr.to_u32() as u64 | (MXCSR_MASK as u64) << 32;
+        self.ac.set_data64((sg, adr + 24), v)
+    }
+
+    fn load_mxcsr(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        let v = self.ac.get_data32((sg, adr + 24))?;
+        if v & !MXCSR_MASK != 0 {
+            return Err(EmuException::CPUException(CPUException::GP(None)));
+        }
+        self.ac.core.sse.mxcsr.from_u32(v);
+        Ok(())
+    }
+
+    fn save_xmm(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        for i in 0..self.xmm_count() {
+            let v = self.ac.core.sse.get_xmm(i);
+            self.set_m128((sg, adr + 160 + i as u64 * 16), v, false)?;
+        }
+        Ok(())
+    }
+
+    fn load_xmm(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        for i in 0..self.xmm_count() {
+            let v = self.get_m128((sg, adr + 160 + i as u64 * 16), false)?;
+            self.ac.core.sse.set_xmm(i, v);
+        }
+        Ok(())
+    }
+
+    fn save_ymmh(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        for i in 0..self.xmm_count() {
+            let v = self.ac.core.sse.get_ymm(i)[1];
+            self.set_m128((sg, adr + 576 + i as u64 * 16), v, false)?;
+        }
+        Ok(())
+    }
+
+    fn load_ymmh(&mut self, (sg, adr): (SgReg, u64)) -> Result<(), EmuException> {
+        for i in 0..self.xmm_count() {
+            let v = [self.ac.core.sse.get_xmm(i), self.get_m128((sg, adr + 576 + i as u64 * 16), false)?];
+            self.ac.core.sse.set_ymm(i, v);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn xsave_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.rm = 6;
+    parse.instr.disp = 0x440;
+
+    let mut exe = super::Exec::new(&mut ac, &parse);
+    match exe.xsetbv() {
+        Err(EmuException::CPUException(CPUException::UD)) => {},
+        _ => panic!("expected #UD"),
+    }
+    exe.ac.core.cregs.4.OSXSAVE = 1;
+    exe.set_eax(0b100).unwrap();
+    match exe.xsetbv() {
+        Err(EmuException::CPUException(CPUException::GP(None))) => {},
+        _ => panic!("expected #GP"),
+    }
+    exe.set_eax(0b111).unwrap();
+    exe.xsetbv().unwrap();
+    exe.set_eax(0).unwrap();
+    exe.xgetbv().unwrap();
+    assert_eq!(exe.get_eax().unwrap(), 0b111);
+
+    exe.ac.core.sse.set_ymm(3, [0x11, 0x22]);
+    exe.set_eax(0b110).unwrap();
+    exe.xsave().unwrap();
+    assert_eq!(exe.ac.get_data64((SgReg::DS, 0x440 + 512)).unwrap(), 0b110);
+    assert_eq!(exe.get_m128((SgReg::DS, 0x440 + 576 + 3 * 16), false).unwrap(), 0x22);
+
+    exe.ac.core.sse.set_ymm(3, [0, 0]);
+    exe.set_eax(0b100).unwrap();
+    exe.xrstor().unwrap();
+    assert_eq!(exe.ac.core.sse.get_ymm(3), [0, 0x22]);
+}
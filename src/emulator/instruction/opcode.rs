@@ -10,19 +10,22 @@ use super::access;
 use crate::emulator::{EmuException, CPUException};
 
 bitflags! {
-    pub(super) struct OpFlags: u8 {
-        const NONE  = 0b00000000;
-        const MODRM = 0b00000001;
-        const IMM   = 0b00000010;
-        const PTR16 = 0b00000100;
-        const MOFFS = 0b00001000;
-        const SZ8   = 0b00010000;
-        const SZ16  = 0b00100000;
-        const SZ32  = 0b01000000;
-        const SZ64  = 0b10000000;
+    pub(super) struct OpFlags: u16 {
+        const NONE  = 0b000000000;
+        const MODRM = 0b000000001;
+        const IMM   = 0b000000010;
+        const PTR16 = 0b000000100;
+        const MOFFS = 0b000001000;
+        const SZ8   = 0b000010000;
+        const SZ16  = 0b000100000;
+        const SZ32  = 0b001000000;
+        const SZ64  = 0b010000000;
+        const SIMD  = 0b100000000;
+        const SZ24      = Self::SZ8.bits | Self::SZ16.bits;
         const SZBIT     = Self::SZ8.bits | Self::SZ16.bits | Self::SZ32.bits | Self::SZ64.bits;
         const IMM8      = Self::IMM.bits | Self::SZ8.bits;
         const IMM16     = Self::IMM.bits | Self::SZ16.bits;
+        const IMM24     = Self::IMM.bits | Self::SZ24.bits;
         const IMM32     = Self::IMM.bits | Self::SZ32.bits;
         const IMM64     = Self::IMM.bits | Self::SZ64.bits;
     }
@@ -39,9 +42,17 @@ impl Default for OpcodeType {
     }
 }
 
-const MAX_OPCODE: usize = 0x200;
+const MAX_OPCODE: usize = 0x400;
 type OpcodeArr = [OpcodeType; MAX_OPCODE];
 
+const fn opcode_index(n: u32) -> usize {
+    match n >> 8 {
+        0x0f38 => 0x200 + (n & 0xff) as usize,
+        0x0f3a => 0x300 + (n & 0xff) as usize,
+        _ => (n & 0x1ff) as usize,
+    }
+}
+
 pub(super) struct Opcode {
     op16: opcode16::Opcode16,
     op32: opcode32::Opcode32,
@@ -1,23 +1,31 @@
 use crate::emulator::instruction::exec;
 use crate::emulator::instruction::opcode::*;
+use crate::emulator::access::register::SgReg;
 
 pub fn init_cmn_opcode(op: &mut super::OpcodeArr){
     macro_rules! setcmnop {
-        ($n:expr, $fnc:ident, $flg:expr) => { op[$n & 0x1ff] = OpcodeType{func:$fnc, flag:$flg} }
+        ($n:expr, $fnc:ident, $flg:expr) => { op[opcode_index($n)] = OpcodeType{func:$fnc, flag:$flg} }
     }
 
     setcmnop!(0x00, add_rm8_r8,    OpFlags::MODRM);
     setcmnop!(0x02, add_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x04, add_al_imm8,   OpFlags::IMM8);
+    setcmnop!(0x06, push_es,       OpFlags::NONE);
+    setcmnop!(0x07, pop_es,        OpFlags::NONE);
     setcmnop!(0x08, or_rm8_r8,     OpFlags::MODRM);
     setcmnop!(0x0a, or_r8_rm8,     OpFlags::MODRM);
     setcmnop!(0x0c, or_al_imm8,    OpFlags::IMM8);
+    setcmnop!(0x0e, push_cs,       OpFlags::NONE);
     setcmnop!(0x10, adc_rm8_r8,    OpFlags::MODRM);
     setcmnop!(0x12, adc_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x14, adc_al_imm8,   OpFlags::IMM8);
+    setcmnop!(0x16, push_ss,       OpFlags::NONE);
+    setcmnop!(0x17, pop_ss,        OpFlags::NONE);
     setcmnop!(0x18, sbb_rm8_r8,    OpFlags::MODRM);
     setcmnop!(0x1a, sbb_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x1c, sbb_al_imm8,   OpFlags::IMM8);
+    setcmnop!(0x1e, push_ds,       OpFlags::NONE);
+    setcmnop!(0x1f, pop_ds,        OpFlags::NONE);
     setcmnop!(0x20, and_rm8_r8,    OpFlags::MODRM);
     setcmnop!(0x22, and_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x24, and_al_imm8,   OpFlags::IMM8);
@@ -27,9 +35,13 @@ pub fn init_cmn_opcode(op: &mut super::OpcodeArr){
     setcmnop!(0x30, xor_rm8_r8,    OpFlags::MODRM);
     setcmnop!(0x32, xor_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x34, xor_al_imm8,   OpFlags::IMM8);
+    setcmnop!(0x27, daa,           OpFlags::NONE);
+    setcmnop!(0x2f, das,           OpFlags::NONE);
+    setcmnop!(0x37, aaa,           OpFlags::NONE);
     setcmnop!(0x38, cmp_rm8_r8,    OpFlags::MODRM);
     setcmnop!(0x3a, cmp_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x3c, cmp_al_imm8,   OpFlags::IMM8);
+    setcmnop!(0x3f, aas,           OpFlags::NONE);
     setcmnop!(0x70, jo_imm8,       OpFlags::IMM8);
     setcmnop!(0x71, jno_imm8,      OpFlags::IMM8);
     setcmnop!(0x72, jb_imm8,       OpFlags::IMM8);
@@ -52,6 +64,8 @@ pub fn init_cmn_opcode(op: &mut super::OpcodeArr){
     setcmnop!(0x8a, mov_r8_rm8,    OpFlags::MODRM);
     setcmnop!(0x8e, mov_sreg_rm16, OpFlags::MODRM);
     setcmnop!(0x90, nop,           OpFlags::NONE);
+    setcmnop!(0x9e, sahf,          OpFlags::NONE);
+    setcmnop!(0x9f, lahf,          OpFlags::NONE);
     setcmnop!(0xa0, mov_al_moffs8, OpFlags::MOFFS);
     setcmnop!(0xa2, mov_moffs8_al, OpFlags::MOFFS);
     setcmnop!(0xa8, test_al_imm8,  OpFlags::IMM8);
@@ -62,22 +76,49 @@ pub fn init_cmn_opcode(op: &mut super::OpcodeArr){
     setcmnop!(0xcc, int3,          OpFlags::NONE);
     setcmnop!(0xcd, int_imm8,      OpFlags::IMM8);
     setcmnop!(0xce, into,          OpFlags::NONE);
+    setcmnop!(0x9b, fwait,         OpFlags::NONE);
+    setcmnop!(0xd4, aam,           OpFlags::IMM8);
+    setcmnop!(0xd5, aad,           OpFlags::IMM8);
+    setcmnop!(0xd7, xlat,          OpFlags::NONE);
+    setcmnop!(0xd8, fpu_d8,        OpFlags::MODRM);
+    setcmnop!(0xd9, fpu_d9,        OpFlags::MODRM);
+    setcmnop!(0xda, fpu_da,        OpFlags::MODRM);
+    setcmnop!(0xdb, fpu_db,        OpFlags::MODRM);
+    setcmnop!(0xdc, fpu_dc,        OpFlags::MODRM);
+    setcmnop!(0xdd, fpu_dd,        OpFlags::MODRM);
+    setcmnop!(0xde, fpu_de,        OpFlags::MODRM);
+    setcmnop!(0xdf, fpu_df,        OpFlags::MODRM);
+    setcmnop!(0xe0, loopnz_imm8,   OpFlags::IMM8);
+    setcmnop!(0xe1, loopz_imm8,    OpFlags::IMM8);
+    setcmnop!(0xe2, loop_imm8,     OpFlags::IMM8);
+    setcmnop!(0xe3, jrcxz_imm8,    OpFlags::IMM8);
     setcmnop!(0xe4, in_al_imm8,    OpFlags::IMM8);
     setcmnop!(0xe6, out_imm8_al,   OpFlags::IMM8);
     setcmnop!(0xeb, jmp_imm8,      OpFlags::IMM8);
     setcmnop!(0xec, in_al_dx,      OpFlags::NONE);
     setcmnop!(0xee, out_dx_al,     OpFlags::NONE);
     setcmnop!(0xf1, icebp,         OpFlags::NONE);
+    setcmnop!(0xf5, cmc,           OpFlags::NONE);
+    setcmnop!(0xf8, clc,           OpFlags::NONE);
+    setcmnop!(0xf9, stc,           OpFlags::NONE);
     setcmnop!(0xfa, cli,           OpFlags::NONE);
     setcmnop!(0xfb, sti,           OpFlags::NONE);
     setcmnop!(0xfc, cld,           OpFlags::NONE);
     setcmnop!(0xfd, std,           OpFlags::NONE);
     setcmnop!(0xf4, hlt,           OpFlags::NONE);
 
+    setcmnop!(0x0f05, syscall,     OpFlags::NONE);
+    setcmnop!(0x0f06, clts,        OpFlags::NONE);
+    setcmnop!(0x0f07, sysret,      OpFlags::NONE);
     setcmnop!(0x0f20, mov_r32_cr,  OpFlags::MODRM);
+    setcmnop!(0x0f21, mov_r32_dr,  OpFlags::MODRM);
     setcmnop!(0x0f22, mov_cr_r32,  OpFlags::MODRM);
+    setcmnop!(0x0f23, mov_dr_r32,  OpFlags::MODRM);
     setcmnop!(0x0f30, wrmsr,       OpFlags::NONE);
     setcmnop!(0x0f32, rdmsr,       OpFlags::NONE);
+    setcmnop!(0x0f31, rdtsc,       OpFlags::NONE);
+    setcmnop!(0x0f34, sysenter,    OpFlags::NONE);
+    setcmnop!(0x0f35, sysexit,     OpFlags::NONE);
     setcmnop!(0x0f90, seto_rm8,    OpFlags::MODRM);
     setcmnop!(0x0f91, setno_rm8,   OpFlags::MODRM);
     setcmnop!(0x0f92, setb_rm8,    OpFlags::MODRM);
@@ -94,9 +135,42 @@ pub fn init_cmn_opcode(op: &mut super::OpcodeArr){
     setcmnop!(0x0f9d, setnl_rm8,   OpFlags::MODRM);
     setcmnop!(0x0f9e, setle_rm8,   OpFlags::MODRM);
     setcmnop!(0x0f9f, setnle_rm8,  OpFlags::MODRM);
+    setcmnop!(0x0fa0, push_fs,     OpFlags::NONE);
+    setcmnop!(0x0fa1, pop_fs,      OpFlags::NONE);
+    setcmnop!(0x0fa2, cpuid,       OpFlags::NONE);
+    setcmnop!(0x0fa8, push_gs,     OpFlags::NONE);
+    setcmnop!(0x0fa9, pop_gs,      OpFlags::NONE);
+    setcmnop!(0x0fae, code_0fae,   OpFlags::MODRM);
+    setcmnop!(0x0fb0, cmpxchg_rm8_r8, OpFlags::MODRM);
+    setcmnop!(0x0fc0, xadd_rm8_r8,  OpFlags::MODRM);
+    setcmnop!(0x0fc7, code_0fc7,   OpFlags::MODRM);
+
+    for n in 0x0f18..=0x0f1f {
+        setcmnop!(n, nop, OpFlags::MODRM);
+    }
+    for n in (0x0f10..=0x0f17).chain(0x0f28..=0x0f2f).chain(0x0f50..=0x0f6f).chain(0x0f74..=0x0f76).chain(0x0f7e..=0x0f7f).chain(0x0fd1..=0x0ffe) {
+        setcmnop!(n, sse, OpFlags::MODRM | OpFlags::SIMD);
+    }
+    for n in [0x0f70, 0x0f71, 0x0f72, 0x0f73, 0x0fc2, 0x0fc4, 0x0fc5, 0x0fc6].iter() {
+        setcmnop!(*n, sse, OpFlags::MODRM | OpFlags::IMM8 | OpFlags::SIMD);
+    }
+    setcmnop!(0x0fc3, sse,         OpFlags::MODRM | OpFlags::SIMD);
+    setcmnop!(0x0f77, sse,         OpFlags::SIMD);
+    for n in [0x00, 0x17, 0x18, 0x19, 0x1a, 0x29, 0x36, 0x37, 0x58, 0x59, 0x5a, 0x78, 0x79].iter() {
+        setcmnop!(0x0f3800 + *n, sse, OpFlags::MODRM | OpFlags::SIMD);
+    }
+    for n in [0x00, 0x01, 0x06, 0x0f, 0x18, 0x19, 0x38, 0x39, 0x46, 0x4c].iter() {
+        setcmnop!(0x0f3a00 + *n, sse, OpFlags::MODRM | OpFlags::IMM8 | OpFlags::SIMD);
+    }
+
+    setcmnop!(0x0f3882, invpcid_r_m128, OpFlags::MODRM);
+    for n in [0xf2, 0xf3, 0xf7].iter() {
+        setcmnop!(0x0f3800 + *n, bmi1, OpFlags::MODRM | OpFlags::SIMD);
+    }
 
     setcmnop!(0x80, code_80,       OpFlags::MODRM | OpFlags::IMM8);
     setcmnop!(0xc0, code_c0,       OpFlags::MODRM | OpFlags::IMM8);
+    setcmnop!(0xd0, code_d0,       OpFlags::MODRM);
     setcmnop!(0xd2, code_d2,       OpFlags::MODRM);
     setcmnop!(0xf6, code_f6,       OpFlags::MODRM | OpFlags::IMM8);
     setcmnop!(0xfe, code_fe,       OpFlags::MODRM);
@@ -135,6 +209,19 @@ cmp_dst_src!(8, rm8, r8);
 cmp_dst_src!(8, r8, rm8);
 cmp_dst_src!(8, al, imm8);
 
+fn push_es(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.push_sgreg(SgReg::ES) }
+fn pop_es(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.pop_sgreg(SgReg::ES) }
+fn push_cs(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.push_sgreg(SgReg::CS) }
+fn push_ss(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.push_sgreg(SgReg::SS) }
+fn pop_ss(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.pop_sgreg(SgReg::SS) }
+fn push_ds(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.push_sgreg(SgReg::DS) }
+fn pop_ds(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.pop_sgreg(SgReg::DS) }
+
+fn daa(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.daa() }
+fn das(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.das() }
+fn aaa(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.aaa() }
+fn aas(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.aas() }
+
 jcc_rel!(8, o, imm8);
 jcc_rel!(8, b, imm8);
 jcc_rel!(8, z, imm8);
@@ -155,6 +242,19 @@ mov_dst_src!(16, sreg, rm16);
 
 fn nop(_exec: &mut exec::Exec) -> Result<(), EmuException> { Ok(()) }
 
+fn sahf(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    let flag = exec.ac.get_rflags()?;
+    let ah = exec.get_ah()? as u64;
+    debug!("sahf: {:02x}", ah);
+    exec.ac.set_rflags((flag & !0xd5) | (ah & 0xd5))
+}
+
+fn lahf(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    let flag = exec.ac.get_rflags()? as u8;
+    debug!("lahf: {:02x}", flag);
+    exec.set_ah(flag)
+}
+
 mov_dst_src!(8, al, moffs8);
 mov_dst_src!(8, moffs8, al);
 
@@ -165,7 +265,7 @@ mov_dst_src!(8, opr8, imm8);
 mov_dst_src!(8, rm8, imm8);
 
 fn int3(_exec: &mut exec::Exec) -> Result<(), EmuException> { Err(EmuException::CPUException(CPUException::BP)) }
-fn int_imm8(exec: &mut exec::Exec) -> Result<(), EmuException> { Err(EmuException::Interrupt(exec.get_imm8()?)) }
+fn int_imm8(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.int_n(exec.get_imm8()?) }
 fn into(_exec: &mut exec::Exec) -> Result<(), EmuException> { Err(EmuException::CPUException(CPUException::OF)) }
 
 in_reg_port!(8, al, imm8);
@@ -173,24 +273,114 @@ out_port_reg!(8, imm8, al);
 
 jmp_rel!(8, imm8);
 
+fn loopnz_imm8(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.dec_counter()? != 0 && !exec.check_rflags_z()? { jmp_imm8(exec)?; }
+    Ok(())
+}
+
+fn loopz_imm8(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.dec_counter()? != 0 && exec.check_rflags_z()? { jmp_imm8(exec)?; }
+    Ok(())
+}
+
+fn loop_imm8(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.dec_counter()? != 0 { jmp_imm8(exec)?; }
+    Ok(())
+}
+
+fn jrcxz_imm8(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.get_counter()? == 0 { jmp_imm8(exec)?; }
+    Ok(())
+}
+
 in_reg_port!(8, al, dx);
 out_port_reg!(8, dx, al);
 
 fn icebp(_exec: &mut exec::Exec) -> Result<(), EmuException> { Err(EmuException::CPUException(CPUException::DB)) }
 
-fn cli(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ac.core.rflags.set_interrupt(false); Ok(()) }
-fn sti(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ac.core.rflags.set_interrupt(true); Ok(()) }
+fn cli(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.clear_interrupt() }
+fn sti(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.set_interrupt() }
 fn cld(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ac.core.rflags.set_direction(false); Ok(()) }
 fn std(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ac.core.rflags.set_direction(true); Ok(()) }
 
+fn cmc(exec: &mut exec::Exec) -> Result<(), EmuException> { let cf = exec.ac.core.rflags.is_carry(); exec.ac.core.rflags.set_carry(!cf); Ok(()) }
+fn clc(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ac.core.rflags.set_carry(false); Ok(()) }
+fn stc(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ac.core.rflags.set_carry(true); Ok(()) }
+
 fn hlt(_exec: &mut exec::Exec) -> Result<(), EmuException> { Err(EmuException::Halt) }
 
+fn aam(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.aam() }
+fn aad(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.aad() }
+fn xlat(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.xlat() }
+
+fn fwait(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_wait() }
+fn fpu_d8(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xd8) }
+fn fpu_d9(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xd9) }
+fn fpu_da(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xda) }
+fn fpu_db(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xdb) }
+fn fpu_dc(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xdc) }
+fn fpu_dd(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xdd) }
+fn fpu_de(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xde) }
+fn fpu_df(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.fpu_esc(0xdf) }
+
+pub fn invlpg_m8(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.ac.get_cpl()? > 0 {
+        return Err(EmuException::CPUException(CPUException::GP(None)));
+    }
+
+    let (sg, adr) = exec.get_m()?;
+    exec.ac.invalidate_page(sg, adr)
+}
+
+fn invpcid_r_m128(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.invpcid() }
+
+pub fn smsw_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.msw_to_rm() }
+pub fn lmsw_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.msw_from_rm() }
+pub fn swapgs(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.swapgs() }
+fn clts(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.clts() }
+
+pub fn clac(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.ac.get_cpl()? > 0 { return Err(EmuException::CPUException(CPUException::UD)); }
+    exec.ac.core.rflags.set_aligncheck(false);
+    Ok(())
+}
+
+pub fn stac(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    if exec.ac.get_cpl()? > 0 { return Err(EmuException::CPUException(CPUException::UD)); }
+    exec.ac.core.rflags.set_aligncheck(true);
+    Ok(())
+}
+
+fn syscall(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.syscall() }
+fn sysret(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.sysret() }
+
 fn mov_r32_cr(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.cr_to_reg() }
 fn mov_cr_r32(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.cr_from_reg() }
+fn mov_r32_dr(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.dr_to_reg() }
+fn mov_dr_r32(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.dr_from_reg() }
 
 fn wrmsr(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.msr_from_reg() }
 fn rdmsr(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.msr_to_reg() }
 
+fn rdtsc(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.tsc_to_reg() }
+pub fn rdtscp(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.tscp_to_reg() }
+fn rdpid(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.pid_to_reg() }
+
+fn sysenter(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.sysenter() }
+fn sysexit(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.sysexit() }
+
+fn push_fs(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.push_sgreg(SgReg::FS) }
+fn pop_fs(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.pop_sgreg(SgReg::FS) }
+fn cpuid(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.cpuid() }
+fn push_gs(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.push_sgreg(SgReg::GS) }
+fn pop_gs(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.pop_sgreg(SgReg::GS) }
+
+fn sse(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.sse() }
+fn bmi1(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.bmi1() }
+
+pub fn xgetbv(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.xgetbv() }
+pub fn xsetbv(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.xsetbv() }
+
 setcc_dst!(8, o, rm8);
 setcc_dst!(8, b, rm8);
 setcc_dst!(8, z, rm8);
@@ -230,12 +420,10 @@ pub fn code_82(exec: &mut exec::Exec) -> Result<(), EmuException> {
 
 fn code_c0(exec: &mut exec::Exec) -> Result<(), EmuException> {
     match exec.idata.modrm.reg as u8 {
-        /*
         0 => rol_rm8_imm8(exec)?,
         1 => ror_rm8_imm8(exec)?,
         2 => rcl_rm8_imm8(exec)?,
         3 => rcr_rm8_imm8(exec)?,
-        */
         4 => shl_rm8_imm8(exec)?,
         5 => shr_rm8_imm8(exec)?,
         6 => sal_rm8_imm8(exec)?,
@@ -245,25 +433,45 @@ fn code_c0(exec: &mut exec::Exec) -> Result<(), EmuException> {
     Ok(())
 }
 
-/*
 rol_dst_src!(8, rm8, imm8);
 ror_dst_src!(8, rm8, imm8);
 rcl_dst_src!(8, rm8, imm8);
 rcr_dst_src!(8, rm8, imm8);
-*/
 shl_dst_src!(8, rm8, imm8);
 shr_dst_src!(8, rm8, imm8);
 sal_dst_src!(8, rm8, imm8);
 sar_dst_src!(8, rm8, imm8);
 
+fn code_d0(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    match exec.idata.modrm.reg as u8 {
+        0 => rol_rm8_one(exec)?,
+        1 => ror_rm8_one(exec)?,
+        2 => rcl_rm8_one(exec)?,
+        3 => rcr_rm8_one(exec)?,
+        4 => shl_rm8_one(exec)?,
+        5 => shr_rm8_one(exec)?,
+        6 => sal_rm8_one(exec)?,
+        7 => sar_rm8_one(exec)?,
+        _ => { return Err(EmuException::UnexpectedError); },
+    }
+    Ok(())
+}
+
+rol_dst_src!(8, rm8, one);
+ror_dst_src!(8, rm8, one);
+rcl_dst_src!(8, rm8, one);
+rcr_dst_src!(8, rm8, one);
+shl_dst_src!(8, rm8, one);
+shr_dst_src!(8, rm8, one);
+sal_dst_src!(8, rm8, one);
+sar_dst_src!(8, rm8, one);
+
 fn code_d2(exec: &mut exec::Exec) -> Result<(), EmuException> {
     match exec.idata.modrm.reg as u8 {
-        /*
         0 => rol_rm8_cl(exec)?,
         1 => ror_rm8_cl(exec)?,
         2 => rcl_rm8_cl(exec)?,
         3 => rcr_rm8_cl(exec)?,
-        */
         4 => shl_rm8_cl(exec)?,
         5 => shr_rm8_cl(exec)?,
         6 => sal_rm8_cl(exec)?,
@@ -273,12 +481,10 @@ fn code_d2(exec: &mut exec::Exec) -> Result<(), EmuException> {
     Ok(())
 }
 
-/*
 rol_dst_src!(8, rm8, cl);
 ror_dst_src!(8, rm8, cl);
 rcl_dst_src!(8, rm8, cl);
 rcr_dst_src!(8, rm8, cl);
-*/
 shl_dst_src!(8, rm8, cl);
 shr_dst_src!(8, rm8, cl);
 sal_dst_src!(8, rm8, cl);
@@ -320,15 +526,22 @@ dec_dst!(rm8);
 
 fn code_0f00(exec: &mut exec::Exec) -> Result<(), EmuException> {
     match exec.idata.modrm.reg as u16 {
+        0 => sldt_rm16(exec)?,
+        1 => str_rm16(exec)?,
         2 => lldt_rm16(exec)?,
         3 => ltr_rm16(exec)?,
-        _ => { return Err(EmuException::NotImplementedOpcode); },
+        4 => verr_rm16(exec)?,
+        5 => verw_rm16(exec)?,
+        _ => { return Err(EmuException::CPUException(CPUException::UD)); },
     }
     Ok(())
 }
 
+fn sldt_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.ldtr_to_rm() }
+fn str_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.tr_to_rm() }
+
 fn lldt_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> {
-    if exec.ac.test_cpumode(access::CpuMode::Real) {
+    if exec.ac.test_cpumode(access::CpuMode::Real) || exec.ac.test_cpumode(access::CpuMode::V8086) {
         return Err(EmuException::CPUException(CPUException::UD));
     }
 
@@ -337,10 +550,87 @@ fn lldt_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> {
 }
 
 fn ltr_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> {
-    if exec.ac.test_cpumode(access::CpuMode::Real) {
+    if exec.ac.test_cpumode(access::CpuMode::Real) || exec.ac.test_cpumode(access::CpuMode::V8086) {
         return Err(EmuException::CPUException(CPUException::UD));
     }
 
     let sel = exec.get_rm16()?;
     exec.ac.set_tr(sel)
 }
+
+fn verr_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.verify_segment(false) }
+fn verw_rm16(exec: &mut exec::Exec) -> Result<(), EmuException> { exec.verify_segment(true) }
+
+fn code_0fae(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    match (exec.idata.modrm.reg, exec.idata.modrm.mod_ == 3) {
+        (0, false) => exec.fxsave()?,
+        (1, false) => exec.fxrstor()?,
+        (2, false) => exec.ldmxcsr()?,
+        (3, false) => exec.stmxcsr()?,
+        (4, false) => exec.xsave()?,
+        (5, false) => exec.xrstor()?,
+        (6, false) => exec.xsave()?,
+        (7, false) => { exec.get_m()?; },
+        (5, true) | (6, true) | (7, true) => {},
+        _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+    }
+    Ok(())
+}
+
+cmpxchg_dst_src!(8, al, rm8, r8);
+xadd_dst_src!(8, rm8, r8);
+
+fn code_0fc7(exec: &mut exec::Exec) -> Result<(), EmuException> {
+    match exec.idata.modrm.reg as u16 {
+        1 if exec.idata.modrm.mod_ != 3 => exec.cmpxchg8b()?,
+        7 if exec.idata.modrm.mod_ == 3 => rdpid(exec)?,
+        _ => { return Err(EmuException::NotImplementedOpcode); },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[test]
+fn rotate_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::access;
+    use crate::emulator::access::register::*;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.mod_ = 3;
+
+    let mut exe = exec::Exec::new(&mut ac, &parse);
+    exe.ac.set_gpreg(GpReg8::AL, 0x81).unwrap();
+    rol_rm8_one(&mut exe).unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg8::AL).unwrap(), 0x03);
+    assert!(exe.ac.core.rflags.is_carry());
+    assert!(exe.ac.core.rflags.is_overflow());
+
+    rcr_rm8_one(&mut exe).unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg8::AL).unwrap(), 0x81);
+    assert!(exe.ac.core.rflags.is_carry());
+    assert!(exe.ac.core.rflags.is_overflow());
+
+    parse.instr.imm = Some(9);
+    let mut exe = exec::Exec::new(&mut ac, &parse);
+    rcl_rm8_imm8(&mut exe).unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg8::AL).unwrap(), 0x81);
+    assert!(exe.ac.core.rflags.is_carry());
+
+    parse.instr.imm = Some(0x23);
+    let mut exe = exec::Exec::new(&mut ac, &parse);
+    ror_rm8_imm8(&mut exe).unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg8::AL).unwrap(), 0x30);
+    assert!(!exe.ac.core.rflags.is_carry());
+
+    parse.instr.imm = Some(10);
+    let mut exe = exec::Exec::new(&mut ac, &parse);
+    shl_rm8_imm8(&mut exe).unwrap();
+    assert_eq!(exe.ac.get_gpreg(GpReg8::AL).unwrap(), 0);
+    assert!(exe.ac.core.rflags.is_zero());
+}
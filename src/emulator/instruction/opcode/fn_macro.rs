@@ -105,6 +105,39 @@ macro_rules! cmp_dst_src {
     } };
 }
 
+macro_rules! cmpxchg_dst_src {
+    ( $size:expr, $acc:ident, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<cmpxchg_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let acc = exec.[<get_ $acc>]()? as u!($size);
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src = exec.[<get_ $src>]()? as u!($size);
+
+            debug!("cmpxchg: {:02x}, {:02x}, {:02x}", acc, dst, src);
+            exec.update_rflags_sub(acc, dst)?;
+            if acc == dst {
+                exec.[<set_ $dst>](src)
+            } else {
+                exec.[<set_ $dst>](dst)?;
+                exec.[<set_ $acc>](dst)
+            }
+        }
+    } };
+}
+
+macro_rules! xadd_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<xadd_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src = exec.[<get_ $src>]()? as u!($size);
+
+            debug!("xadd: {:02x}, {:02x}", dst, src);
+            exec.update_rflags_add(dst, src)?;
+            exec.[<set_ $src>](dst)?;
+            exec.[<set_ $dst>](dst.wrapping_add(src))
+        }
+    } };
+}
+
 macro_rules! inc_dst {
     ( $dst:ident ) => { paste::item! {
         fn [<inc_ $dst>](exec: &mut exec::Exec) -> Result<(), EmuException> {
@@ -233,10 +266,35 @@ macro_rules! callf_abs {
     } };
 }
 
+macro_rules! enter {
+    ( $size:expr ) => { paste::item! {
+        fn enter(exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let imm = exec.get_imm32()?;
+            let (size, level) = (imm as u16, (imm >> 16) as u8 & 0x1f);
+            debug!("enter: {:04x}, {:02x}", size, level);
+
+            let mask = exec.ac.stack_mask();
+            let rbp = exec.ac.get_gpreg(GpReg64::RBP)?;
+            exec.ac.[<push_u $size>](rbp as u!($size))?;
+            let frame = exec.ac.get_gpreg(GpReg64::RSP)? & mask;
+            if level > 0 {
+                for i in 1..level as u64 {
+                    let v = exec.ac.[<get_data $size>]((SgReg::SS, rbp.wrapping_sub(i * $size / 8) & mask))?;
+                    exec.ac.[<push_u $size>](v)?;
+                }
+                exec.ac.[<push_u $size>](frame as u!($size))?;
+            }
+            exec.ac.set_gpreg(GpReg64::RBP, (rbp & !mask) | frame)?;
+            let rsp = exec.ac.get_gpreg(GpReg64::RSP)?;
+            exec.ac.set_gpreg(GpReg64::RSP, (rsp & !mask) | (rsp.wrapping_sub(size as u64) & mask))
+        }
+    } };
+}
+
 macro_rules! pushf {
     ( $size:expr ) => { paste::item! {
         fn pushf(exec: &mut exec::Exec) -> Result<(), EmuException> {
-            let flag = exec.ac.get_rflags()? as u!($size);
+            let flag = exec.get_pushf($size == 16)? as u!($size);
             debug!("pushf: {:08x}", flag);
             exec.ac.[<push_u $size>](flag)
         }
@@ -248,7 +306,27 @@ macro_rules! popf {
         fn popf(exec: &mut exec::Exec) -> Result<(), EmuException> {
             let flag = exec.ac.[<pop_u $size>]()?;
             debug!("popf: {:08x}", flag);
-            exec.ac.set_rflags(flag as u64)
+            exec.set_popf(flag as u64, $size == 16)
+        }
+    } };
+}
+
+macro_rules! ins_dst_src {
+    ( $opsize:expr, $adsize:expr ) => { paste::item! {
+        fn [<ins_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
+            exec.[<in_str $adsize>]()?;
+            exec.[<repeat_ $opsize>]()
+        }
+    } };
+}
+
+macro_rules! outs_src_dst {
+    ( $opsize:expr, $adsize:expr ) => { paste::item! {
+        fn [<outs_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
+            exec.[<out_str $adsize>]()?;
+            exec.[<repeat_ $opsize>]()
         }
     } };
 }
@@ -256,6 +334,7 @@ macro_rules! popf {
 macro_rules! movs_dst_src {
     ( $opsize:expr, $adsize:expr ) => { paste::item! {
         fn [<movs_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
             exec.[<move_str $adsize>]()?;
             exec.[<repeat_ $opsize>]()
         }
@@ -265,6 +344,7 @@ macro_rules! movs_dst_src {
 macro_rules! cmps_src_dst {
     ( $opsize:expr, $adsize:expr ) => { paste::item! {
         fn [<cmps_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
             exec.[<cmp_str $adsize>]()?;
             exec.[<repeat_ $opsize>]()
         }
@@ -274,6 +354,7 @@ macro_rules! cmps_src_dst {
 macro_rules! stos_dst_src {
     ( $opsize:expr, $adsize:expr ) => { paste::item! {
         fn [<stos_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
             exec.[<store_str $adsize>]()?;
             exec.[<repeat_ $opsize>]()
         }
@@ -283,6 +364,7 @@ macro_rules! stos_dst_src {
 macro_rules! lods_dst_src {
     ( $opsize:expr, $adsize:expr ) => { paste::item! {
         fn [<lods_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
             exec.[<load_str $adsize>]()?;
             exec.[<repeat_ $opsize>]()
         }
@@ -292,6 +374,7 @@ macro_rules! lods_dst_src {
 macro_rules! scas_src_dst {
     ( $opsize:expr, $adsize:expr ) => { paste::item! {
         fn [<scas_m $adsize>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.[<rep_empty_ $opsize>]()? { return Ok(()); }
             exec.[<scan_str $adsize>]()?;
             exec.[<repeat_ $opsize>]()
         }
@@ -299,19 +382,32 @@ macro_rules! scas_src_dst {
 }
 
 macro_rules! ret {
-    ( $size:expr ) => { paste::item! {
+    () => {
         fn ret(exec: &mut exec::Exec) -> Result<(), EmuException> {
-            let ret = exec.ac.[<pop_u $size>]()? as u64;
+            let ret = exec.pop_ip()?;
             debug!("ret: {:04x}", ret);
             exec.ac.set_ip(ret)
         }
-    } };
+
+        fn ret_imm16(exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let imm = exec.get_imm16()?;
+            let ret = exec.pop_ip()?;
+            debug!("ret: {:04x}, {:04x}", ret, imm);
+            exec.ac.release_stack(imm)?;
+            exec.ac.set_ip(ret)
+        }
+    };
 }
 
 macro_rules! retf {
     ( $size:expr ) => { paste::item! {
         fn retf(exec: &mut exec::Exec) -> Result<(), EmuException> {
-            exec.[<ret_far_u $size>]()
+            exec.[<ret_far_u $size>](0)
+        }
+
+        fn retf_imm16(exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let imm = exec.get_imm16()?;
+            exec.[<ret_far_u $size>](imm)
         }
     } };
 }
@@ -349,15 +445,36 @@ macro_rules! out_port_reg {
 macro_rules! call_rel {
     ( $size:expr, $rel:ident ) => { paste::item! {
         fn [<call_ $rel>](exec: &mut exec::Exec) -> Result<(), EmuException> {
-            let offs = exec.[<get_ $rel>]()? as i64;
+            let offs = exec.[<get_ $rel>]()? as i!($size) as i64;
             let rip = exec.ac.get_ip()?;
             debug!("call: 0x{:04x}", rip as i64 + offs);
-            exec.ac.[<push_u $size>](rip as u!($size))?;
+            exec.push_ip()?;
             exec.ac.update_ip(offs)
         }
     } };
 }
 
+macro_rules! call_abs {
+    ( $abs:ident ) => { paste::item! {
+        fn [<call_ $abs>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let abs = exec.[<get_ $abs>]()? as u64;
+            debug!("call: 0x{:04x}", abs);
+            exec.push_ip()?;
+            exec.ac.set_ip(abs)
+        }
+    } };
+}
+
+macro_rules! callf_m {
+    ( $size:expr, $m:ident ) => { paste::item! {
+        fn [<callf_ $m>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let (sel, abs) = exec.[<get_ $m>]()?;
+            debug!("callf: {:04x}:{:04x}", sel, abs);
+            exec.[<call_far_u $size>](sel, abs)
+        }
+    } };
+}
+
 macro_rules! jmp_rel {
     ( $size:expr, $rel:ident ) => { paste::item! {
         fn [<jmp_ $rel>](exec: &mut exec::Exec) -> Result<(), EmuException> {
@@ -379,6 +496,81 @@ macro_rules! jmpf_abs {
     } };
 }
 
+macro_rules! jmp_abs {
+    ( $abs:ident ) => { paste::item! {
+        fn [<jmp_ $abs>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let abs = exec.[<get_ $abs>]()? as u64;
+            debug!("jmp: 0x{:04x}", abs);
+            exec.ac.set_ip(abs)
+        }
+    } };
+}
+
+macro_rules! jmpf_m {
+    ( $size:expr, $m:ident ) => { paste::item! {
+        fn [<jmpf_ $m>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let (sel, abs) = exec.[<get_ $m>]()?;
+            debug!("jmpf: {:04x}:{:04x}", sel, abs);
+            exec.[<jmp_far_u $size>](sel, abs)
+        }
+    } };
+}
+
+macro_rules! lseg_dst_src {
+    ( $sreg:ident, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<l $sreg:lower _ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let (sel, abs) = exec.[<get_ $src>]()?;
+            debug!("l{}: {:04x}:{:04x}", stringify!($sreg), sel, abs);
+            exec.mov_to_sreg(SgReg::$sreg, sel)?;
+            exec.[<set_ $dst>](abs)
+        }
+    } };
+}
+
+macro_rules! lar_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<lar_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let sel = exec.[<get_ $src>]()?;
+            debug!("lar: {:04x}", sel);
+            if let Some(v) = exec.load_access_rights(sel)? {
+                exec.[<set_ $dst>](v as u!($size))?;
+            }
+            Ok(())
+        }
+    } };
+}
+
+macro_rules! lsl_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<lsl_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let sel = exec.[<get_ $src>]()?;
+            debug!("lsl: {:04x}", sel);
+            if let Some(v) = exec.load_segment_limit(sel)? {
+                exec.[<set_ $dst>](v as u!($size))?;
+            }
+            Ok(())
+        }
+    } };
+}
+
+macro_rules! cmovcc_dst_src {
+    ( $size:expr, $cc:ident, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<cmov $cc _ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src = exec.[<get_ $src>]()? as u!($size);
+            let flag: bool = exec.[<check_rflags_ $cc>]()?;
+            exec.[<set_ $dst>](if flag { src } else { dst })
+        }
+
+        fn [<cmovn $cc _ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src = exec.[<get_ $src>]()? as u!($size);
+            let flag: bool = exec.[<check_rflags_ $cc>]()?;
+            exec.[<set_ $dst>](if flag { dst } else { src })
+        }
+    } };
+}
+
 macro_rules! setcc_dst {
     ( $size:expr, $cc:ident, $dst:ident ) => { paste::item! {
         fn [<set $cc _ $dst>](exec: &mut exec::Exec) -> Result<(), EmuException> {
@@ -425,14 +617,78 @@ macro_rules! movsx_dst_src {
     } };
 }
 
+macro_rules! rol_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<rol_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src: u32 = exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
+            if src == 0 { return Ok(()); }
+
+            debug!("rol: {:02x}, {:02x}", dst, src);
+            let result = dst.rotate_left(src);
+            exec.update_rflags_rot(dst, result, result & 1 != 0, src)?;
+            exec.[<set_ $dst>](result)
+        }
+    } };
+}
+
+macro_rules! ror_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<ror_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src: u32 = exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
+            if src == 0 { return Ok(()); }
+
+            debug!("ror: {:02x}, {:02x}", dst, src);
+            let result = dst.rotate_right(src);
+            exec.update_rflags_rot(dst, result, result >> ($size - 1) != 0, src)?;
+            exec.[<set_ $dst>](result)
+        }
+    } };
+}
+
+macro_rules! rcl_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<rcl_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src: u32 = (exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f }) % ($size + 1);
+            if src == 0 { return Ok(()); }
+
+            debug!("rcl: {:02x}, {:02x}", dst, src);
+            let v = (exec.check_rflags_b()? as u128) << $size | dst as u128;
+            let v = (v << src | v >> ($size + 1 - src)) & ((1u128 << ($size + 1)) - 1);
+            let result = v as u!($size);
+            exec.update_rflags_rot(dst, result, v >> $size != 0, src)?;
+            exec.[<set_ $dst>](result)
+        }
+    } };
+}
+
+macro_rules! rcr_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<rcr_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src: u32 = (exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f }) % ($size + 1);
+            if src == 0 { return Ok(()); }
+
+            debug!("rcr: {:02x}, {:02x}", dst, src);
+            let v = (exec.check_rflags_b()? as u128) << $size | dst as u128;
+            let v = (v >> src | v << ($size + 1 - src)) & ((1u128 << ($size + 1)) - 1);
+            let result = v as u!($size);
+            exec.update_rflags_rot(dst, result, v >> $size != 0, src)?;
+            exec.[<set_ $dst>](result)
+        }
+    } };
+}
+
 macro_rules! shl_dst_src {
     ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
         fn [<shl_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
             let dst = exec.[<get_ $dst>]()? as u!($size);
-            let src: u32 = exec.[<get_ $src>]()? as u32;
+            let src: u32 = exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
             debug!("shl: {:02x}, {:02x}", dst, src);
             exec.update_rflags_shl(dst, src)?;
-            exec.[<set_ $dst>](dst.wrapping_shl(src))
+            exec.[<set_ $dst>](dst.checked_shl(src).unwrap_or(0))
         }
     } };
 }
@@ -441,10 +697,10 @@ macro_rules! shr_dst_src {
     ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
         fn [<shr_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
             let dst = exec.[<get_ $dst>]()? as u!($size);
-            let src: u32 = exec.[<get_ $src>]()? as u32;
+            let src: u32 = exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
             debug!("shr: {:02x}, {:02x}", dst, src);
             exec.update_rflags_shr(dst, src)?;
-            exec.[<set_ $dst>](dst.wrapping_shr(src))
+            exec.[<set_ $dst>](dst.checked_shr(src).unwrap_or(0))
         }
     } };
 }
@@ -453,10 +709,10 @@ macro_rules! sal_dst_src {
     ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
         fn [<sal_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
             let dst = exec.[<get_ $dst>]()? as i!($size);
-            let src: u32 = exec.[<get_ $src>]()? as u32;
+            let src: u32 = exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
             debug!("sal: {:02x}, {:02x}", dst, src);
             exec.update_rflags_shl(dst as u!($size), src)?;
-            exec.[<set_ $dst>](dst.wrapping_shl(src) as u!($size))
+            exec.[<set_ $dst>](dst.checked_shl(src).unwrap_or(0) as u!($size))
         }
     } };
 }
@@ -465,10 +721,10 @@ macro_rules! sar_dst_src {
     ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
         fn [<sar_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
             let dst = exec.[<get_ $dst>]()? as i!($size);
-            let src: u32 = exec.[<get_ $src>]()? as u32;
+            let src: u32 = exec.[<get_ $src>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
             debug!("sar: {:02x}, {:02x}", dst, src);
             exec.update_rflags_sar(dst, src)?;
-            exec.[<set_ $dst>](dst.wrapping_shr(src) as u!($size))
+            exec.[<set_ $dst>](dst.checked_shr(src).unwrap_or(dst >> ($size - 1)) as u!($size))
         }
     } };
 }
@@ -559,4 +815,121 @@ macro_rules! idiv_quot_rem_src {
             Ok(())
         }
     } };
-}
\ No newline at end of file
+}
+macro_rules! bt_dst_src {
+    ( $size:expr, $op:ident, $dst:ident, imm8 ) => { paste::item! {
+        fn [<$op _ $dst _imm8>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let src = exec.get_imm8()? as i64 & ($size - 1);
+            debug!("{}: {:02x}", stringify!($op), src);
+            exec.[<$op _u $size>](src)
+        }
+    } };
+    ( $size:expr, $op:ident, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<$op _ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let src = exec.[<get_ $src>]()? as i!($size) as i64;
+            debug!("{}: {:02x}", stringify!($op), src);
+            exec.[<$op _u $size>](src)
+        }
+    } };
+}
+
+macro_rules! bsf_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<bsf_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.is_repz() { return Self::[<tzcnt_ $dst _ $src>](exec); }
+
+            let src = exec.[<get_ $src>]()? as u!($size);
+            debug!("bsf: {:02x}", src);
+            exec.update_rflags_bsf(src)?;
+            if src == 0 { return Ok(()); }
+            exec.[<set_ $dst>](src.trailing_zeros() as u!($size))
+        }
+
+        fn [<tzcnt_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let src = exec.[<get_ $src>]()? as u!($size);
+            let cnt = src.trailing_zeros();
+            debug!("tzcnt: {:02x}", src);
+            exec.update_rflags_cnt(src, cnt)?;
+            exec.[<set_ $dst>](cnt as u!($size))
+        }
+    } };
+}
+
+macro_rules! bsr_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<bsr_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if exec.is_repz() { return Self::[<lzcnt_ $dst _ $src>](exec); }
+
+            let src = exec.[<get_ $src>]()? as u!($size);
+            debug!("bsr: {:02x}", src);
+            exec.update_rflags_bsf(src)?;
+            if src == 0 { return Ok(()); }
+            exec.[<set_ $dst>]($size - 1 - src.leading_zeros() as u!($size))
+        }
+
+        fn [<lzcnt_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let src = exec.[<get_ $src>]()? as u!($size);
+            let cnt = src.leading_zeros();
+            debug!("lzcnt: {:02x}", src);
+            exec.update_rflags_cnt(src, cnt)?;
+            exec.[<set_ $dst>](cnt as u!($size))
+        }
+    } };
+}
+
+macro_rules! popcnt_dst_src {
+    ( $size:expr, $dst:ident, $src:ident ) => { paste::item! {
+        fn [<popcnt_ $dst _ $src>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            if !exec.is_repz() { return Err(EmuException::CPUException(CPUException::UD)); }
+
+            let src = exec.[<get_ $src>]()? as u!($size);
+            debug!("popcnt: {:02x}", src);
+            exec.update_rflags_popcnt(src)?;
+            exec.[<set_ $dst>](src.count_ones() as u!($size))
+        }
+    } };
+}
+
+macro_rules! shld_dst_src_cnt {
+    ( $size:expr, $dst:ident, $src:ident, $cnt:ident ) => { paste::item! {
+        fn [<shld_ $dst _ $src _ $cnt>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src = exec.[<get_ $src>]()? as u!($size);
+            let cnt = exec.[<get_ $cnt>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
+            if cnt == 0 { return Ok(()); }
+
+            debug!("shld: {:02x}, {:02x}, {:02x}", dst, src, cnt);
+            let v = (dst as u128) << $size | src as u128;
+            let result = ((v << cnt) >> $size) as u!($size);
+            exec.update_rflags_shd(dst, result, (v << (cnt - 1)) >> (2 * $size - 1) & 1 != 0, cnt)?;
+            exec.[<set_ $dst>](result)
+        }
+    } };
+}
+
+macro_rules! shrd_dst_src_cnt {
+    ( $size:expr, $dst:ident, $src:ident, $cnt:ident ) => { paste::item! {
+        fn [<shrd_ $dst _ $src _ $cnt>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let dst = exec.[<get_ $dst>]()? as u!($size);
+            let src = exec.[<get_ $src>]()? as u!($size);
+            let cnt = exec.[<get_ $cnt>]()? as u32 & if $size == 64 { 0x3f } else { 0x1f };
+            if cnt == 0 { return Ok(()); }
+
+            debug!("shrd: {:02x}, {:02x}, {:02x}", dst, src, cnt);
+            let v = (src as u128) << $size | dst as u128;
+            let result = (v >> cnt) as u!($size);
+            exec.update_rflags_shd(dst, result, (v >> (cnt - 1)) & 1 != 0, cnt)?;
+            exec.[<set_ $dst>](result)
+        }
+    } };
+}
+
+macro_rules! bswap_dst {
+    ( $size:expr, $dst:ident ) => { paste::item! {
+        fn [<bswap_ $dst>](exec: &mut exec::Exec) -> Result<(), EmuException> {
+            let v = exec.[<get_ $dst>]()? as u!($size);
+            debug!("bswap: {:02x}", v);
+            exec.[<set_ $dst>](v.swap_bytes())
+        }
+    } };
+}
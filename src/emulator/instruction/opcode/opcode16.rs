@@ -13,7 +13,7 @@ impl Opcode16 {
 impl super::OpcodeTrait for Opcode16 {
     fn init_opcode(&mut self) -> () {
         macro_rules! setop {
-            ($n:expr, $fnc:ident, $flg:expr) => { self.0[$n & 0x1ff] = OpcodeType{func:Self::$fnc, flag:$flg} }
+            ($n:expr, $fnc:ident, $flg:expr) => { self.0[opcode_index($n)] = OpcodeType{func:Self::$fnc, flag:$flg} }
         }
 
         // 0x00 : add_rm8_r8
@@ -83,6 +83,10 @@ impl super::OpcodeTrait for Opcode16 {
         setop!(0x69, imul_r16_rm16_imm16,   OpFlags::MODRM | OpFlags::IMM16);
         setop!(0x6a, push_imm8,             OpFlags::IMM8);
         setop!(0x6b, imul_r16_rm16_imm8,    OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x6c, ins_m8,                OpFlags::NONE);
+        setop!(0x6d, ins_m16,               OpFlags::NONE);
+        setop!(0x6e, outs_m8,               OpFlags::NONE);
+        setop!(0x6f, outs_m16,              OpFlags::NONE);
 
         // 0x70-0x7f : jcc
 
@@ -132,12 +136,18 @@ impl super::OpcodeTrait for Opcode16 {
             setop!(0xb8+i, mov_opr16_imm16,   OpFlags::IMM16);
         }
 
+        setop!(0xc2, ret_imm16,         OpFlags::IMM16);
         setop!(0xc3, ret,               OpFlags::NONE);
 
+        setop!(0xc4, les_r16_m16_16,    OpFlags::MODRM);
+        setop!(0xc5, lds_r16_m16_16,    OpFlags::MODRM);
+
         setop!(0xc7, mov_rm16_imm16,    OpFlags::MODRM | OpFlags::IMM16);
 
+        setop!(0xc8, enter,             OpFlags::IMM24);
         setop!(0xc9, leave,             OpFlags::NONE);
 
+        setop!(0xca, retf_imm16,        OpFlags::IMM16);
         setop!(0xcb, retf,              OpFlags::NONE);
         // 0xcc : int3
         // 0xcd : int_imm8
@@ -157,6 +167,23 @@ impl super::OpcodeTrait for Opcode16 {
         // 0xee : out_dx_al
         setop!(0xef, out_dx_ax,         OpFlags::NONE);
 
+        setop!(0x0f40, cmovo_r16_rm16,   OpFlags::MODRM);
+        setop!(0x0f41, cmovno_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f42, cmovb_r16_rm16,   OpFlags::MODRM);
+        setop!(0x0f43, cmovnb_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f44, cmovz_r16_rm16,   OpFlags::MODRM);
+        setop!(0x0f45, cmovnz_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f46, cmovbe_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f47, cmovnbe_r16_rm16, OpFlags::MODRM);
+        setop!(0x0f48, cmovs_r16_rm16,   OpFlags::MODRM);
+        setop!(0x0f49, cmovns_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f4a, cmovp_r16_rm16,   OpFlags::MODRM);
+        setop!(0x0f4b, cmovnp_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f4c, cmovl_r16_rm16,   OpFlags::MODRM);
+        setop!(0x0f4d, cmovnl_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f4e, cmovle_r16_rm16,  OpFlags::MODRM);
+        setop!(0x0f4f, cmovnle_r16_rm16, OpFlags::MODRM);
+
         setop!(0x0f80, jo_imm16,        OpFlags::IMM16);
         setop!(0x0f81, jno_imm16,       OpFlags::IMM16);
         setop!(0x0f82, jb_imm16,        OpFlags::IMM16);
@@ -174,28 +201,55 @@ impl super::OpcodeTrait for Opcode16 {
         setop!(0x0f8e, jle_imm16,       OpFlags::IMM16);
         setop!(0x0f8f, jnle_imm16,      OpFlags::IMM16);
 
+        setop!(0x0fa3, bt_rm16_r16,     OpFlags::MODRM);
+        setop!(0x0fa4, shld_rm16_r16_imm8, OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x0fa5, shld_rm16_r16_cl, OpFlags::MODRM);
+        setop!(0x0fab, bts_rm16_r16,    OpFlags::MODRM);
+        setop!(0x0fac, shrd_rm16_r16_imm8, OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x0fad, shrd_rm16_r16_cl, OpFlags::MODRM);
         setop!(0x0faf, imul_r16_rm16,   OpFlags::MODRM);
 
+        setop!(0x0fb1, cmpxchg_rm16_r16, OpFlags::MODRM);
+        setop!(0x0fb2, lss_r16_m16_16,   OpFlags::MODRM);
+        setop!(0x0fb3, btr_rm16_r16,    OpFlags::MODRM);
+        setop!(0x0fb4, lfs_r16_m16_16,   OpFlags::MODRM);
+        setop!(0x0fb5, lgs_r16_m16_16,   OpFlags::MODRM);
+
         setop!(0x0fb6, movzx_r16_rm8,   OpFlags::MODRM);
         setop!(0x0fb7, movzx_r16_rm16,  OpFlags::MODRM);
 
+        setop!(0x0fb8, popcnt_r16_rm16, OpFlags::MODRM);
+        setop!(0x0fbb, btc_rm16_r16,    OpFlags::MODRM);
+        setop!(0x0fbc, bsf_r16_rm16,    OpFlags::MODRM);
+        setop!(0x0fbd, bsr_r16_rm16,    OpFlags::MODRM);
         setop!(0x0fbe, movsx_r16_rm8,   OpFlags::MODRM);
         setop!(0x0fbf, movsx_r16_rm16,  OpFlags::MODRM);
 
+        setop!(0x0fc1, xadd_rm16_r16,    OpFlags::MODRM);
+        for i in 0..8 {
+            setop!(0x0fc8+i, bswap_opr16, OpFlags::NONE);
+        }
+
         // 0x80 : code_80
         setop!(0x81, code_81, OpFlags::MODRM | OpFlags::IMM16);
         setop!(0x82, code_82, OpFlags::MODRM | OpFlags::IMM8);
         setop!(0x83, code_83, OpFlags::MODRM | OpFlags::IMM8);
         // 0xc0 : code_c0
         setop!(0xc1, code_c1, OpFlags::MODRM | OpFlags::IMM8);
+        // 0xd0 : code_d0
+        setop!(0xd1, code_d1, OpFlags::MODRM);
         // 0xd2 : code_d2
         setop!(0xd3, code_d3, OpFlags::MODRM);
         // 0xf6 : code_f6
         setop!(0xf7, code_f7, OpFlags::MODRM | OpFlags::IMM16);
         // 0xfe : code_fe
         setop!(0xff, code_ff, OpFlags::MODRM);
+        setop!(0x8f, code_8f, OpFlags::MODRM);
         // 0x0f00 : code_0f00
         setop!(0x0f01, code_0f01, OpFlags::MODRM);
+        setop!(0x0f02, lar_r16_rm16,     OpFlags::MODRM);
+        setop!(0x0f03, lsl_r16_rm16,     OpFlags::MODRM);
+        setop!(0x0fba, code_0fba,       OpFlags::MODRM | OpFlags::IMM8);
     }
 
     fn exec(&self, exec: &mut exec::Exec) -> Result<(), EmuException> {
@@ -273,6 +327,10 @@ impl Opcode16 {
     imul_dst_src1_src2!(16, r16, rm16, imm16);
     push_src!(16, imm16);
     imul_dst_src1_src2!(16, r16, rm16, imm8);
+    ins_dst_src!(16, 8);
+    ins_dst_src!(16, 16);
+    outs_src_dst!(16, 8);
+    outs_src_dst!(16, 16);
 
     test_dst_src!(16, rm16, r16);
     xchg_dst_src!(16, r16, rm16);
@@ -315,10 +373,15 @@ impl Opcode16 {
 
     mov_dst_src!(16, opr16, imm16);
 
-    ret!(16);
+    ret!();
+
+    lseg_dst_src!(ES, r16, m16_16);
+    lseg_dst_src!(DS, r16, m16_16);
 
     mov_dst_src!(16, rm16, imm16);
 
+    enter!(16);
+
     fn leave(exec: &mut exec::Exec) -> Result<(), EmuException> {
         let bp = exec.ac.get_gpreg(GpReg16::BP)?;
         exec.ac.set_gpreg(GpReg16::SP, bp)?;
@@ -339,6 +402,15 @@ impl Opcode16 {
     in_reg_port!(16, ax, dx);
     out_port_reg!(16, dx, ax);
 
+    cmovcc_dst_src!(16, o, r16, rm16);
+    cmovcc_dst_src!(16, b, r16, rm16);
+    cmovcc_dst_src!(16, z, r16, rm16);
+    cmovcc_dst_src!(16, be, r16, rm16);
+    cmovcc_dst_src!(16, s, r16, rm16);
+    cmovcc_dst_src!(16, p, r16, rm16);
+    cmovcc_dst_src!(16, l, r16, rm16);
+    cmovcc_dst_src!(16, le, r16, rm16);
+
     jcc_rel!(16, o, imm16);
     jcc_rel!(16, b, imm16);
     jcc_rel!(16, z, imm16);
@@ -409,12 +481,10 @@ impl Opcode16 {
 
     fn code_c1(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
-            /*
             0 => Opcode16::rol_rm16_imm8(exec)?,
             1 => Opcode16::ror_rm16_imm8(exec)?,
             2 => Opcode16::rcl_rm16_imm8(exec)?,
             3 => Opcode16::rcr_rm16_imm8(exec)?,
-            */
             4 => Opcode16::shl_rm16_imm8(exec)?,
             5 => Opcode16::shr_rm16_imm8(exec)?,
             6 => Opcode16::sal_rm16_imm8(exec)?,
@@ -424,25 +494,45 @@ impl Opcode16 {
         Ok(())
     }
 
-    /*
     rol_dst_src!(16, rm16, imm8);
     ror_dst_src!(16, rm16, imm8);
     rcl_dst_src!(16, rm16, imm8);
     rcr_dst_src!(16, rm16, imm8);
-    */
     shl_dst_src!(16, rm16, imm8);
     shr_dst_src!(16, rm16, imm8);
     sal_dst_src!(16, rm16, imm8);
     sar_dst_src!(16, rm16, imm8);
 
+    fn code_d1(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            0 => Opcode16::rol_rm16_one(exec)?,
+            1 => Opcode16::ror_rm16_one(exec)?,
+            2 => Opcode16::rcl_rm16_one(exec)?,
+            3 => Opcode16::rcr_rm16_one(exec)?,
+            4 => Opcode16::shl_rm16_one(exec)?,
+            5 => Opcode16::shr_rm16_one(exec)?,
+            6 => Opcode16::sal_rm16_one(exec)?,
+            7 => Opcode16::sar_rm16_one(exec)?,
+            _ => { return Err(EmuException::UnexpectedError); },
+        }
+        Ok(())
+    }
+
+    rol_dst_src!(16, rm16, one);
+    ror_dst_src!(16, rm16, one);
+    rcl_dst_src!(16, rm16, one);
+    rcr_dst_src!(16, rm16, one);
+    shl_dst_src!(16, rm16, one);
+    shr_dst_src!(16, rm16, one);
+    sal_dst_src!(16, rm16, one);
+    sar_dst_src!(16, rm16, one);
+
     fn code_d3(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
-            /*
             0 => Opcode16::rol_rm16_cl(exec)?,
             1 => Opcode16::ror_rm16_cl(exec)?,
             2 => Opcode16::rcl_rm16_cl(exec)?,
             3 => Opcode16::rcr_rm16_cl(exec)?,
-            */
             4 => Opcode16::shl_rm16_cl(exec)?,
             5 => Opcode16::shr_rm16_cl(exec)?,
             6 => Opcode16::sal_rm16_cl(exec)?,
@@ -452,12 +542,10 @@ impl Opcode16 {
         Ok(())
     }
 
-    /*
     rol_dst_src!(16, rm16, cl);
     ror_dst_src!(16, rm16, cl);
     rcl_dst_src!(16, rm16, cl);
     rcr_dst_src!(16, rm16, cl);
-    */
     shl_dst_src!(16, rm16, cl);
     shr_dst_src!(16, rm16, cl);
     sal_dst_src!(16, rm16, cl);
@@ -489,23 +577,138 @@ impl Opcode16 {
         match exec.idata.modrm.reg as u8 {
             0 => Opcode16::inc_rm16(exec)?,
             1 => Opcode16::dec_rm16(exec)?,
-            _ => { return Err(EmuException::UnexpectedError); },
+            2 => Opcode16::call_rm16(exec)?,
+            3 => Opcode16::callf_m16_16(exec)?,
+            4 => Opcode16::jmp_rm16(exec)?,
+            5 => Opcode16::jmpf_m16_16(exec)?,
+            6 => Opcode16::push_rm16(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
         }
         Ok(())
     }
 
     inc_dst!(rm16);
     dec_dst!(rm16);
+    call_abs!(rm16);
+    callf_m!(16, m16_16);
+    jmp_abs!(rm16);
+    jmpf_m!(16, m16_16);
+    push_src!(16, rm16);
+
+    fn code_8f(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            0 => Opcode16::pop_rm16(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+        }
+        Ok(())
+    }
+
+    pop_dst!(16, rm16);
+
+    fn code_0fba(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            4 => Opcode16::bt_rm16_imm8(exec)?,
+            5 => Opcode16::bts_rm16_imm8(exec)?,
+            6 => Opcode16::btr_rm16_imm8(exec)?,
+            7 => Opcode16::btc_rm16_imm8(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+        }
+        Ok(())
+    }
+
+    bt_dst_src!(16, bt, rm16, imm8);
+    bt_dst_src!(16, bts, rm16, imm8);
+    bt_dst_src!(16, btr, rm16, imm8);
+    bt_dst_src!(16, btc, rm16, imm8);
+
+    bt_dst_src!(16, bt, rm16, r16);
+    bt_dst_src!(16, bts, rm16, r16);
+    bt_dst_src!(16, btr, rm16, r16);
+    bt_dst_src!(16, btc, rm16, r16);
+
+    shld_dst_src_cnt!(16, rm16, r16, imm8);
+    shld_dst_src_cnt!(16, rm16, r16, cl);
+    shrd_dst_src_cnt!(16, rm16, r16, imm8);
+    shrd_dst_src_cnt!(16, rm16, r16, cl);
+
+    lseg_dst_src!(SS, r16, m16_16);
+    lseg_dst_src!(FS, r16, m16_16);
+    lseg_dst_src!(GS, r16, m16_16);
+
+    cmpxchg_dst_src!(16, ax, rm16, r16);
+    xadd_dst_src!(16, rm16, r16);
+
+    bsf_dst_src!(16, r16, rm16);
+    bsr_dst_src!(16, r16, rm16);
+    popcnt_dst_src!(16, r16, rm16);
+    bswap_dst!(16, opr16);
+
+    lar_dst_src!(16, r16, rm16);
+    lsl_dst_src!(16, r16, rm16);
 
     fn code_0f01(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u16 {
+            0 if exec.idata.modrm.mod_ != 3 => Opcode16::sgdt_m16_32(exec)?,
+            1 if exec.idata.modrm.mod_ != 3 => Opcode16::sidt_m16_32(exec)?,
+            1 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    2 => super::common::clac(exec)?,
+                    3 => super::common::stac(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
+            2 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    0 => super::common::xgetbv(exec)?,
+                    1 => super::common::xsetbv(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
             2 => Opcode16::lgdt_m16_24(exec)?,
             3 => Opcode16::lidt_m16_24(exec)?,
+            4 => super::common::smsw_rm16(exec)?,
+            6 => super::common::lmsw_rm16(exec)?,
+            7 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    0 => super::common::swapgs(exec)?,
+                    1 => super::common::rdtscp(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
+            7 if exec.idata.modrm.mod_ != 3 => super::common::invlpg_m8(exec)?,
             _ => { return Err(EmuException::NotImplementedOpcode); },
         }
         Ok(())
     }
 
+    fn sgdt_m16_32(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        let (sg, adr) = exec.get_m()?;
+        exec.check_umip()?;
+
+        let (base, limit) = exec.ac.get_gdtr()?;
+        debug!("sgdt: base = {:08x}, limit = {:04x}", base, limit);
+        exec.ac.set_data16((sg,adr), limit as u16)?;
+        if exec.is_mode64() {
+            exec.ac.set_data64((sg,adr+2), base)
+        } else {
+            exec.ac.set_data32((sg,adr+2), base as u32)
+        }
+    }
+
+    fn sidt_m16_32(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        let (sg, adr) = exec.get_m()?;
+        exec.check_umip()?;
+
+        let (base, limit) = exec.ac.get_idtr()?;
+        debug!("sidt: base = {:08x}, limit = {:04x}", base, limit);
+        exec.ac.set_data16((sg,adr), limit as u16)?;
+        if exec.is_mode64() {
+            exec.ac.set_data64((sg,adr+2), base)
+        } else {
+            exec.ac.set_data32((sg,adr+2), base as u32)
+        }
+    }
+
     fn lgdt_m16_24(exec: &mut exec::Exec) -> Result<(), EmuException> {
         let (sg, adr) = exec.get_m()?;
 
@@ -532,3 +735,34 @@ impl Opcode16 {
         exec.ac.set_idtr(base as u64, limit)
     }
 }
+
+#[cfg(test)]
+#[test]
+fn rep_zero_count_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.prefix.repeat = Some(parse::Rep::REPZ);
+    parse.instr.len = 2;
+
+    ac.set_data8((SgReg::DS, 0x100), 0xab).unwrap();
+    ac.set_gpreg(GpReg16::SI, 0x100).unwrap();
+    ac.set_gpreg(GpReg16::DI, 0x200).unwrap();
+    ac.set_gpreg(GpReg16::AX, 0x1234).unwrap();
+    ac.set_ip(0x10).unwrap();
+
+    let mut exe = exec::Exec::new(&mut ac, &parse);
+    Opcode16::movs_m8(&mut exe).unwrap();
+    Opcode16::stos_m16(&mut exe).unwrap();
+    Opcode16::lods_m8(&mut exe).unwrap();
+    assert_eq!(ac.get_data16((SgReg::ES, 0x200)).unwrap(), 0);
+    assert_eq!(ac.get_gpreg(GpReg16::AX).unwrap(), 0x1234);
+    assert_eq!(ac.get_gpreg(GpReg16::CX).unwrap(), 0);
+    assert_eq!(ac.get_gpreg(GpReg16::DI).unwrap(), 0x200);
+    assert_eq!(ac.get_ip().unwrap(), 0x10);
+}
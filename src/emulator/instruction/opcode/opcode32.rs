@@ -13,7 +13,7 @@ impl Opcode32 {
 impl super::OpcodeTrait for Opcode32 {
     fn init_opcode(&mut self) -> () {
         macro_rules! setop {
-            ($n:expr, $fnc:ident, $flg:expr) => { self.0[$n & 0x1ff] = OpcodeType{func:Self::$fnc, flag:$flg} }
+            ($n:expr, $fnc:ident, $flg:expr) => { self.0[opcode_index($n)] = OpcodeType{func:Self::$fnc, flag:$flg} }
         }
 
         // 0x00 : add_rm8_r8
@@ -83,6 +83,10 @@ impl super::OpcodeTrait for Opcode32 {
         setop!(0x69, imul_r32_rm32_imm32,   OpFlags::MODRM | OpFlags::IMM32);
         setop!(0x6a, push_imm8,             OpFlags::IMM8);
         setop!(0x6b, imul_r32_rm32_imm8,    OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x6c, ins_m8,                OpFlags::NONE);
+        setop!(0x6d, ins_m32,               OpFlags::NONE);
+        setop!(0x6e, outs_m8,               OpFlags::NONE);
+        setop!(0x6f, outs_m32,              OpFlags::NONE);
 
         // 0x70-0x7f : jcc
 
@@ -132,12 +136,18 @@ impl super::OpcodeTrait for Opcode32 {
             setop!(0xb8+i, mov_opr32_imm32,   OpFlags::IMM32);
         }
 
+        setop!(0xc2, ret_imm16,         OpFlags::IMM16);
         setop!(0xc3, ret,               OpFlags::NONE);
 
+        setop!(0xc4, les_r32_m16_32,    OpFlags::MODRM);
+        setop!(0xc5, lds_r32_m16_32,    OpFlags::MODRM);
+
         setop!(0xc7, mov_rm32_imm32,    OpFlags::MODRM | OpFlags::IMM32);
 
+        setop!(0xc8, enter,             OpFlags::IMM24);
         setop!(0xc9, leave,             OpFlags::NONE);
 
+        setop!(0xca, retf_imm16,        OpFlags::IMM16);
         setop!(0xcb, retf,              OpFlags::NONE);
         // 0xcc : int3
         // 0xcd : int_imm8
@@ -157,6 +167,23 @@ impl super::OpcodeTrait for Opcode32 {
         // 0xee : out_dx_al
         setop!(0xef, out_dx_eax,        OpFlags::NONE);
 
+        setop!(0x0f40, cmovo_r32_rm32,   OpFlags::MODRM);
+        setop!(0x0f41, cmovno_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f42, cmovb_r32_rm32,   OpFlags::MODRM);
+        setop!(0x0f43, cmovnb_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f44, cmovz_r32_rm32,   OpFlags::MODRM);
+        setop!(0x0f45, cmovnz_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f46, cmovbe_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f47, cmovnbe_r32_rm32, OpFlags::MODRM);
+        setop!(0x0f48, cmovs_r32_rm32,   OpFlags::MODRM);
+        setop!(0x0f49, cmovns_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f4a, cmovp_r32_rm32,   OpFlags::MODRM);
+        setop!(0x0f4b, cmovnp_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f4c, cmovl_r32_rm32,   OpFlags::MODRM);
+        setop!(0x0f4d, cmovnl_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f4e, cmovle_r32_rm32,  OpFlags::MODRM);
+        setop!(0x0f4f, cmovnle_r32_rm32, OpFlags::MODRM);
+
         setop!(0x0f80, jo_imm32,        OpFlags::IMM32);
         setop!(0x0f81, jno_imm32,       OpFlags::IMM32);
         setop!(0x0f82, jb_imm32,        OpFlags::IMM32);
@@ -174,28 +201,55 @@ impl super::OpcodeTrait for Opcode32 {
         setop!(0x0f8e, jle_imm32,       OpFlags::IMM32);
         setop!(0x0f8f, jnle_imm32,      OpFlags::IMM32);
 
+        setop!(0x0fa3, bt_rm32_r32,     OpFlags::MODRM);
+        setop!(0x0fa4, shld_rm32_r32_imm8, OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x0fa5, shld_rm32_r32_cl, OpFlags::MODRM);
+        setop!(0x0fab, bts_rm32_r32,    OpFlags::MODRM);
+        setop!(0x0fac, shrd_rm32_r32_imm8, OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x0fad, shrd_rm32_r32_cl, OpFlags::MODRM);
         setop!(0x0faf, imul_r32_rm32,   OpFlags::MODRM);
 
+        setop!(0x0fb1, cmpxchg_rm32_r32, OpFlags::MODRM);
+        setop!(0x0fb2, lss_r32_m16_32,   OpFlags::MODRM);
+        setop!(0x0fb3, btr_rm32_r32,    OpFlags::MODRM);
+        setop!(0x0fb4, lfs_r32_m16_32,   OpFlags::MODRM);
+        setop!(0x0fb5, lgs_r32_m16_32,   OpFlags::MODRM);
+
         setop!(0x0fb6, movzx_r32_rm8,   OpFlags::MODRM);
         setop!(0x0fb7, movzx_r32_rm32,  OpFlags::MODRM);
 
+        setop!(0x0fb8, popcnt_r32_rm32, OpFlags::MODRM);
+        setop!(0x0fbb, btc_rm32_r32,    OpFlags::MODRM);
+        setop!(0x0fbc, bsf_r32_rm32,    OpFlags::MODRM);
+        setop!(0x0fbd, bsr_r32_rm32,    OpFlags::MODRM);
         setop!(0x0fbe, movsx_r32_rm8,   OpFlags::MODRM);
         setop!(0x0fbf, movsx_r32_rm32,  OpFlags::MODRM);
 
+        setop!(0x0fc1, xadd_rm32_r32,    OpFlags::MODRM);
+        for i in 0..8 {
+            setop!(0x0fc8+i, bswap_opr32, OpFlags::NONE);
+        }
+
         // 0x80 : code_80
         setop!(0x81, code_81, OpFlags::MODRM | OpFlags::IMM32);
         setop!(0x82, code_82, OpFlags::MODRM | OpFlags::IMM8);
         setop!(0x83, code_83, OpFlags::MODRM | OpFlags::IMM8);
         // 0xc0 : code_c0
         setop!(0xc1, code_c1, OpFlags::MODRM | OpFlags::IMM8);
+        // 0xd0 : code_d0
+        setop!(0xd1, code_d1, OpFlags::MODRM);
         // 0xd2 : code_d2
         setop!(0xd3, code_d3, OpFlags::MODRM);
         // 0xf6 : code_f6
         setop!(0xf7, code_f7, OpFlags::MODRM | OpFlags::IMM32);
         // 0xfe : code_fe
         setop!(0xff, code_ff, OpFlags::MODRM);
+        setop!(0x8f, code_8f, OpFlags::MODRM);
         // 0x0f00 : code_0f00
         setop!(0x0f01, code_0f01, OpFlags::MODRM);
+        setop!(0x0f02, lar_r32_rm16,     OpFlags::MODRM);
+        setop!(0x0f03, lsl_r32_rm16,     OpFlags::MODRM);
+        setop!(0x0fba, code_0fba,       OpFlags::MODRM | OpFlags::IMM8);
     }
 
     fn exec(&self, exec: &mut exec::Exec) -> Result<(), EmuException> {
@@ -273,6 +327,10 @@ impl Opcode32 {
     imul_dst_src1_src2!(32, r32, rm32, imm32);
     push_src!(32, imm32);
     imul_dst_src1_src2!(32, r32, rm32, imm8);
+    ins_dst_src!(32, 8);
+    ins_dst_src!(32, 32);
+    outs_src_dst!(32, 8);
+    outs_src_dst!(32, 32);
 
     test_dst_src!(32, rm32, r32);
     xchg_dst_src!(32, r32, rm32);
@@ -315,10 +373,15 @@ impl Opcode32 {
 
     mov_dst_src!(32, opr32, imm32);
 
-    ret!(32);
+    ret!();
+
+    lseg_dst_src!(ES, r32, m16_32);
+    lseg_dst_src!(DS, r32, m16_32);
 
     mov_dst_src!(32, rm32, imm32);
 
+    enter!(32);
+
     fn leave(exec: &mut exec::Exec) -> Result<(), EmuException> {
         let ebp = exec.ac.get_gpreg(GpReg32::EBP)?;
         exec.ac.set_gpreg(GpReg32::ESP, ebp)?;
@@ -339,6 +402,15 @@ impl Opcode32 {
     in_reg_port!(32, eax, dx);
     out_port_reg!(32, dx, eax);
 
+    cmovcc_dst_src!(32, o, r32, rm32);
+    cmovcc_dst_src!(32, b, r32, rm32);
+    cmovcc_dst_src!(32, z, r32, rm32);
+    cmovcc_dst_src!(32, be, r32, rm32);
+    cmovcc_dst_src!(32, s, r32, rm32);
+    cmovcc_dst_src!(32, p, r32, rm32);
+    cmovcc_dst_src!(32, l, r32, rm32);
+    cmovcc_dst_src!(32, le, r32, rm32);
+
     jcc_rel!(32, o, imm32);
     jcc_rel!(32, b, imm32);
     jcc_rel!(32, z, imm32);
@@ -409,12 +481,10 @@ impl Opcode32 {
 
     fn code_c1(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
-            /*
             0 => Opcode32::rol_rm32_imm8(exec)?,
             1 => Opcode32::ror_rm32_imm8(exec)?,
             2 => Opcode32::rcl_rm32_imm8(exec)?,
             3 => Opcode32::rcr_rm32_imm8(exec)?,
-            */
             4 => Opcode32::shl_rm32_imm8(exec)?,
             5 => Opcode32::shr_rm32_imm8(exec)?,
             6 => Opcode32::sal_rm32_imm8(exec)?,
@@ -424,25 +494,45 @@ impl Opcode32 {
         Ok(())
     }
 
-    /*
     rol_dst_src!(32, rm32, imm8);
     ror_dst_src!(32, rm32, imm8);
     rcl_dst_src!(32, rm32, imm8);
     rcr_dst_src!(32, rm32, imm8);
-    */
     shl_dst_src!(32, rm32, imm8);
     shr_dst_src!(32, rm32, imm8);
     sal_dst_src!(32, rm32, imm8);
     sar_dst_src!(32, rm32, imm8);
 
+    fn code_d1(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            0 => Opcode32::rol_rm32_one(exec)?,
+            1 => Opcode32::ror_rm32_one(exec)?,
+            2 => Opcode32::rcl_rm32_one(exec)?,
+            3 => Opcode32::rcr_rm32_one(exec)?,
+            4 => Opcode32::shl_rm32_one(exec)?,
+            5 => Opcode32::shr_rm32_one(exec)?,
+            6 => Opcode32::sal_rm32_one(exec)?,
+            7 => Opcode32::sar_rm32_one(exec)?,
+            _ => { return Err(EmuException::UnexpectedError); },
+        }
+        Ok(())
+    }
+
+    rol_dst_src!(32, rm32, one);
+    ror_dst_src!(32, rm32, one);
+    rcl_dst_src!(32, rm32, one);
+    rcr_dst_src!(32, rm32, one);
+    shl_dst_src!(32, rm32, one);
+    shr_dst_src!(32, rm32, one);
+    sal_dst_src!(32, rm32, one);
+    sar_dst_src!(32, rm32, one);
+
     fn code_d3(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
-            /*
             0 => Opcode32::rol_rm32_cl(exec)?,
             1 => Opcode32::ror_rm32_cl(exec)?,
             2 => Opcode32::rcl_rm32_cl(exec)?,
             3 => Opcode32::rcr_rm32_cl(exec)?,
-            */
             4 => Opcode32::shl_rm32_cl(exec)?,
             5 => Opcode32::shr_rm32_cl(exec)?,
             6 => Opcode32::sal_rm32_cl(exec)?,
@@ -452,12 +542,10 @@ impl Opcode32 {
         Ok(())
     }
 
-    /*
     rol_dst_src!(32, rm32, cl);
     ror_dst_src!(32, rm32, cl);
     rcl_dst_src!(32, rm32, cl);
     rcr_dst_src!(32, rm32, cl);
-    */
     shl_dst_src!(32, rm32, cl);
     shr_dst_src!(32, rm32, cl);
     sal_dst_src!(32, rm32, cl);
@@ -489,23 +577,146 @@ impl Opcode32 {
         match exec.idata.modrm.reg as u8 {
             0 => Opcode32::inc_rm32(exec)?,
             1 => Opcode32::dec_rm32(exec)?,
-            _ => { return Err(EmuException::UnexpectedError); },
+            2 if exec.is_mode64() => Opcode32::call_rm64(exec)?,
+            2 => Opcode32::call_rm32(exec)?,
+            3 => Opcode32::callf_m16_32(exec)?,
+            4 if exec.is_mode64() => Opcode32::jmp_rm64(exec)?,
+            4 => Opcode32::jmp_rm32(exec)?,
+            5 => Opcode32::jmpf_m16_32(exec)?,
+            6 if exec.is_mode64() => Opcode32::push_rm64(exec)?,
+            6 => Opcode32::push_rm32(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
         }
         Ok(())
     }
 
     inc_dst!(rm32);
     dec_dst!(rm32);
+    call_abs!(rm32);
+    call_abs!(rm64);
+    callf_m!(32, m16_32);
+    jmp_abs!(rm32);
+    jmp_abs!(rm64);
+    jmpf_m!(32, m16_32);
+    push_src!(32, rm32);
+    push_src!(64, rm64);
+
+    fn code_8f(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            0 if exec.is_mode64() => Opcode32::pop_rm64(exec)?,
+            0 => Opcode32::pop_rm32(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+        }
+        Ok(())
+    }
+
+    pop_dst!(32, rm32);
+    pop_dst!(64, rm64);
+
+    fn code_0fba(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            4 => Opcode32::bt_rm32_imm8(exec)?,
+            5 => Opcode32::bts_rm32_imm8(exec)?,
+            6 => Opcode32::btr_rm32_imm8(exec)?,
+            7 => Opcode32::btc_rm32_imm8(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+        }
+        Ok(())
+    }
+
+    bt_dst_src!(32, bt, rm32, imm8);
+    bt_dst_src!(32, bts, rm32, imm8);
+    bt_dst_src!(32, btr, rm32, imm8);
+    bt_dst_src!(32, btc, rm32, imm8);
+
+    bt_dst_src!(32, bt, rm32, r32);
+    bt_dst_src!(32, bts, rm32, r32);
+    bt_dst_src!(32, btr, rm32, r32);
+    bt_dst_src!(32, btc, rm32, r32);
+
+    shld_dst_src_cnt!(32, rm32, r32, imm8);
+    shld_dst_src_cnt!(32, rm32, r32, cl);
+    shrd_dst_src_cnt!(32, rm32, r32, imm8);
+    shrd_dst_src_cnt!(32, rm32, r32, cl);
+
+    lseg_dst_src!(SS, r32, m16_32);
+    lseg_dst_src!(FS, r32, m16_32);
+    lseg_dst_src!(GS, r32, m16_32);
+
+    cmpxchg_dst_src!(32, eax, rm32, r32);
+    xadd_dst_src!(32, rm32, r32);
+
+    bsf_dst_src!(32, r32, rm32);
+    bsr_dst_src!(32, r32, rm32);
+    popcnt_dst_src!(32, r32, rm32);
+    bswap_dst!(32, opr32);
+
+    lar_dst_src!(32, r32, rm16);
+    lsl_dst_src!(32, r32, rm16);
 
     fn code_0f01(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
+            0 if exec.idata.modrm.mod_ != 3 => Opcode32::sgdt_m16_32(exec)?,
+            1 if exec.idata.modrm.mod_ != 3 => Opcode32::sidt_m16_32(exec)?,
+            1 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    2 => super::common::clac(exec)?,
+                    3 => super::common::stac(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
+            2 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    0 => super::common::xgetbv(exec)?,
+                    1 => super::common::xsetbv(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
             2 => Opcode32::lgdt_m16_32(exec)?,
             3 => Opcode32::lidt_m16_32(exec)?,
+            4 => super::common::smsw_rm16(exec)?,
+            6 => super::common::lmsw_rm16(exec)?,
+            7 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    0 => super::common::swapgs(exec)?,
+                    1 => super::common::rdtscp(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
+            7 if exec.idata.modrm.mod_ != 3 => super::common::invlpg_m8(exec)?,
             _ => { return Err(EmuException::NotImplementedOpcode); },
         }
         Ok(())
     }
 
+    fn sgdt_m16_32(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        let (sg, adr) = exec.get_m()?;
+        exec.check_umip()?;
+
+        let (base, limit) = exec.ac.get_gdtr()?;
+        debug!("sgdt: base = {:08x}, limit = {:04x}", base, limit);
+        exec.ac.set_data16((sg,adr), limit as u16)?;
+        if exec.is_mode64() {
+            exec.ac.set_data64((sg,adr+2), base)
+        } else {
+            exec.ac.set_data32((sg,adr+2), base as u32)
+        }
+    }
+
+    fn sidt_m16_32(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        let (sg, adr) = exec.get_m()?;
+        exec.check_umip()?;
+
+        let (base, limit) = exec.ac.get_idtr()?;
+        debug!("sidt: base = {:08x}, limit = {:04x}", base, limit);
+        exec.ac.set_data16((sg,adr), limit as u16)?;
+        if exec.is_mode64() {
+            exec.ac.set_data64((sg,adr+2), base)
+        } else {
+            exec.ac.set_data32((sg,adr+2), base as u32)
+        }
+    }
+
     fn lgdt_m16_32(exec: &mut exec::Exec) -> Result<(), EmuException> {
         let (sg, adr) = exec.get_m()?;
 
@@ -532,3 +743,54 @@ impl Opcode32 {
         exec.ac.set_idtr(base as u64, limit)
     }
 }
+
+#[cfg(test)]
+#[test]
+fn enter_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.imm = Some(0x10);
+
+    ac.set_gpreg(GpReg32::ESP, 0xdead_0100).unwrap();
+    ac.set_gpreg(GpReg32::EBP, 0xbeef_0080).unwrap();
+    Opcode32::enter(&mut exec::Exec::new(&mut ac, &parse)).unwrap();
+    assert_eq!(ac.get_gpreg(GpReg32::EBP).unwrap(), 0xbeef_00fc);
+    assert_eq!(ac.get_gpreg(GpReg32::ESP).unwrap(), 0xdead_00ec);
+    assert_eq!(ac.get_data32((SgReg::SS, 0xfc)).unwrap(), 0xbeef_0080);
+}
+
+#[cfg(test)]
+#[test]
+fn sgdt_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::emulator::instruction::parse;
+
+    let hw = hardware::Hardware::new(0x1000);
+    let (dev, _) = device::Device::new();
+    let mut ac = access::Access::new(hw, dev);
+    let mut parse: parse::ParseInstr = Default::default();
+    parse.instr.modrm.rm = 6;
+    parse.instr.disp = 0x100;
+
+    ac.set_gdtr(0x1234_5678_9abc, 0x27).unwrap();
+    Opcode32::sgdt_m16_32(&mut exec::Exec::new(&mut ac, &parse)).unwrap();
+    assert_eq!(ac.get_data16((SgReg::DS, 0x100)).unwrap(), 0x27);
+    assert_eq!(ac.get_data64((SgReg::DS, 0x102)).unwrap(), 0x5678_9abc);
+
+    ac.mode = access::CpuMode::Long;
+    ac.oasz.ad = access::AcsSize::BIT64;
+    parse.instr.adsize = access::AcsSize::BIT64;
+    parse.instr.modrm.rm = 0;
+    parse.instr.disp = 0;
+    ac.set_gpreg(GpReg64::RAX, 0x200).unwrap();
+    Opcode32::sgdt_m16_32(&mut exec::Exec::new(&mut ac, &parse)).unwrap();
+    assert_eq!(ac.get_data16((SgReg::DS, 0x200)).unwrap(), 0x27);
+    assert_eq!(ac.get_data64((SgReg::DS, 0x202)).unwrap(), 0x1234_5678_9abc);
+}
@@ -12,7 +12,7 @@ impl Opcode64 {
 impl super::OpcodeTrait for Opcode64 {
     fn init_opcode(&mut self) -> () {
         macro_rules! setop {
-            ($n:expr, $fnc:ident, $flg:expr) => { self.0[$n & 0x1ff] = OpcodeType{func:Self::$fnc, flag:$flg} }
+            ($n:expr, $fnc:ident, $flg:expr) => { self.0[opcode_index($n)] = OpcodeType{func:Self::$fnc, flag:$flg} }
         }
 
         // 0x00 : add_rm8_r8
@@ -82,6 +82,10 @@ impl super::OpcodeTrait for Opcode64 {
         setop!(0x69, imul_r64_rm64_imm64,   OpFlags::MODRM | OpFlags::IMM64);
         setop!(0x6a, push_imm8,             OpFlags::IMM8);
         setop!(0x6b, imul_r64_rm64_imm8,    OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x6c, ins_m8,                OpFlags::NONE);
+        setop!(0x6d, ins_m32,               OpFlags::NONE);
+        setop!(0x6e, outs_m8,               OpFlags::NONE);
+        setop!(0x6f, outs_m32,              OpFlags::NONE);
 
         // 0x70-0x7f : jcc
 
@@ -131,12 +135,15 @@ impl super::OpcodeTrait for Opcode64 {
             setop!(0xb8+i, mov_opr64_imm64,   OpFlags::IMM64);
         }
 
+        setop!(0xc2, ret_imm16,         OpFlags::IMM16);
         setop!(0xc3, ret,               OpFlags::NONE);
 
         setop!(0xc7, mov_rm64_imm64,    OpFlags::MODRM | OpFlags::IMM64);
 
+        setop!(0xc8, enter,             OpFlags::IMM24);
         setop!(0xc9, leave,             OpFlags::NONE);
 
+        setop!(0xca, retf_imm16,        OpFlags::IMM16);
         setop!(0xcb, retf,              OpFlags::NONE);
         // 0xcc : int3
         // 0xcd : int_imm8
@@ -156,6 +163,23 @@ impl super::OpcodeTrait for Opcode64 {
         // 0xee : out_dx_al
         setop!(0xef, out_dx_eax,        OpFlags::NONE);
 
+        setop!(0x0f40, cmovo_r64_rm64,   OpFlags::MODRM);
+        setop!(0x0f41, cmovno_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f42, cmovb_r64_rm64,   OpFlags::MODRM);
+        setop!(0x0f43, cmovnb_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f44, cmovz_r64_rm64,   OpFlags::MODRM);
+        setop!(0x0f45, cmovnz_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f46, cmovbe_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f47, cmovnbe_r64_rm64, OpFlags::MODRM);
+        setop!(0x0f48, cmovs_r64_rm64,   OpFlags::MODRM);
+        setop!(0x0f49, cmovns_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f4a, cmovp_r64_rm64,   OpFlags::MODRM);
+        setop!(0x0f4b, cmovnp_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f4c, cmovl_r64_rm64,   OpFlags::MODRM);
+        setop!(0x0f4d, cmovnl_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f4e, cmovle_r64_rm64,  OpFlags::MODRM);
+        setop!(0x0f4f, cmovnle_r64_rm64, OpFlags::MODRM);
+
         setop!(0x0f80, jo_imm64,        OpFlags::IMM64);
         setop!(0x0f81, jno_imm64,       OpFlags::IMM64);
         setop!(0x0f82, jb_imm64,        OpFlags::IMM64);
@@ -173,28 +197,55 @@ impl super::OpcodeTrait for Opcode64 {
         setop!(0x0f8e, jle_imm64,       OpFlags::IMM64);
         setop!(0x0f8f, jnle_imm64,      OpFlags::IMM64);
 
+        setop!(0x0fa3, bt_rm64_r64,     OpFlags::MODRM);
+        setop!(0x0fa4, shld_rm64_r64_imm8, OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x0fa5, shld_rm64_r64_cl, OpFlags::MODRM);
+        setop!(0x0fab, bts_rm64_r64,    OpFlags::MODRM);
+        setop!(0x0fac, shrd_rm64_r64_imm8, OpFlags::MODRM | OpFlags::IMM8);
+        setop!(0x0fad, shrd_rm64_r64_cl, OpFlags::MODRM);
         setop!(0x0faf, imul_r64_rm64,   OpFlags::MODRM);
 
+        setop!(0x0fb1, cmpxchg_rm64_r64, OpFlags::MODRM);
+        setop!(0x0fb2, lss_r64_m16_64,   OpFlags::MODRM);
+        setop!(0x0fb3, btr_rm64_r64,    OpFlags::MODRM);
+        setop!(0x0fb4, lfs_r64_m16_64,   OpFlags::MODRM);
+        setop!(0x0fb5, lgs_r64_m16_64,   OpFlags::MODRM);
+
         setop!(0x0fb6, movzx_r64_rm8,   OpFlags::MODRM);
         setop!(0x0fb7, movzx_r64_rm64,  OpFlags::MODRM);
 
+        setop!(0x0fb8, popcnt_r64_rm64, OpFlags::MODRM);
+        setop!(0x0fbb, btc_rm64_r64,    OpFlags::MODRM);
+        setop!(0x0fbc, bsf_r64_rm64,    OpFlags::MODRM);
+        setop!(0x0fbd, bsr_r64_rm64,    OpFlags::MODRM);
         setop!(0x0fbe, movsx_r64_rm8,   OpFlags::MODRM);
         setop!(0x0fbf, movsx_r64_rm64,  OpFlags::MODRM);
 
+        setop!(0x0fc1, xadd_rm64_r64,    OpFlags::MODRM);
+        for i in 0..8 {
+            setop!(0x0fc8+i, bswap_opr64, OpFlags::NONE);
+        }
+
         // 0x80 : code_80
         setop!(0x81, code_81, OpFlags::MODRM | OpFlags::IMM64);
         // 0x82 : invalid
         setop!(0x83, code_83, OpFlags::MODRM | OpFlags::IMM8);
         // 0xc0 : code_c0
         setop!(0xc1, code_c1, OpFlags::MODRM | OpFlags::IMM8);
+        // 0xd0 : code_d0
+        setop!(0xd1, code_d1, OpFlags::MODRM);
         // 0xd2 : code_d2
         setop!(0xd3, code_d3, OpFlags::MODRM);
         // 0xf6 : code_f6
         setop!(0xf7, code_f7, OpFlags::MODRM | OpFlags::IMM64);
         // 0xfe : code_fe
         setop!(0xff, code_ff, OpFlags::MODRM);
+        setop!(0x8f, code_8f, OpFlags::MODRM);
         // 0x0f00 : code_0f00
         setop!(0x0f01, code_0f01, OpFlags::MODRM);
+        setop!(0x0f02, lar_r64_rm16,     OpFlags::MODRM);
+        setop!(0x0f03, lsl_r64_rm16,     OpFlags::MODRM);
+        setop!(0x0fba, code_0fba,       OpFlags::MODRM | OpFlags::IMM8);
     }
 
     fn exec(&self, exec: &mut exec::Exec) -> Result<(), EmuException> {
@@ -245,6 +296,10 @@ impl Opcode64 {
     imul_dst_src1_src2!(64, r64, rm64, imm64);
     push_src!(64, imm64);
     imul_dst_src1_src2!(64, r64, rm64, imm8);
+    ins_dst_src!(64, 8);
+    ins_dst_src!(64, 32);
+    outs_src_dst!(64, 8);
+    outs_src_dst!(64, 32);
 
     test_dst_src!(64, rm64, r64);
     xchg_dst_src!(64, r64, rm64);
@@ -287,10 +342,12 @@ impl Opcode64 {
 
     mov_dst_src!(64, opr64, imm64);
 
-    ret!(64);
+    ret!();
 
     mov_dst_src!(64, rm64, imm64);
 
+    enter!(64);
+
     fn leave(exec: &mut exec::Exec) -> Result<(), EmuException> {
         let rbp = exec.ac.get_gpreg(GpReg64::RBP)?;
         exec.ac.set_gpreg(GpReg64::RSP, rbp)?;
@@ -311,6 +368,15 @@ impl Opcode64 {
     in_reg_port!(32, eax, dx);
     out_port_reg!(32, dx, eax);
 
+    cmovcc_dst_src!(64, o, r64, rm64);
+    cmovcc_dst_src!(64, b, r64, rm64);
+    cmovcc_dst_src!(64, z, r64, rm64);
+    cmovcc_dst_src!(64, be, r64, rm64);
+    cmovcc_dst_src!(64, s, r64, rm64);
+    cmovcc_dst_src!(64, p, r64, rm64);
+    cmovcc_dst_src!(64, l, r64, rm64);
+    cmovcc_dst_src!(64, le, r64, rm64);
+
     jcc_rel!(64, o, imm64);
     jcc_rel!(64, b, imm64);
     jcc_rel!(64, z, imm64);
@@ -381,12 +447,10 @@ impl Opcode64 {
 
     fn code_c1(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
-            /*
             0 => Opcode64::rol_rm64_imm8(exec)?,
             1 => Opcode64::ror_rm64_imm8(exec)?,
             2 => Opcode64::rcl_rm64_imm8(exec)?,
             3 => Opcode64::rcr_rm64_imm8(exec)?,
-            */
             4 => Opcode64::shl_rm64_imm8(exec)?,
             5 => Opcode64::shr_rm64_imm8(exec)?,
             6 => Opcode64::sal_rm64_imm8(exec)?,
@@ -396,25 +460,45 @@ impl Opcode64 {
         Ok(())
     }
 
-    /*
     rol_dst_src!(64, rm64, imm8);
     ror_dst_src!(64, rm64, imm8);
     rcl_dst_src!(64, rm64, imm8);
     rcr_dst_src!(64, rm64, imm8);
-    */
     shl_dst_src!(64, rm64, imm8);
     shr_dst_src!(64, rm64, imm8);
     sal_dst_src!(64, rm64, imm8);
     sar_dst_src!(64, rm64, imm8);
 
+    fn code_d1(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            0 => Opcode64::rol_rm64_one(exec)?,
+            1 => Opcode64::ror_rm64_one(exec)?,
+            2 => Opcode64::rcl_rm64_one(exec)?,
+            3 => Opcode64::rcr_rm64_one(exec)?,
+            4 => Opcode64::shl_rm64_one(exec)?,
+            5 => Opcode64::shr_rm64_one(exec)?,
+            6 => Opcode64::sal_rm64_one(exec)?,
+            7 => Opcode64::sar_rm64_one(exec)?,
+            _ => { return Err(EmuException::UnexpectedError); },
+        }
+        Ok(())
+    }
+
+    rol_dst_src!(64, rm64, one);
+    ror_dst_src!(64, rm64, one);
+    rcl_dst_src!(64, rm64, one);
+    rcr_dst_src!(64, rm64, one);
+    shl_dst_src!(64, rm64, one);
+    shr_dst_src!(64, rm64, one);
+    sal_dst_src!(64, rm64, one);
+    sar_dst_src!(64, rm64, one);
+
     fn code_d3(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
-            /*
             0 => Opcode64::rol_rm64_cl(exec)?,
             1 => Opcode64::ror_rm64_cl(exec)?,
             2 => Opcode64::rcl_rm64_cl(exec)?,
             3 => Opcode64::rcr_rm64_cl(exec)?,
-            */
             4 => Opcode64::shl_rm64_cl(exec)?,
             5 => Opcode64::shr_rm64_cl(exec)?,
             6 => Opcode64::sal_rm64_cl(exec)?,
@@ -424,12 +508,10 @@ impl Opcode64 {
         Ok(())
     }
 
-    /*
     rol_dst_src!(64, rm64, cl);
     ror_dst_src!(64, rm64, cl);
     rcl_dst_src!(64, rm64, cl);
     rcr_dst_src!(64, rm64, cl);
-    */
     shl_dst_src!(64, rm64, cl);
     shr_dst_src!(64, rm64, cl);
     sal_dst_src!(64, rm64, cl);
@@ -461,23 +543,130 @@ impl Opcode64 {
         match exec.idata.modrm.reg as u8 {
             0 => Opcode64::inc_rm64(exec)?,
             1 => Opcode64::dec_rm64(exec)?,
-            _ => { return Err(EmuException::UnexpectedError); },
+            2 => Opcode64::call_rm64(exec)?,
+            3 => Opcode64::callf_m16_64(exec)?,
+            4 => Opcode64::jmp_rm64(exec)?,
+            5 => Opcode64::jmpf_m16_64(exec)?,
+            6 => Opcode64::push_rm64(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
         }
         Ok(())
     }
 
     inc_dst!(rm64);
     dec_dst!(rm64);
+    call_abs!(rm64);
+    callf_m!(64, m16_64);
+    jmp_abs!(rm64);
+    jmpf_m!(64, m16_64);
+    push_src!(64, rm64);
+
+    fn code_8f(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            0 => Opcode64::pop_rm64(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+        }
+        Ok(())
+    }
+
+    pop_dst!(64, rm64);
+
+    fn code_0fba(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        match exec.idata.modrm.reg as u8 {
+            4 => Opcode64::bt_rm64_imm8(exec)?,
+            5 => Opcode64::bts_rm64_imm8(exec)?,
+            6 => Opcode64::btr_rm64_imm8(exec)?,
+            7 => Opcode64::btc_rm64_imm8(exec)?,
+            _ => { return Err(EmuException::CPUException(CPUException::UD)); },
+        }
+        Ok(())
+    }
+
+    bt_dst_src!(64, bt, rm64, imm8);
+    bt_dst_src!(64, bts, rm64, imm8);
+    bt_dst_src!(64, btr, rm64, imm8);
+    bt_dst_src!(64, btc, rm64, imm8);
+
+    bt_dst_src!(64, bt, rm64, r64);
+    bt_dst_src!(64, bts, rm64, r64);
+    bt_dst_src!(64, btr, rm64, r64);
+    bt_dst_src!(64, btc, rm64, r64);
+
+    shld_dst_src_cnt!(64, rm64, r64, imm8);
+    shld_dst_src_cnt!(64, rm64, r64, cl);
+    shrd_dst_src_cnt!(64, rm64, r64, imm8);
+    shrd_dst_src_cnt!(64, rm64, r64, cl);
+
+    lseg_dst_src!(SS, r64, m16_64);
+    lseg_dst_src!(FS, r64, m16_64);
+    lseg_dst_src!(GS, r64, m16_64);
+
+    cmpxchg_dst_src!(64, rax, rm64, r64);
+    xadd_dst_src!(64, rm64, r64);
+
+    bsf_dst_src!(64, r64, rm64);
+    bsr_dst_src!(64, r64, rm64);
+    popcnt_dst_src!(64, r64, rm64);
+    bswap_dst!(64, opr64);
+
+    lar_dst_src!(64, r64, rm16);
+    lsl_dst_src!(64, r64, rm16);
 
     fn code_0f01(exec: &mut exec::Exec) -> Result<(), EmuException> {
         match exec.idata.modrm.reg as u8 {
+            0 if exec.idata.modrm.mod_ != 3 => Opcode64::sgdt_m16_64(exec)?,
+            1 if exec.idata.modrm.mod_ != 3 => Opcode64::sidt_m16_64(exec)?,
+            1 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    2 => super::common::clac(exec)?,
+                    3 => super::common::stac(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
+            2 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    0 => super::common::xgetbv(exec)?,
+                    1 => super::common::xsetbv(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
             2 => Opcode64::lgdt_m16_64(exec)?,
             3 => Opcode64::lidt_m16_64(exec)?,
+            4 => super::common::smsw_rm16(exec)?,
+            6 => super::common::lmsw_rm16(exec)?,
+            7 if exec.idata.modrm.mod_ == 3 => {
+                match exec.idata.modrm.rm {
+                    0 => super::common::swapgs(exec)?,
+                    1 => super::common::rdtscp(exec)?,
+                    _ => { return Err(EmuException::NotImplementedOpcode); },
+                }
+            },
+            7 if exec.idata.modrm.mod_ != 3 => super::common::invlpg_m8(exec)?,
             _ => { return Err(EmuException::NotImplementedOpcode); },
         }
         Ok(())
     }
 
+    fn sgdt_m16_64(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        let (sg, adr) = exec.get_m()?;
+        exec.check_umip()?;
+
+        let (base, limit) = exec.ac.get_gdtr()?;
+        debug!("sgdt: base = {:016x}, limit = {:04x}", base, limit);
+        exec.ac.set_data16((sg,adr), limit as u16)?;
+        exec.ac.set_data64((sg,adr+2), base)
+    }
+
+    fn sidt_m16_64(exec: &mut exec::Exec) -> Result<(), EmuException> {
+        let (sg, adr) = exec.get_m()?;
+        exec.check_umip()?;
+
+        let (base, limit) = exec.ac.get_idtr()?;
+        debug!("sidt: base = {:016x}, limit = {:04x}", base, limit);
+        exec.ac.set_data16((sg,adr), limit as u16)?;
+        exec.ac.set_data64((sg,adr+2), base)
+    }
+
     fn lgdt_m16_64(exec: &mut exec::Exec) -> Result<(), EmuException> {
         let (sg, adr) = exec.get_m()?;
 
@@ -2,7 +2,7 @@ use packed_struct::prelude::*;
 use super::opcode;
 use crate::emulator::access;
 use crate::emulator::access::register::*;
-use crate::emulator::EmuException;
+use crate::emulator::{EmuException, CPUException};
 
 #[derive(Default)]
 pub(super) struct ParseInstr {
@@ -14,12 +14,26 @@ pub(super) struct ParseInstr {
 pub(in crate::emulator) struct PrefixData {
     pub(super) segment: Option<SgReg>,
     pub(super) repeat: Option<Rep>,
+    pub(super) lock: bool,
     pub(super) size: OverrideSize,
     pub(super) rex: Option<Rex>,
+    pub(super) mandatory: Option<Mandatory>,
+    pub(super) vex: Option<Vex>,
 }
 
 pub(in crate::emulator) enum Rep { REPZ, REPNZ }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(in crate::emulator) enum Mandatory { P66, PF3, PF2 }
+
+#[derive(Debug, Clone, Copy)]
+pub(in crate::emulator) struct Vex {
+    pub(super) map:  u8,
+    pub(super) vvvv: u8,
+    pub(super) l:    u8,
+    pub(super) w:    u8,
+}
+
 bitflags! {
     pub(in crate::emulator) struct OverrideSize: u8 {
         const NONE = 0b00000000;
@@ -74,9 +88,13 @@ impl ParseInstr {
     pub fn parse_prefix(&mut self, ac: &mut access::Access) -> Result<(), EmuException> {
         self.get_legacy_prefix(ac)?;
 
-        if let (access::CpuMode::Long, access::AcsSize::BIT64) = (&ac.mode, ac.oasz.ad) {
+        let mode64 = matches!((&ac.mode, ac.oasz.ad), (access::CpuMode::Long, access::AcsSize::BIT64));
+        if mode64 {
             self.get_rex_prefix(ac)?;
         }
+        if !matches!(ac.mode, access::CpuMode::Real | access::CpuMode::V8086) {
+            self.get_vex_prefix(ac, mode64)?;
+        }
         Ok(())
     }
 
@@ -88,6 +106,12 @@ impl ParseInstr {
     pub fn parse_oprand(&mut self, ac: &mut access::Access, flag: opcode::OpFlags, adsize: access::AcsSize) -> Result<(), EmuException> {
         self.instr.adsize = adsize;
 
+        if flag.contains(opcode::OpFlags::SIMD) {
+            self.get_mandatory_prefix();
+        } else if self.prefix.vex.is_some() {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+
         if flag.contains(opcode::OpFlags::MODRM) {
             self.get_modrm(ac)?;
             self.get_sib_disp(ac)?;
@@ -97,6 +121,7 @@ impl ParseInstr {
             let (imm, len) = match flag & opcode::OpFlags::SZBIT {
                 opcode::OpFlags::SZ8  => (ac.get_code8(self.instr.len)? as u64, 1),
                 opcode::OpFlags::SZ16 => (ac.get_code16(self.instr.len)? as u64, 2),
+                opcode::OpFlags::SZ24 => (ac.get_code16(self.instr.len)? as u64 + ((ac.get_code8(self.instr.len+2)? as u64) << 16), 3),
                 opcode::OpFlags::SZ32 => (ac.get_code32(self.instr.len)? as u64, 4),
                 opcode::OpFlags::SZ64 => (ac.get_code64(self.instr.len)? as u64, 8),
                 _ => (0, 0),
@@ -114,8 +139,32 @@ impl ParseInstr {
             self.get_moffs(ac)?;
         }
 
+        if self.prefix.lock && !self.is_lockable() {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
         Ok(())
     }
+
+    pub fn is_locked(&self) -> bool {
+        self.prefix.lock || (matches!(self.instr.opcode, 0x86 | 0x87) && self.instr.modrm.mod_ != 3)
+    }
+
+    fn is_lockable(&self) -> bool {
+        let (op, reg) = (self.instr.opcode, self.instr.modrm.reg);
+        if self.instr.modrm.mod_ == 3 { return false; }
+
+        match op {
+            0x00..=0x3f => op & 7 < 2 && op & 0x38 != 0x38,
+            0x80..=0x83 => reg != 7,
+            0x86 | 0x87 => true,
+            0xf6 | 0xf7 => reg == 2 || reg == 3,
+            0xfe | 0xff => reg < 2,
+            0x1ab | 0x1b3 | 0x1bb | 0x1b0 | 0x1b1 | 0x1c0 | 0x1c1 => true,
+            0x1ba => reg > 4,
+            0x1c7 => reg == 1,
+            _ => false,
+        }
+    }
 }
 
 impl ParseInstr {
@@ -131,6 +180,7 @@ impl ParseInstr {
                 0x65 => prefix.segment = Some(SgReg::GS),
                 0x66 => prefix.size |= OverrideSize::OP,
                 0x67 => prefix.size |= OverrideSize::AD,
+                0xf0 => prefix.lock = true,
                 0xf2 => prefix.repeat = Some(Rep::REPNZ),
                 0xf3 => prefix.repeat = Some(Rep::REPZ),
                 _ => break,
@@ -140,6 +190,53 @@ impl ParseInstr {
         Ok(())
     }
 
+    fn get_mandatory_prefix(&mut self) -> () {
+        let prefix = &mut self.prefix;
+        if prefix.vex.is_some() { return; }
+
+        prefix.mandatory = match prefix.repeat.take() {
+            Some(Rep::REPZ)  => Some(Mandatory::PF3),
+            Some(Rep::REPNZ) => Some(Mandatory::PF2),
+            None if prefix.size.contains(OverrideSize::OP) => {
+                prefix.size.remove(OverrideSize::OP);
+                Some(Mandatory::P66)
+            },
+            None => None,
+        };
+    }
+
+    fn get_vex_prefix(&mut self, ac: &mut access::Access, mode64: bool) -> Result<(), EmuException> {
+        let code = ac.get_code8(self.instr.len)?;
+        if code != 0xc4 && code != 0xc5 { return Ok(()); }
+
+        let b1 = ac.get_code8(self.instr.len + 1)?;
+        if !mode64 && (b1 >> 6) != 3 { return Ok(()); }
+        if self.prefix.rex.is_some() || self.prefix.repeat.is_some() || self.prefix.lock || self.prefix.size.contains(OverrideSize::OP) {
+            return Err(EmuException::CPUException(CPUException::UD));
+        }
+
+        let (rxb, map, b2) = if code == 0xc5 {
+            ((b1 >> 5) & 4 | 3, 1, b1 & 0x7f)
+        } else {
+            (b1 >> 5, b1 & 0x1f, ac.get_code8(self.instr.len + 2)?)
+        };
+        let vex = Vex { map, vvvv: !(b2 >> 3) & 0xf, l: (b2 >> 2) & 1, w: b2 >> 7 };
+        debug!("{:?} ", vex);
+
+        self.prefix.mandatory = match b2 & 3 {
+            1 => Some(Mandatory::P66),
+            2 => Some(Mandatory::PF3),
+            3 => Some(Mandatory::PF2),
+            _ => None,
+        };
+        if mode64 {
+            self.prefix.rex = Some(Rex { b: !rxb & 1, x: !(rxb >> 1) & 1, r: !(rxb >> 2) & 1, w: vex.w });
+        }
+        self.prefix.vex = Some(vex);
+        self.instr.len += if code == 0xc5 { 2 } else { 3 };
+        Ok(())
+    }
+
     fn get_rex_prefix(&mut self, ac: &mut access::Access) -> Result<(), EmuException> {
         let code = ac.get_code8(self.instr.len)?;
         if (code >> 4) != 4 { return Ok(()); }
@@ -155,10 +252,26 @@ impl ParseInstr {
         let mut opcode = ac.get_code8(self.instr.len)? as u16;
         self.instr.len += 1;
 
-        if opcode == 0x0f {
+        if let Some(vex) = self.prefix.vex {
+            if vex.map < 1 || vex.map > 3 {
+                return Err(EmuException::CPUException(CPUException::UD));
+            }
+            opcode += (vex.map as u16) << 8;
+            debug!("opcode: vex.{:x} {:02x} ", vex.map, opcode&0xff);
+        } else if opcode == 0x0f {
             opcode = (1<<8) + ac.get_code8(self.instr.len)? as u16;
             self.instr.len += 1;
-            debug!("opcode: 0f{:02x} ", opcode&0xff);
+
+            match opcode {
+                0x138 | 0x13a => {
+                    let esc = opcode&0xff;
+                    let map = if esc == 0x38 { 2 } else { 3 };
+                    opcode = (map<<8) + ac.get_code8(self.instr.len)? as u16;
+                    self.instr.len += 1;
+                    debug!("opcode: 0f{:02x}{:02x} ", esc, opcode&0xff);
+                },
+                _ => debug!("opcode: 0f{:02x} ", opcode&0xff),
+            }
         } else {
             debug!("opcode: {:02x} ", opcode);
         }
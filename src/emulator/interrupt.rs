@@ -8,11 +8,26 @@ use crate::emulator::access::descriptor::*;
 pub(super) enum IntrEvent {
     Hardware(u8),
     Software(u8),
+    Exception(CPUException),
 }
 
 #[derive(Default)]
 pub(super) struct Interrupt(VecDeque<IntrEvent>);
 
+#[derive(PartialEq)]
+enum ExceptionClass { Benign, Contributory, PageFault, DoubleFault }
+
+impl From<&CPUException> for ExceptionClass {
+    fn from(e: &CPUException) -> Self {
+        match e {
+            CPUException::DE | CPUException::TS | CPUException::NP | CPUException::SS(_) | CPUException::GP(_) => ExceptionClass::Contributory,
+            CPUException::PF(..) => ExceptionClass::PageFault,
+            CPUException::DF => ExceptionClass::DoubleFault,
+            _ => ExceptionClass::Benign,
+        }
+    }
+}
+
 impl Interrupt {
     pub fn enqueue(&mut self, e: IntrEvent) -> () {
         self.0.push_back(e);
@@ -22,20 +37,41 @@ impl Interrupt {
         self.0.push_front(e);
     }
 
+    pub fn clear(&mut self) -> () {
+        self.0.clear();
+    }
+
     pub fn handle(&mut self, ac: &mut Access) -> Result<(), EmuException> {
-        if let Some(e) = self.0.pop_front(){
-            let (n, hw) = match e {
-                IntrEvent::Hardware(n) => (n, true),
-                IntrEvent::Software(n) => (n, false),
+        while let Some(e) = self.0.pop_front(){
+            let (n, hw, errcode, first) = match e {
+                IntrEvent::Hardware(n) => (n, true, None, None),
+                IntrEvent::Software(n) => (n, false, None, None),
+                IntrEvent::Exception(e) => {
+                    if let CPUException::PF(laddr, _) = e { ac.core.cregs.2.from_u64(laddr); }
+                    (u8::from(&e), true, e.error_code(), Some(ExceptionClass::from(&e)))
+                },
             };
 
-            interrupt_vector(ac, n, hw)?;
+            match interrupt_vector(ac, n, hw, errcode) {
+                Err(EmuException::CPUException(e)) => {
+                    debug!("CPUException while delivering 0x{:02x} : {:?}", n, e);
+                    let second = ExceptionClass::from(&e);
+                    match (first, second) {
+                        (Some(ExceptionClass::DoubleFault), _) => { return Err(EmuException::TripleFault(e)); },
+                        (Some(ExceptionClass::Contributory), ExceptionClass::Contributory) |
+                        (Some(ExceptionClass::PageFault), ExceptionClass::Contributory) |
+                        (Some(ExceptionClass::PageFault), ExceptionClass::PageFault) => self.enqueue_top(IntrEvent::Exception(CPUException::DF)),
+                        _ => self.enqueue_top(IntrEvent::Exception(e)),
+                    }
+                },
+                r => return r,
+            }
         }
         Ok(())
     }
 }
 
-fn interrupt_vector(ac: &mut Access, ivec: u8, hw: bool) -> Result<(), EmuException> {
+fn interrupt_vector(ac: &mut Access, ivec: u8, hw: bool, errcode: Option<u32>) -> Result<(), EmuException> {
     let idtr = &ac.core.dtregs.idtr;
 
     match ac.mode {
@@ -45,53 +81,273 @@ fn interrupt_vector(ac: &mut Access, ivec: u8, hw: bool) -> Result<(), EmuExcept
             if ivt_ofs > idtr.limit { return Err(EmuException::CPUException(CPUException::GP(None))); }
 
             let mut ivt: IVT = Default::default();
-            ac.read_l(&mut ivt as *mut IVT as *mut _, idtr.base + ivt_ofs as u64, std::mem::size_of_val(&ivt))?;
+            ac.read_l_sys(&mut ivt as *mut IVT as *mut _, idtr.base + ivt_ofs as u64, std::mem::size_of_val(&ivt))?;
 
             ac.save_regs(AcsSize::BIT16, None)?;
             ac.load_segment(SgReg::CS, ivt.segment)?;
             ac.set_ip(ivt.offset as u64)?;
         },
-        CpuMode::Protected | CpuMode::Long => {
+        CpuMode::Protected | CpuMode::V8086 | CpuMode::Long => {
             let cpl = ac.get_cpl()?;
             match ac.obtain_i_desc(ivec)? {
                 Some(DescType::System(SysDescType::Intr(gate))) => {
-                    let (new_ip, dpl) = (((gate.offset_h as u32) << 16) + gate.offset_l as u32, gate.DPL);
-                    let gatesize = if gate.D == 0 { AcsSize::BIT16 } else if ac.mode == CpuMode::Long { AcsSize::BIT64 } else { AcsSize::BIT32 };
-
-                    let (sel, desc) = ac.select_intrtrapgate(gate)?;
-                    let rpl = (sel & 3) as u8;
-                    if (cpl < rpl) || (!hw && cpl > dpl) { return Err(EmuException::CPUException(CPUException::GP(None))); }
-
-                    let cache = ac.select_segdesc(SgReg::CS, rpl, Some(SegDescType::Code(desc)))?;
-
-                    ac.save_regs(gatesize, if rpl < cpl { Some(rpl) } else { None })?;
+                    enter_intrtrap_gate(ac, gate, hw, errcode)?;
                     ac.core.rflags.set_interrupt(false);
-                    ac.set_sgreg(SgReg::CS, sel, cache)?;
-                    ac.set_ip(new_ip as u64)?;
                 },
                 Some(DescType::System(SysDescType::Trap(gate))) => {
-                    let (new_ip, dpl) = (((gate.offset_h as u32) << 16) + gate.offset_l as u32, gate.DPL);
-                    let gatesize = if gate.D == 0 { AcsSize::BIT16 } else if ac.mode == CpuMode::Long { AcsSize::BIT64 } else { AcsSize::BIT32 };
-
-                    let (sel, desc) = ac.select_intrtrapgate(gate)?;
-                    let rpl = (sel & 3) as u8;
-                    if (cpl < rpl) || (!hw && cpl > dpl) { return Err(EmuException::CPUException(CPUException::GP(None))); }
-
-                    let cache = ac.select_segdesc(SgReg::CS, rpl, Some(SegDescType::Code(desc)))?;
-
-                    ac.save_regs(gatesize, if rpl < cpl { Some(rpl) } else { None })?;
-                    ac.set_sgreg(SgReg::CS, sel, cache)?;
-                    ac.set_ip(new_ip as u64)?;
+                    enter_intrtrap_gate(ac, gate, hw, errcode)?;
                 },
                 Some(DescType::System(SysDescType::Task(gate))) => {
                     if gate.DPL < cpl { return Err(EmuException::CPUException(CPUException::GP(None))); }
                     let tss_sel = gate.tss_sel;
                     let desc = ac.select_taskgate(gate)?;
+                    let gatesize = if desc.D == 0 { AcsSize::BIT16 } else { AcsSize::BIT32 };
                     ac.switch_task(TSMode::CallInt, tss_sel, desc)?;
+                    push_errcode(ac, gatesize, errcode)?;
                 },
                 _ => { return Err(EmuException::CPUException(CPUException::GP(None))); },
             }
         },
     }
+    ac.core.rflags.set_resume(false);
+    ac.update_opadsize()?;
+    ac.update_stacksize()
+}
+
+fn enter_intrtrap_gate(ac: &mut Access, gate: IntrTrapGateDesc, hw: bool, errcode: Option<u32>) -> Result<(), EmuException> {
+    let cpl = ac.get_cpl()?;
+    let (new_ip, dpl, ist) = (gate.offset(), gate.DPL, gate.IST);
+    let gatesize = match (gate.D, &ac.mode) {
+        (0, CpuMode::Long) => { return Err(EmuException::CPUException(CPUException::GP(None))); },
+        (0, _) => AcsSize::BIT16,
+        (_, CpuMode::Long) => AcsSize::BIT64,
+        _ => AcsSize::BIT32,
+    };
+
+    let (sel, desc) = ac.select_intrtrapgate(gate)?;
+    let rpl = (sel & 3) as u8;
+    if (cpl < rpl) || (!hw && cpl > dpl) { return Err(EmuException::CPUException(CPUException::GP(None))); }
+    if ac.mode == CpuMode::V8086 && rpl != 0 { return Err(EmuException::CPUException(CPUException::GP(Some(sel)))); }
+    if ac.mode == CpuMode::Long && (desc.L == 0 || desc.DB == 1) { return Err(EmuException::CPUException(CPUException::GP(Some(sel)))); }
+
+    let cache = ac.select_segdesc(SgReg::CS, rpl, Some(SegDescType::Code(desc)))?;
+
+    let new_pl = if rpl < cpl { Some(rpl) } else { None };
+    let sys = ac.set_supervisor(new_pl.is_some());
+    let saved = match gatesize {
+        AcsSize::BIT64 => ac.save_regs64(new_pl, ist),
+        _ => ac.save_regs(gatesize, new_pl),
+    }.and_then(|_| push_errcode(ac, gatesize, errcode));
+    ac.set_supervisor(sys);
+    saved?;
+    ac.core.rflags.set_trap(false);
+    ac.core.rflags.set_nesttask(false);
+    ac.set_sgreg(SgReg::CS, sel, cache)?;
+    ac.update_opadsize()?;
+    ac.set_ip(new_ip)
+}
+
+fn push_errcode(ac: &mut Access, size: AcsSize, errcode: Option<u32>) -> Result<(), EmuException> {
+    if let Some(code) = errcode {
+        match size {
+            AcsSize::BIT16 => ac.push_u16(code as u16)?,
+            AcsSize::BIT32 => ac.push_u32(code)?,
+            AcsSize::BIT64 => ac.push_u64(code as u64)?,
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+#[test]
+fn exception_errcode_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x4000);
+    let (dev, _) = device::Device::new();
+    let mut ac = Access::new(hw, dev);
+    {
+        let mut mem = ac.mem.write().unwrap();
+        mem.write64(0x108, 0x00cf9a000000ffff);
+        mem.write64(0x110, 0x00cf92000000ffff);
+        mem.write64(0x200 + 13*8, 0x00008e00_00081234);
+    }
+
+    ac.core.cregs.0.PE = 1;
+    ac.update_cpumode().unwrap();
+    ac.set_gdtr(0x100, 0x17).unwrap();
+    ac.set_idtr(0x200, 0xff).unwrap();
+    ac.load_segment(SgReg::CS, 0x08).unwrap();
+    ac.load_segment(SgReg::SS, 0x10).unwrap();
+    ac.update_opadsize().unwrap();
+    ac.update_stacksize().unwrap();
+    ac.set_gpreg(GpReg32::ESP, 0x3000).unwrap();
+    ac.set_ip(0x500).unwrap();
+
+    let mut intrpt: Interrupt = Default::default();
+    intrpt.enqueue(IntrEvent::Exception(CPUException::GP(Some(0x28))));
+    intrpt.handle(&mut ac).unwrap();
+
+    assert_eq!(ac.get_ip().unwrap(), 0x1234);
+    assert_eq!(ac.pop_u32().unwrap(), 0x28);
+    assert_eq!(ac.pop_u32().unwrap(), 0x500);
+    assert_eq!(ac.pop_u32().unwrap(), 0x08);
+
+    ac.set_gpreg(GpReg32::ESP, 0x3000).unwrap();
+    intrpt.enqueue(IntrEvent::Exception(CPUException::PF(0xdeadb000, 0x6)));
+    ac.mem.write().unwrap().write64(0x200 + 14*8, 0x00008e00_00085678);
+    intrpt.handle(&mut ac).unwrap();
+
+    assert_eq!(ac.get_ip().unwrap(), 0x5678);
+    assert_eq!(ac.core.cregs.2.to_u64(), 0xdeadb000);
+    assert_eq!(ac.pop_u32().unwrap(), 0x6);
+}
+
+#[cfg(test)]
+#[test]
+fn double_fault_test() {
+    use crate::hardware;
+    use crate::device;
+
+    let hw = hardware::Hardware::new(0x4000);
+    let (dev, _) = device::Device::new();
+    let mut ac = Access::new(hw, dev);
+    {
+        let mut mem = ac.mem.write().unwrap();
+        mem.write64(0x108, 0x00cf9a000000ffff);
+        mem.write64(0x110, 0x00cf92000000ffff);
+        mem.write64(0x200 + 8*8, 0x00008e00_00088888);
+    }
+
+    ac.core.cregs.0.PE = 1;
+    ac.update_cpumode().unwrap();
+    ac.set_gdtr(0x100, 0x17).unwrap();
+    ac.set_idtr(0x200, 0x4f).unwrap();
+    ac.load_segment(SgReg::CS, 0x08).unwrap();
+    ac.load_segment(SgReg::SS, 0x10).unwrap();
+    ac.update_opadsize().unwrap();
+    ac.update_stacksize().unwrap();
+    ac.set_gpreg(GpReg32::ESP, 0x3000).unwrap();
+
+    let mut intrpt: Interrupt = Default::default();
+    intrpt.enqueue(IntrEvent::Exception(CPUException::GP(None)));
+    intrpt.handle(&mut ac).unwrap();
+    assert_eq!(ac.get_ip().unwrap(), 0x8888);
+    assert_eq!(ac.pop_u32().unwrap(), 0);
+
+    ac.set_idtr(0x200, 0x7).unwrap();
+    intrpt.enqueue(IntrEvent::Exception(CPUException::NP));
+    match intrpt.handle(&mut ac) {
+        Err(EmuException::TripleFault(CPUException::GP(_))) => {},
+        r => panic!("{:?}", r),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn long_mode_interrupt_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::hardware::processor::segment::SgDescCache;
+
+    let hw = hardware::Hardware::new(0x4000);
+    let (dev, _) = device::Device::new();
+    let mut ac = Access::new(hw, dev);
+    {
+        let mut mem = ac.mem.write().unwrap();
+        mem.write64(0x108, 0x00af9a000000ffff);
+        mem.write64(0x200 + 0x20*16, 0x0000ee00_00081234);
+        mem.write64(0x208 + 0x20*16, 0x00000001);
+        mem.write64(0x200 + 0x21*16, 0x00008e02_00085678);
+        mem.write32(0x600 + 0x04, 0x3008);
+        mem.write32(0x600 + 0x2c, 0x2800);
+    }
+
+    ac.mode = CpuMode::Long;
+    ac.core.msr.efer.LMA = 1;
+    ac.set_gdtr(0x100, 0x2f).unwrap();
+    ac.set_idtr(0x200, 0x21f).unwrap();
+    ac.core.dtregs.tr.cache.base = 0x600;
+    ac.core.dtregs.tr.cache.limit = 0x67;
+    ac.set_sgreg(SgReg::CS, 0x1b, SgDescCache { Type: 0xb, DPL: 3, P: 1, L: 1, ..Default::default() }).unwrap();
+    ac.set_sgreg(SgReg::SS, 0x23, SgDescCache { Type: 0x3, DPL: 3, P: 1, DB: 1, ..Default::default() }).unwrap();
+    ac.update_opadsize().unwrap();
+    ac.update_stacksize().unwrap();
+    ac.set_gpreg(GpReg64::RSP, 0x1ff8).unwrap();
+    ac.set_ip(0x401000).unwrap();
+    ac.core.rflags.set_interrupt(true);
+
+    let mut intrpt: Interrupt = Default::default();
+    intrpt.enqueue(IntrEvent::Software(0x20));
+    intrpt.handle(&mut ac).unwrap();
+
+    assert_eq!(ac.get_ip().unwrap(), 0x1_0000_1234);
+    assert_eq!(ac.get_cpl().unwrap(), 0);
+    assert_eq!(ac.get_sgreg(SgReg::SS).unwrap().0, 0);
+    assert!(!ac.core.rflags.is_interrupt());
+    assert_eq!(ac.pop_u64().unwrap(), 0x401000);
+    assert_eq!(ac.pop_u64().unwrap(), 0x1b);
+    assert_eq!(ac.pop_u64().unwrap() & 0x200, 0x200);
+    assert_eq!(ac.pop_u64().unwrap(), 0x1ff8);
+    assert_eq!(ac.pop_u64().unwrap(), 0x23);
+    assert_eq!(ac.get_gpreg(GpReg64::RSP).unwrap(), 0x3000);
+
+    intrpt.enqueue(IntrEvent::Hardware(0x21));
+    intrpt.handle(&mut ac).unwrap();
+    assert_eq!(ac.get_ip().unwrap(), 0x5678);
+    assert_eq!(ac.get_gpreg(GpReg64::RSP).unwrap(), 0x2800 - 40);
+    assert_eq!(ac.pop_u64().unwrap(), 0x1_0000_1234);
+}
+
+#[cfg(test)]
+#[test]
+fn paged_interrupt_test() {
+    use crate::hardware;
+    use crate::device;
+    use crate::hardware::processor::segment::SgDescCache;
+
+    let hw = hardware::Hardware::new(0x8000);
+    let (dev, _) = device::Device::new();
+    let mut ac = Access::new(hw, dev);
+    {
+        let mut mem = ac.mem.write().unwrap();
+        mem.write64(0x4000, 0x5007);
+        mem.write64(0x5000, 0x6007);
+        mem.write64(0x6000, 0x83);
+        mem.write64(0x108, 0x00af9a000000ffff);
+        mem.write64(0x200 + 0x20*16, 0x0000ee00_00081234);
+        mem.write32(0x600 + 0x04, 0x3000);
+    }
+
+    ac.core.cregs.0.PE = 1;
+    ac.core.cregs.0.PG = 1;
+    ac.core.cregs.4.PAE = 1;
+    ac.core.cregs.3.from_u64(0x4000);
+    ac.core.msr.efer.LME = 1;
+    ac.core.msr.efer.LMA = 1;
+    ac.update_cpumode().unwrap();
+    ac.update_pgmode().unwrap();
+    ac.set_gdtr(0x100, 0x2f).unwrap();
+    ac.set_idtr(0x200, 0x21f).unwrap();
+    ac.core.dtregs.tr.cache.base = 0x600;
+    ac.core.dtregs.tr.cache.limit = 0x67;
+    ac.set_sgreg(SgReg::CS, 0x1b, SgDescCache { Type: 0xb, DPL: 3, P: 1, L: 1, ..Default::default() }).unwrap();
+    ac.set_sgreg(SgReg::SS, 0x23, SgDescCache { Type: 0x3, DPL: 3, P: 1, DB: 1, ..Default::default() }).unwrap();
+    ac.update_opadsize().unwrap();
+    ac.update_stacksize().unwrap();
+    ac.set_gpreg(GpReg64::RSP, 0x1ff8).unwrap();
+    ac.set_ip(0x401000).unwrap();
+
+    let mut intrpt: Interrupt = Default::default();
+    intrpt.enqueue(IntrEvent::Software(0x20));
+    intrpt.handle(&mut ac).unwrap();
+
+    assert_eq!(ac.get_ip().unwrap(), 0x1234);
+    assert_eq!(ac.get_cpl().unwrap(), 0);
+    assert_eq!(ac.pop_u64().unwrap(), 0x401000);
+    assert_eq!(ac.pop_u64().unwrap(), 0x1b);
+    ac.pop_u64().unwrap();
+    assert_eq!(ac.pop_u64().unwrap(), 0x1ff8);
+    assert_eq!(ac.pop_u64().unwrap(), 0x23);
+}
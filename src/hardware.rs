@@ -1,11 +1,12 @@
 pub(crate) mod processor;
 pub(crate) mod memory;
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 pub struct Hardware {
     pub core: processor::Processor,
     pub mem: Arc<RwLock<memory::Memory>>,
+    pub bus: Arc<Mutex<()>>,
 }
 
 impl Hardware {
@@ -13,6 +14,7 @@ impl Hardware {
         Self {
             core: processor::Processor::new(),
             mem: Arc::new(RwLock::new(memory::Memory::new(size))),
+            bus: Arc::new(Mutex::new(())),
         }
     }
 }
\ No newline at end of file
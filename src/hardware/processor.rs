@@ -3,8 +3,13 @@ pub mod general;
 pub mod rflags;
 pub mod segment;
 pub mod control;
+pub mod debug;
 pub mod descriptor;
 pub mod model_specific;
+pub mod cpuid;
+pub mod tsc;
+pub mod fpu;
+pub mod sse;
 
 use std::convert::TryFrom;
 use general::*;
@@ -15,9 +20,13 @@ pub struct Processor {
     pub gpregs: general::GpRegisters,
     pub rflags: rflags::RFlags,
     pub cregs:  control::CRegisters,
+    pub dregs:  debug::DRegisters,
     pub sgregs: segment::SgRegisters,
     pub dtregs: descriptor::DTRegisters, 
     pub msr: model_specific::ModelSpecific,
+    pub tsc: tsc::TimeStampCounter,
+    pub fpu: fpu::FPU,
+    pub sse: sse::SSE,
 }
 
 impl Processor {
@@ -27,9 +36,13 @@ impl Processor {
             gpregs: general::GpRegisters::new(),
             rflags: Default::default(),
             cregs:  Default::default(),
+            dregs:  Default::default(),
             sgregs: segment::SgRegisters::new(),
             dtregs: Default::default(),
             msr: Default::default(),
+            tsc: Default::default(),
+            fpu: Default::default(),
+            sse: Default::default(),
         };
 
         let cs = prc.sgregs.get_mut(SgReg::CS);
@@ -35,12 +35,12 @@ pub trait CRAccess {
 #[packed_struct(bit_numbering="lsb0", size_bytes="4")]
 pub struct CR0 {
     #[packed_field(bits="0")]  pub PE: u8,
-    #[packed_field(bits="1")]  MP: u8,
-    #[packed_field(bits="2")]  EM: u8,
+    #[packed_field(bits="1")]  pub MP: u8,
+    #[packed_field(bits="2")]  pub EM: u8,
     #[packed_field(bits="3")]  pub TS: u8,
     #[packed_field(bits="4")]  ET: u8,
-    #[packed_field(bits="5")]  NE: u8,
-    #[packed_field(bits="16")] WP: u8,
+    #[packed_field(bits="5")]  pub NE: u8,
+    #[packed_field(bits="16")] pub WP: u8,
     #[packed_field(bits="18")] AM: u8,
     #[packed_field(bits="29")] NW: u8,
     #[packed_field(bits="30")] CD: u8,
@@ -63,8 +63,10 @@ impl CRAccess for CR2 {
 #[derive(Debug, Default, PackedStruct)]
 #[packed_struct(bit_numbering="lsb0", size_bytes="8", endian="msb")]
 pub struct CR3 {
+    #[packed_field(bits="0:2")]  PCID_l: u8,
     #[packed_field(bits="3")]  PWT: u8,
     #[packed_field(bits="4")]  PCD: u8,
+    #[packed_field(bits="5:11")]  PCID_h: u8,
     #[packed_field(bits="12:39")]  PageDirBase: u32,
 }
 impl CRAccess for CR3 {
@@ -78,31 +80,35 @@ impl CR3 {
     pub fn get_pagedir_base(&self) -> u64 {
         (self.PageDirBase as u64) << 12
     }
+
+    pub fn get_pcid(&self) -> u16 {
+        ((self.PCID_h as u16) << 5) + ((self.PCD as u16) << 4) + ((self.PWT as u16) << 3) + self.PCID_l as u16
+    }
 }
 
 #[derive(Debug, Default, PackedStruct)]
 #[packed_struct(bit_numbering="lsb0", size_bytes="4")]
 pub struct CR4 {
-    #[packed_field(bits="0")]  VME: u8,
-    #[packed_field(bits="1")]  PVI: u8,
-    #[packed_field(bits="2")]  TSD: u8,
-    #[packed_field(bits="3")]  DE:  u8,
+    #[packed_field(bits="0")]  pub VME: u8,
+    #[packed_field(bits="1")]  pub PVI: u8,
+    #[packed_field(bits="2")]  pub TSD: u8,
+    #[packed_field(bits="3")]  pub DE:  u8,
     #[packed_field(bits="4")]  pub PSE: u8,
     #[packed_field(bits="5")]  pub PAE: u8,
     #[packed_field(bits="6")]  MCE: u8,
-    #[packed_field(bits="7")]  PGE: u8,
+    #[packed_field(bits="7")]  pub PGE: u8,
     #[packed_field(bits="8")]  PCE: u8,
-    #[packed_field(bits="9")]  OSFXSR: u8,
-    #[packed_field(bits="10")] OSXMMEXCPT: u8,
-    #[packed_field(bits="11")] UMIP: u8,
+    #[packed_field(bits="9")]  pub OSFXSR: u8,
+    #[packed_field(bits="10")] pub OSXMMEXCPT: u8,
+    #[packed_field(bits="11")] pub UMIP: u8,
     #[packed_field(bits="12")] pub LA57: u8,
     #[packed_field(bits="13")] VMXE: u8,
     #[packed_field(bits="14")] SMXE: u8,
     #[packed_field(bits="16")] FSGSBASE: u8,
-    #[packed_field(bits="17")] PCIDE: u8,
-    #[packed_field(bits="18")] OSXSAVE: u8,
-    #[packed_field(bits="20")] SMEP: u8,
-    #[packed_field(bits="21")] SMAP: u8,
+    #[packed_field(bits="17")] pub PCIDE: u8,
+    #[packed_field(bits="18")] pub OSXSAVE: u8,
+    #[packed_field(bits="20")] pub SMEP: u8,
+    #[packed_field(bits="21")] pub SMAP: u8,
     #[packed_field(bits="22")] PKE: u8,
 }
 impl CRAccess for CR4 {
@@ -124,4 +130,8 @@ fn cr_test() {
     cr.3.PWT = 1;
     cr.3.PageDirBase = 0xdead;
     assert_eq!(cr.3.to_u32(), 0xdead008);
+
+    cr.3.from_u64(0xbeef123);
+    assert_eq!(cr.3.get_pcid(), 0x123);
+    assert_eq!(cr.3.get_pagedir_base(), 0xbeef000);
 }
\ No newline at end of file
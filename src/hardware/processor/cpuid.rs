@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::error;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CpuidLeaf {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+pub struct CpuidModel (BTreeMap<(u32, Option<u32>), CpuidLeaf>);
+
+bitflags! { pub struct Leaf01Ecx: u32 {
+    const CX16    = 1<<13;
+    const PCID    = 1<<17;
+    const POPCNT  = 1<<23;
+    const XSAVE   = 1<<26;
+    const OSXSAVE = 1<<27;
+    const AVX     = 1<<28;
+} }
+
+bitflags! { pub struct Leaf01Edx: u32 {
+    const FPU     = 1<<0;
+    const VME     = 1<<1;
+    const DE      = 1<<2;
+    const PSE     = 1<<3;
+    const TSC     = 1<<4;
+    const MSR     = 1<<5;
+    const PAE     = 1<<6;
+    const CX8     = 1<<8;
+    const SEP     = 1<<11;
+    const CMOV    = 1<<15;
+    const PGE     = 1<<13;
+    const CLFSH   = 1<<19;
+    const FXSR    = 1<<24;
+    const SSE     = 1<<25;
+    const SSE2    = 1<<26;
+} }
+
+bitflags! { pub struct Leaf07Ebx: u32 {
+    const BMI1    = 1<<3;
+    const AVX2    = 1<<5;
+    const SMEP    = 1<<7;
+    const INVPCID = 1<<10;
+    const SMAP    = 1<<20;
+} }
+
+bitflags! { pub struct Leaf07Ecx: u32 {
+    const UMIP    = 1<<2;
+    const LA57    = 1<<16;
+    const RDPID   = 1<<22;
+} }
+
+bitflags! { pub struct Ext01Ecx: u32 {
+    const LAHF    = 1<<0;
+    const LZCNT   = 1<<5;
+} }
+
+bitflags! { pub struct Ext01Edx: u32 {
+    const SYSCALL = 1<<11;
+    const NX      = 1<<20;
+    const PAGE1GB = 1<<26;
+    const RDTSCP  = 1<<27;
+    const LM      = 1<<29;
+} }
+
+const VENDOR: &[u8; 12] = b"GenuineIntel";
+const BRAND: &[u8; 48] = b"x64emu Virtual Processor\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+fn str_regs(s: &[u8]) -> Vec<u32> {
+    s.chunks(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+}
+
+impl Default for CpuidModel {
+    fn default() -> Self {
+        let mut model = CpuidModel(BTreeMap::new());
+
+        let v = str_regs(VENDOR);
+        model.set(0x0, None, CpuidLeaf { eax: 0xd, ebx: v[0], ecx: v[2], edx: v[1] });
+        model.set_signature(6, 0x3a, 9);
+        model.set(0x1, None, CpuidLeaf {
+            ebx: 8<<8,
+            ecx: (Leaf01Ecx::CX16 | Leaf01Ecx::PCID | Leaf01Ecx::POPCNT | Leaf01Ecx::XSAVE | Leaf01Ecx::AVX).bits,
+            edx: (Leaf01Edx::FPU | Leaf01Edx::VME | Leaf01Edx::DE | Leaf01Edx::PSE | Leaf01Edx::TSC | Leaf01Edx::MSR | Leaf01Edx::PAE | Leaf01Edx::CX8 | Leaf01Edx::SEP | Leaf01Edx::PGE |
+                  Leaf01Edx::CMOV | Leaf01Edx::CLFSH | Leaf01Edx::FXSR | Leaf01Edx::SSE | Leaf01Edx::SSE2).bits,
+            ..model.get(0x1, 0)
+        });
+        model.set(0x7, Some(0), CpuidLeaf {
+            ebx: (Leaf07Ebx::BMI1 | Leaf07Ebx::AVX2 | Leaf07Ebx::SMEP | Leaf07Ebx::INVPCID | Leaf07Ebx::SMAP).bits,
+            ecx: (Leaf07Ecx::UMIP | Leaf07Ecx::LA57 | Leaf07Ecx::RDPID).bits,
+            ..Default::default()
+        });
+        model.set(0xd, Some(0), CpuidLeaf { eax: 0x7, ebx: 576, ecx: 832, ..Default::default() });
+        model.set(0xd, Some(1), CpuidLeaf { eax: 0x1, ..Default::default() });
+        model.set(0xd, Some(2), CpuidLeaf { eax: 256, ebx: 576, ..Default::default() });
+
+        model.set(0x80000000, None, CpuidLeaf { eax: 0x80000008, ..Default::default() });
+        model.set(0x80000001, None, CpuidLeaf {
+            ecx: (Ext01Ecx::LAHF | Ext01Ecx::LZCNT).bits,
+            edx: (Ext01Edx::SYSCALL | Ext01Edx::NX | Ext01Edx::PAGE1GB | Ext01Edx::RDTSCP | Ext01Edx::LM).bits,
+            ..Default::default()
+        });
+        for (i, b) in str_regs(BRAND).chunks(4).enumerate() {
+            model.set(0x80000002 + i as u32, None, CpuidLeaf { eax: b[0], ebx: b[1], ecx: b[2], edx: b[3] });
+        }
+        model.set(0x80000008, None, CpuidLeaf { eax: 0x3028, ..Default::default() });
+
+        model
+    }
+}
+
+impl CpuidModel {
+    pub fn get(&self, leaf: u32, subleaf: u32) -> CpuidLeaf {
+        let tbl = &self.0;
+        *tbl.get(&(leaf, Some(subleaf))).or(tbl.get(&(leaf, None))).unwrap_or(&Default::default())
+    }
+
+    pub fn set(&mut self, leaf: u32, subleaf: Option<u32>, regs: CpuidLeaf) -> () {
+        self.0.insert((leaf, subleaf), regs);
+    }
+
+    pub fn set_signature(&mut self, family: u32, model: u32, stepping: u32) -> () {
+        let (ext_family, family) = if family > 0xf { (family - 0xf, 0xf) } else { (0, family) };
+        let eax = (ext_family << 20) + ((model >> 4) << 16) + (family << 8) + ((model & 0xf) << 4) + (stepping & 0xf);
+        let leaf = self.get(0x1, 0);
+        self.set(0x1, None, CpuidLeaf { eax, ..leaf });
+    }
+
+    pub fn load_override(&mut self, text: &str) -> Result<(), Box<dyn error::Error>> {
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() { continue; }
+
+            let tok: Vec<&str> = line.split_whitespace().collect();
+            if tok.len() != 6 {
+                return Err(format!("cpuid line {}: expected 'leaf subleaf eax ebx ecx edx'", n+1).into());
+            }
+
+            let parse = |s: &str| -> Result<u32, Box<dyn error::Error>> {
+                let v = if let Some(h) = s.strip_prefix("0x") { u32::from_str_radix(h, 16) } else { s.parse() };
+                v.map_err(|e| format!("cpuid line {}: '{}' {}", n+1, s, e).into())
+            };
+
+            let leaf = parse(tok[0])?;
+            let subleaf = if tok[1] == "*" { None } else { Some(parse(tok[1])?) };
+            let regs = CpuidLeaf { eax: parse(tok[2])?, ebx: parse(tok[3])?, ecx: parse(tok[4])?, edx: parse(tok[5])? };
+            self.set(leaf, subleaf, regs);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn cpuid_test() {
+    let mut model: CpuidModel = Default::default();
+
+    let l0 = model.get(0, 0);
+    assert_eq!(l0.ebx, 0x756e6547);
+    assert_eq!(l0.edx, 0x49656e69);
+    assert_eq!(l0.ecx, 0x6c65746e);
+    assert_eq!(model.get(0x1, 0).eax, 0x306a9);
+    assert_eq!(model.get(0x7, 1), Default::default());
+    assert!(Ext01Edx::from_bits_truncate(model.get(0x80000001, 0).edx).contains(Ext01Edx::LM));
+
+    model.load_override("# no la57\n0x7 0 0 0x80 0 0\n0x1 * 0x50654 0 0 0x48\n").unwrap();
+    assert_eq!(model.get(0x7, 0).ecx, 0);
+    assert_eq!(model.get(0x7, 0).ebx, 0x80);
+    assert_eq!(model.get(0x1, 3).edx, 0x48);
+    assert!(model.load_override("0x1 0 0").is_err());
+}
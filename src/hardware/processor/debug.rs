@@ -0,0 +1,96 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DRCond { Exec, Write, Io, ReadWrite }
+
+#[derive(Debug)]
+pub struct DRegisters {
+    pub dr: [u64; 4],
+    pub dr6: DR6,
+    pub dr7: DR7,
+}
+
+impl Default for DRegisters {
+    fn default() -> Self {
+        Self { dr: [0; 4], dr6: DR6(0xffff0ff0), dr7: DR7(0x400) }
+    }
+}
+
+impl DRegisters {
+    pub fn match_bp(&self, laddr: u64, size: u64, write: Option<bool>) -> u8 {
+        if !self.dr7.is_any_enabled() { return 0; }
+
+        (0..4).filter(|&i| self.dr7.is_enabled(i)).fold(0, |hits, i| {
+            let cond = matches!((self.dr7.cond(i), write),
+                (DRCond::Exec, None) | (DRCond::Write, Some(true)) | (DRCond::ReadWrite, Some(_)));
+            let len = if write.is_none() { 1 } else { self.dr7.len(i) };
+            let bp = self.dr[i] & !(len - 1);
+
+            if cond && laddr < bp + len && bp < laddr + size { hits | (1 << i) } else { hits }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DR6(u64);
+impl DR6 {
+    pub fn to_u64(&self) -> u64 { self.0 }
+    pub fn from_u64(&mut self, v: u64) -> () { self.0 = (v & 0xe00f) | 0xffff0ff0; }
+
+    pub fn set_hits(&mut self, hits: u8) -> () { self.0 = (self.0 & !0xf) | hits as u64; }
+    pub fn set_bd(&mut self, f: bool) -> () { self.0 = (self.0 & !(1<<13)) | (f as u64) << 13; }
+    pub fn set_bs(&mut self, f: bool) -> () { self.0 = (self.0 & !(1<<14)) | (f as u64) << 14; }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DR7(u64);
+impl DR7 {
+    pub fn to_u64(&self) -> u64 { self.0 }
+    pub fn from_u64(&mut self, v: u64) -> () { self.0 = (v & 0xffff23ff) | 0x400; }
+
+    pub fn is_any_enabled(&self) -> bool { self.0 & 0xff != 0 }
+    pub fn is_enabled(&self, n: usize) -> bool { (self.0 >> (n*2)) & 3 != 0 }
+    pub fn is_gd(&self) -> bool { self.0 & (1<<13) != 0 }
+    pub fn clear_gd(&mut self) -> () { self.0 &= !(1<<13); }
+
+    pub fn cond(&self, n: usize) -> DRCond {
+        match (self.0 >> (16 + n*4)) & 3 {
+            0 => DRCond::Exec,
+            1 => DRCond::Write,
+            2 => DRCond::Io,
+            _ => DRCond::ReadWrite,
+        }
+    }
+
+    pub fn len(&self, n: usize) -> u64 {
+        match (self.0 >> (18 + n*4)) & 3 {
+            0 => 1,
+            1 => 2,
+            2 => 8,
+            _ => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn dr_test() {
+    let mut dr: DRegisters = Default::default();
+    assert_eq!(dr.dr6.to_u64(), 0xffff0ff0);
+    assert_eq!(dr.match_bp(0x1000, 1, None), 0);
+
+    dr.dr[0] = 0x1000;
+    dr.dr[1] = 0x2003;
+    dr.dr[2] = 0x3000;
+    dr.dr7.from_u64(0x0f10002a);
+    assert_eq!(dr.dr7.cond(1), DRCond::Write);
+    assert_eq!(dr.dr7.len(2), 4);
+
+    assert_eq!(dr.match_bp(0x1000, 1, None), 0b001);
+    assert_eq!(dr.match_bp(0x2000, 4, Some(true)), 0b010);
+    assert_eq!(dr.match_bp(0x2000, 4, Some(false)), 0);
+    assert_eq!(dr.match_bp(0x2ffe, 4, Some(false)), 0b100);
+    assert_eq!(dr.match_bp(0x3004, 4, Some(true)), 0);
+
+    dr.dr6.set_hits(0b100);
+    dr.dr6.set_bs(true);
+    assert_eq!(dr.dr6.to_u64(), 0xffff4ff4);
+}
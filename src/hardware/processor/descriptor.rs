@@ -27,6 +27,6 @@ impl Default for DescTbl {
 #[derive(Debug, Default)]
 pub struct DescTblSel {
     pub selector:   u16, 
-    //pub attr:       u16, 
+    pub attr:       u16, 
     pub cache:      DescTbl, 
 }
\ No newline at end of file
@@ -0,0 +1,286 @@
+use packed_struct::prelude::*;
+
+#[derive(Debug, PackedStruct)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="2")]
+pub struct FPUControl {
+    #[packed_field(bits="0")]     IM: u8,
+    #[packed_field(bits="1")]     DM: u8,
+    #[packed_field(bits="2")]     ZM: u8,
+    #[packed_field(bits="3")]     OM: u8,
+    #[packed_field(bits="4")]     UM: u8,
+    #[packed_field(bits="5")]     PM: u8,
+    #[packed_field(bits="6")]    _r06: ReservedOnes<packed_bits::Bits1>,
+    #[packed_field(bits="8:9")]   pub PC: u8,
+    #[packed_field(bits="10:11")] pub RC: u8,
+    #[packed_field(bits="12")]    X:  u8,
+}
+impl Default for FPUControl {
+    fn default() -> Self { Self::unpack(&0x037fu16.to_be_bytes()).unwrap() }
+}
+
+impl FPUControl {
+    pub fn to_u16(&self) -> u16 { u16::from_be_bytes(self.pack().unwrap()) }
+    pub fn from_u16(&mut self, v: u16) -> () { *self = FPUControl::unpack(&(v | 0x40).to_be_bytes()).unwrap(); }
+}
+
+#[derive(Debug, Default, PackedStruct)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="2")]
+pub struct FPUStatus {
+    #[packed_field(bits="0")]     IE:  u8,
+    #[packed_field(bits="1")]     DE:  u8,
+    #[packed_field(bits="2")]     ZE:  u8,
+    #[packed_field(bits="3")]     OE:  u8,
+    #[packed_field(bits="4")]     UE:  u8,
+    #[packed_field(bits="5")]     PE:  u8,
+    #[packed_field(bits="6")]     SF:  u8,
+    #[packed_field(bits="7")]     ES:  u8,
+    #[packed_field(bits="8")]     pub C0: u8,
+    #[packed_field(bits="9")]     pub C1: u8,
+    #[packed_field(bits="10")]    pub C2: u8,
+    #[packed_field(bits="11:13")] TOP: u8,
+    #[packed_field(bits="14")]    pub C3: u8,
+    #[packed_field(bits="15")]    B:   u8,
+}
+
+impl FPUStatus {
+    pub fn to_u16(&self) -> u16 { u16::from_be_bytes(self.pack().unwrap()) }
+    pub fn from_u16(&mut self, v: u16) -> () { *self = FPUStatus::unpack(&v.to_be_bytes()).unwrap(); }
+
+    pub fn is_pending(&self) -> bool { self.ES != 0 }
+    pub fn set_cc(&mut self, c3: bool, c2: bool, c1: bool, c0: bool) -> () {
+        self.C3 = c3 as u8;
+        self.C2 = c2 as u8;
+        self.C1 = c1 as u8;
+        self.C0 = c0 as u8;
+    }
+}
+
+bitflags! { pub struct FPUException: u16 {
+    const IE = 0b00000001;
+    const DE = 0b00000010;
+    const ZE = 0b00000100;
+    const OE = 0b00001000;
+    const UE = 0b00010000;
+    const PE = 0b00100000;
+    const SF = 0b01000000;
+} }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FPUTag { Valid, Zero, Special, Empty }
+
+pub struct FPU {
+    pub control: FPUControl,
+    pub status: FPUStatus,
+    tags: [FPUTag; 8],
+    regs: [[u8; 10]; 8],
+    pub fip: u64,
+    pub fcs: u16,
+    pub fop: u16,
+    pub fdp: u64,
+    pub fds: u16,
+}
+
+impl Default for FPU {
+    fn default() -> Self {
+        Self {
+            control: Default::default(),
+            status: Default::default(),
+            tags: [FPUTag::Empty; 8],
+            regs: [[0; 10]; 8],
+            fip: 0, fcs: 0, fop: 0, fdp: 0, fds: 0,
+        }
+    }
+}
+
+impl FPU {
+    pub fn init(&mut self) -> () {
+        *self = Default::default();
+    }
+
+    fn phys(&self, i: usize) -> usize { (self.status.TOP as usize + i) & 7 }
+
+    pub fn get_st(&self, i: usize) -> Option<f64> {
+        self.get_st_raw(i).map(f80_to_f64)
+    }
+
+    pub fn set_st(&mut self, i: usize, v: f64) -> () {
+        self.set_st_raw(i, f64_to_f80(v));
+    }
+
+    pub fn get_st_raw(&self, i: usize) -> Option<[u8; 10]> {
+        let n = self.phys(i);
+        if self.tags[n] == FPUTag::Empty { None } else { Some(self.regs[n]) }
+    }
+
+    pub fn set_st_raw(&mut self, i: usize, raw: [u8; 10]) {
+        let n = self.phys(i);
+        self.regs[n] = raw;
+        self.tags[n] = classify(&raw);
+    }
+
+    pub fn free_st(&mut self, i: usize) -> () {
+        let n = self.phys(i);
+        self.tags[n] = FPUTag::Empty;
+    }
+
+    pub fn push(&mut self, v: f64) -> bool {
+        self.push_raw(f64_to_f80(v))
+    }
+
+    pub fn push_raw(&mut self, raw: [u8; 10]) -> bool {
+        self.dec_top();
+        let overflow = self.tags[self.phys(0)] != FPUTag::Empty;
+        self.set_st_raw(0, raw);
+        !overflow
+    }
+
+    pub fn pop(&mut self) -> () {
+        self.free_st(0);
+        self.inc_top();
+    }
+
+    pub fn inc_top(&mut self) -> () { self.status.TOP = (self.status.TOP + 1) & 7; }
+    pub fn dec_top(&mut self) -> () { self.status.TOP = (self.status.TOP + 7) & 7; }
+
+    pub fn raise(&mut self, ex: FPUException) -> bool {
+        let st = &mut self.status;
+        st.from_u16(st.to_u16() | ex.bits);
+        let unmasked = ex.bits & 0x3f & !self.control.to_u16() != 0;
+        if unmasked {
+            self.status.ES = 1;
+            self.status.B = 1;
+        }
+        unmasked
+    }
+
+    pub fn load_control(&mut self, v: u16) -> () {
+        self.control.from_u16(v);
+        let pending = self.status.to_u16() & 0x3f & !v != 0;
+        self.status.ES = pending as u8;
+        self.status.B = pending as u8;
+    }
+
+    pub fn clear_exceptions(&mut self) -> () {
+        let st = &mut self.status;
+        st.from_u16(st.to_u16() & 0x7f00);
+    }
+
+    pub fn tag_word(&self) -> u16 {
+        self.tags.iter().enumerate().fold(0, |tw, (i, t)| tw | ((*t as u16) << (i*2)))
+    }
+
+    pub fn set_tag_word(&mut self, tw: u16) -> () {
+        for i in 0..8 {
+            self.tags[i] = if (tw >> (i*2)) & 3 == 3 { FPUTag::Empty } else { classify(&self.regs[i]) };
+        }
+    }
+
+    pub fn get_raw(&self, i: usize) -> [u8; 10] { self.regs[self.phys(i)] }
+    pub fn set_raw(&mut self, i: usize, raw: [u8; 10]) -> () {
+        let n = self.phys(i);
+        self.regs[n] = raw;
+    }
+}
+
+fn classify(raw: &[u8; 10]) -> FPUTag {
+    let mant = u64::from_le_bytes([raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7]]);
+    let exp = u16::from_le_bytes([raw[8], raw[9]]) & 0x7fff;
+    match exp {
+        0 if mant == 0 => FPUTag::Zero,
+        0 | 0x7fff => FPUTag::Special,
+        _ if mant >> 63 == 0 => FPUTag::Special,
+        _ => FPUTag::Valid,
+    }
+}
+
+pub fn cmp_f80(raw: [u8; 10], v: f64) -> std::cmp::Ordering {
+    let key = |raw: [u8; 10]| {
+        let mant = u64::from_le_bytes([raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7]]);
+        let se = u16::from_le_bytes([raw[8], raw[9]]);
+        (se >> 15 != 0, se & 0x7fff, mant)
+    };
+    let ((sa, ea, ma), (sb, eb, mb)) = (key(raw), key(f64_to_f80(v)));
+
+    if ea == 0x7fff || eb == 0x7fff || (ma == 0 && mb == 0) {
+        return std::cmp::Ordering::Equal;
+    }
+    match (sa, sb) {
+        (false, true) => std::cmp::Ordering::Greater,
+        (true, false) => std::cmp::Ordering::Less,
+        (false, false) => (ea, ma).cmp(&(eb, mb)),
+        (true, true) => (eb, mb).cmp(&(ea, ma)),
+    }
+}
+
+pub fn ldexp(mut x: f64, mut e: i32) -> f64 {
+    while e > 1000 { x *= 2f64.powi(1000); e -= 1000; }
+    while e < -1000 { x *= 2f64.powi(-1000); e += 1000; }
+    x * 2f64.powi(e)
+}
+
+pub fn f80_to_f64(raw: [u8; 10]) -> f64 {
+    let mant = u64::from_le_bytes([raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7]]);
+    let se = u16::from_le_bytes([raw[8], raw[9]]);
+    let (sign, exp) = (se >> 15 != 0, (se & 0x7fff) as i32);
+
+    let v = if exp == 0x7fff {
+        if mant << 1 == 0 { f64::INFINITY } else { f64::from_bits(0x7ff8_0000_0000_0000 | ((mant << 1) >> 12)) }
+    } else {
+        ldexp(mant as f64, exp - 16383 - 63)
+    };
+    if sign { -v } else { v }
+}
+
+pub fn f64_to_f80(v: f64) -> [u8; 10] {
+    let bits = v.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let exp = ((bits >> 52) & 0x7ff) as u16;
+    let frac = bits & ((1 << 52) - 1);
+
+    let (exp, mant) = match exp {
+        0x7ff => (0x7fff, (1 << 63) | (frac << 11)),
+        0 if frac == 0 => (0, 0),
+        0 => {
+            let m = frac << 11;
+            let lz = m.leading_zeros() as u16;
+            (16383 - 1022 - lz, m << lz)
+        },
+        e => (e + 16383 - 1023, (1 << 63) | (frac << 11)),
+    };
+
+    let mut raw = [0; 10];
+    raw[..8].copy_from_slice(&mant.to_le_bytes());
+    raw[8..].copy_from_slice(&(sign | exp).to_le_bytes());
+    raw
+}
+
+#[cfg(test)]
+#[test]
+fn fpu_test() {
+    let mut fpu: FPU = Default::default();
+
+    assert_eq!(fpu.control.to_u16(), 0x037f);
+    assert_eq!(fpu.tag_word(), 0xffff);
+
+    assert!(fpu.push(1.5));
+    assert!(fpu.push(0.0));
+    assert_eq!(fpu.get_st(1), Some(1.5));
+    assert_eq!(fpu.status.to_u16() >> 11 & 7, 6);
+    assert_eq!(fpu.tag_word(), 0x1fff);
+    fpu.pop();
+    assert_eq!(fpu.get_st(0), Some(1.5));
+    assert_eq!(fpu.get_st(1), None);
+
+    assert!(!fpu.raise(FPUException::ZE));
+    fpu.control.from_u16(0x037b);
+    assert!(fpu.raise(FPUException::ZE));
+    assert!(fpu.status.is_pending());
+    fpu.clear_exceptions();
+    assert!(!fpu.status.is_pending());
+
+    for v in [1.0, -2.5, 1e300, 4.9e-324, f64::INFINITY, -0.0].iter() {
+        assert_eq!(f80_to_f64(f64_to_f80(*v)).to_bits(), v.to_bits());
+    }
+    assert_eq!(f64_to_f80(1.0), [0, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f]);
+    assert!(f80_to_f64(f64_to_f80(f64::NAN)).is_nan());
+}
@@ -8,6 +8,10 @@ pub struct ModelSpecific {
     pub lstar: LSTAR,
     pub cstar: CSTAR,
     pub fmask: FMASK,
+    pub sysenter_cs: IA32_SYSENTER_CS,
+    pub sysenter_esp: IA32_SYSENTER_ESP,
+    pub sysenter_eip: IA32_SYSENTER_EIP,
+    pub tsc_aux: IA32_TSC_AUX,
 }
 
 pub trait MSRAccess {
@@ -18,7 +22,7 @@ pub trait MSRAccess {
 #[derive(Default, PackedStruct)]
 #[packed_struct(bit_numbering="lsb0", size_bytes="8")]
 pub struct IA32_EFER {
-    #[packed_field(bits="0")]  SCE: u8,
+    #[packed_field(bits="0")]  pub SCE: u8,
     #[packed_field(bits="1:7")]   _r01: ReservedZero<packed_bits::Bits7>,
     #[packed_field(bits="8")]  pub LME: u8,
     #[packed_field(bits="9")]     _r09: ReservedZero<packed_bits::Bits1>,
@@ -86,6 +90,34 @@ impl MSRAccess for FMASK {
     fn from_u64(&mut self, v: u64) -> () { *self = Self::unpack(&v.to_be_bytes()).unwrap(); }
 }
 
+#[derive(Default)]
+pub struct IA32_SYSENTER_CS(u64);
+impl MSRAccess for IA32_SYSENTER_CS {
+    fn to_u64(&self) -> u64 { self.0 }
+    fn from_u64(&mut self, v: u64) -> () { self.0 = v; }
+}
+
+#[derive(Default)]
+pub struct IA32_SYSENTER_ESP(u64);
+impl MSRAccess for IA32_SYSENTER_ESP {
+    fn to_u64(&self) -> u64 { self.0 }
+    fn from_u64(&mut self, v: u64) -> () { self.0 = v; }
+}
+
+#[derive(Default)]
+pub struct IA32_SYSENTER_EIP(u64);
+impl MSRAccess for IA32_SYSENTER_EIP {
+    fn to_u64(&self) -> u64 { self.0 }
+    fn from_u64(&mut self, v: u64) -> () { self.0 = v; }
+}
+
+#[derive(Default)]
+pub struct IA32_TSC_AUX(u32);
+impl MSRAccess for IA32_TSC_AUX {
+    fn to_u64(&self) -> u64 { self.0 as u64 }
+    fn from_u64(&mut self, v: u64) -> () { self.0 = v as u32; }
+}
+
 #[cfg(test)]
 #[test]
 fn msr_test() {
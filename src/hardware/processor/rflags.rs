@@ -32,6 +32,7 @@ impl RFlags {
 
     pub fn is_carry(&self) -> bool { self.CF != 0 }
     pub fn is_parity(&self) -> bool { self.PF != 0 }
+    pub fn is_adjust(&self) -> bool { self.AF != 0 }
     pub fn is_zero(&self) -> bool { self.ZF != 0 }
     pub fn is_sign(&self) -> bool { self.SF != 0 }
     pub fn is_trap(&self) -> bool { self.TF != 0 }
@@ -39,10 +40,16 @@ impl RFlags {
     pub fn is_direction(&self) -> bool { self.DF != 0 }
     pub fn is_overflow(&self) -> bool { self.OF != 0 }
     pub fn is_nesttask(&self) -> bool { self.NT != 0 }
+    pub fn is_resume(&self) -> bool { self.RF != 0 }
+    pub fn is_v8086(&self) -> bool { self.VM != 0 }
+    pub fn is_aligncheck(&self) -> bool { self.AC != 0 }
+    pub fn is_vinterrupt(&self) -> bool { self.VIF != 0 }
+    pub fn is_vinterrupt_pending(&self) -> bool { self.VIP != 0 }
     pub fn get_iopl(&self) -> u8 { self.IOPL }
 
     pub fn set_carry(&mut self, f: bool) -> () { self.CF = f as u8; }
     pub fn set_parity(&mut self, f: bool) -> () { self.PF = f as u8; }
+    pub fn set_adjust(&mut self, f: bool) -> () { self.AF = f as u8; }
     pub fn set_zero(&mut self, f: bool) -> () { self.ZF = f as u8; }
     pub fn set_sign(&mut self, f: bool) -> () { self.SF = f as u8; }
     pub fn set_trap(&mut self, f: bool) -> () { self.TF = f as u8; }
@@ -50,6 +57,11 @@ impl RFlags {
     pub fn set_direction(&mut self, f: bool) -> () { self.DF = f as u8; }
     pub fn set_overflow(&mut self, f: bool) -> () { self.OF = f as u8; }
     pub fn set_nesttask(&mut self, f: bool) -> () { self.NT = f as u8; }
+    pub fn set_resume(&mut self, f: bool) -> () { self.RF = f as u8; }
+    pub fn set_v8086(&mut self, f: bool) -> () { self.VM = f as u8; }
+    pub fn set_aligncheck(&mut self, f: bool) -> () { self.AC = f as u8; }
+    pub fn set_vinterrupt(&mut self, f: bool) -> () { self.VIF = f as u8; }
+    pub fn set_vinterrupt_pending(&mut self, f: bool) -> () { self.VIP = f as u8; }
     pub fn set_iopl(&mut self, pl: u8) -> () { self.IOPL = pl; }
 }
 
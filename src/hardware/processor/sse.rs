@@ -0,0 +1,90 @@
+use packed_struct::prelude::*;
+use super::fpu::FPUException;
+
+pub const MXCSR_MASK: u32 = 0xffff;
+
+#[derive(Debug, PackedStruct)]
+#[packed_struct(bit_numbering="lsb0", size_bytes="4")]
+pub struct MXCSR {
+    #[packed_field(bits="0")]     IE:  u8,
+    #[packed_field(bits="1")]     DE:  u8,
+    #[packed_field(bits="2")]     ZE:  u8,
+    #[packed_field(bits="3")]     OE:  u8,
+    #[packed_field(bits="4")]     UE:  u8,
+    #[packed_field(bits="5")]     PE:  u8,
+    #[packed_field(bits="6")]     DAZ: u8,
+    #[packed_field(bits="7")]     IM:  u8,
+    #[packed_field(bits="8")]     DM:  u8,
+    #[packed_field(bits="9")]     ZM:  u8,
+    #[packed_field(bits="10")]    OM:  u8,
+    #[packed_field(bits="11")]    UM:  u8,
+    #[packed_field(bits="12")]    PM:  u8,
+    #[packed_field(bits="13:14")] pub RC: u8,
+    #[packed_field(bits="15")]    FZ:  u8,
+}
+impl Default for MXCSR {
+    fn default() -> Self { Self::unpack(&0x1f80u32.to_be_bytes()).unwrap() }
+}
+
+impl MXCSR {
+    pub fn to_u32(&self) -> u32 { u32::from_be_bytes(self.pack().unwrap()) }
+    pub fn from_u32(&mut self, v: u32) -> () { *self = MXCSR::unpack(&(v & MXCSR_MASK).to_be_bytes()).unwrap(); }
+}
+
+bitflags! { pub struct XCR0: u64 {
+    const X87 = 0b001;
+    const SSE = 0b010;
+    const AVX = 0b100;
+} }
+impl Default for XCR0 {
+    fn default() -> Self { XCR0::X87 }
+}
+
+#[derive(Default)]
+pub struct SSE {
+    xmm: [u128; 16],
+    ymmh: [u128; 16],
+    pub mxcsr: MXCSR,
+    pub xcr0: XCR0,
+}
+
+impl SSE {
+    pub fn init(&mut self) -> () {
+        *self = Default::default();
+    }
+
+    pub fn get_xmm(&self, n: usize) -> u128 { self.xmm[n] }
+    pub fn set_xmm(&mut self, n: usize, v: u128) -> () { self.xmm[n] = v; }
+    pub fn get_ymm(&self, n: usize) -> [u128; 2] { [self.xmm[n], self.ymmh[n]] }
+    pub fn set_ymm(&mut self, n: usize, v: [u128; 2]) -> () { self.xmm[n] = v[0]; self.ymmh[n] = v[1]; }
+
+    pub fn raise(&mut self, ex: FPUException) -> bool {
+        let v = self.mxcsr.to_u32() | (ex.bits() as u32 & 0x3f);
+        self.mxcsr.from_u32(v);
+        ex.bits() as u32 & 0x3f & !(v >> 7) != 0
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn sse_test() {
+    let mut sse: SSE = Default::default();
+
+    assert_eq!(sse.mxcsr.to_u32(), 0x1f80);
+    sse.set_xmm(15, 0x0123456789abcdef_fedcba9876543210);
+    assert_eq!(sse.get_xmm(15) >> 64, 0x0123456789abcdef);
+    sse.set_ymm(15, [1, 2]);
+    sse.set_xmm(15, 3);
+    assert_eq!(sse.get_ymm(15), [3, 2]);
+    assert_eq!(sse.xcr0, XCR0::X87);
+
+    assert!(!sse.raise(FPUException::ZE));
+    assert_eq!(sse.mxcsr.to_u32(), 0x1f84);
+    sse.mxcsr.from_u32(0x1d80);
+    assert!(sse.raise(FPUException::ZE));
+    assert!(!sse.raise(FPUException::PE));
+    assert_eq!(sse.mxcsr.to_u32(), 0x1da4);
+
+    sse.mxcsr.RC = 3;
+    assert_eq!(sse.mxcsr.to_u32(), 0x7da4);
+}
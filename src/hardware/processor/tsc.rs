@@ -0,0 +1,84 @@
+use std::time::Instant;
+use super::model_specific::MSRAccess;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TscMode { Deterministic(u64), HostScaled(u64) }
+
+impl std::str::FromStr for TscMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut it = s.splitn(2, ':');
+        let (kind, arg) = (it.next().unwrap(), it.next());
+        let arg = |default: u64| -> Result<u64, String> {
+            arg.map_or(Ok(default), |a| a.parse().map_err(|_| format!("invalid tsc rate '{}'", a)))
+        };
+
+        match kind {
+            "det"  => Ok(TscMode::Deterministic(arg(1)?)),
+            "host" => Ok(TscMode::HostScaled(arg(1000)?)),
+            _ => Err(format!("unknown tsc mode '{}'", s)),
+        }
+    }
+}
+
+pub struct TimeStampCounter {
+    pub mode: TscMode,
+    count: u64,
+    start: Instant,
+}
+
+impl Default for TimeStampCounter {
+    fn default() -> Self {
+        Self { mode: TscMode::Deterministic(1), count: 0, start: Instant::now() }
+    }
+}
+
+impl TimeStampCounter {
+    pub fn tick(&mut self) -> () {
+        if let TscMode::Deterministic(n) = self.mode {
+            self.count = self.count.wrapping_add(n);
+        }
+    }
+
+    fn host_ticks(&self, mhz: u64) -> u64 {
+        (self.start.elapsed().as_nanos() * mhz as u128 / 1000) as u64
+    }
+}
+
+impl MSRAccess for TimeStampCounter {
+    fn to_u64(&self) -> u64 {
+        match self.mode {
+            TscMode::Deterministic(_) => self.count,
+            TscMode::HostScaled(mhz)  => self.count.wrapping_add(self.host_ticks(mhz)),
+        }
+    }
+
+    fn from_u64(&mut self, v: u64) -> () {
+        self.count = v;
+        self.start = Instant::now();
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn tsc_test() {
+    let mut tsc: TimeStampCounter = Default::default();
+
+    tsc.mode = "det:3".parse().unwrap();
+    tsc.tick();
+    tsc.tick();
+    assert_eq!(tsc.to_u64(), 6);
+
+    tsc.from_u64(0x1000);
+    tsc.tick();
+    assert_eq!(tsc.to_u64(), 0x1003);
+
+    tsc.mode = "host".parse().unwrap();
+    assert_eq!(tsc.mode, TscMode::HostScaled(1000));
+    let t = tsc.to_u64();
+    assert!(tsc.to_u64() >= t && t >= 0x1003);
+
+    assert!("det:x".parse::<TscMode>().is_err());
+    assert!("wall".parse::<TscMode>().is_err());
+}
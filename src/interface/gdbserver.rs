@@ -6,6 +6,7 @@ use gdbstub::arch;
 use gdbstub::target;
 use gdbstub::arch::x86::reg::id::X86CoreRegId;
 use gdbstub::target::ext::base::singlethread::{ResumeAction, SingleThreadOps, StopReason};
+use gdbstub::target::ext::breakpoints::WatchKind;
 use gdbstub::target::{Target, TargetResult, TargetError};
 use std::net::{TcpListener, TcpStream};
 
@@ -32,11 +33,15 @@ impl Target for emulator::Emulator {
     fn sw_breakpoint(&mut self) -> Option<target::ext::breakpoints::SwBreakpointOps<Self>> {
         Some(self)
     }
+
+    fn hw_watchpoint(&mut self) -> Option<target::ext::breakpoints::HwWatchpointOps<Self>> {
+        Some(self)
+    }
 }
 
 impl SingleThreadOps for emulator::Emulator {
     fn resume(&mut self, action: ResumeAction, check_gdb_interrupt: &mut dyn FnMut() -> bool,) -> Result<StopReason<u32>, Self::Error> {
-        match action {
+        let event = match action {
             ResumeAction::Step => match self.step(true) {
                 Some(e) => e,
                 None => return Ok(StopReason::DoneStep),
@@ -56,7 +61,13 @@ impl SingleThreadOps for emulator::Emulator {
                 }
             }
         };
-        Ok(StopReason::DoneStep)
+
+        match event {
+            emulator::Event::Shutdown(_) => Ok(StopReason::Halted),
+            emulator::Event::WatchWrite(addr) => Ok(StopReason::Watch { kind: self.stop_watch_kind(addr, WatchKind::Write), addr }),
+            emulator::Event::WatchRead(addr) => Ok(StopReason::Watch { kind: self.stop_watch_kind(addr, WatchKind::Read), addr }),
+            _ => Ok(StopReason::DoneStep),
+        }
     }
 
     fn read_registers(&mut self, regs: &mut arch::x86::reg::X86CoreRegs) -> TargetResult<(), Self> {
@@ -79,6 +90,11 @@ impl SingleThreadOps for emulator::Emulator {
         regs.segments[4] = core.sgregs.get(SgReg::FS).selector.to_u16() as u32;
         regs.segments[5] = core.sgregs.get(SgReg::GS).selector.to_u16() as u32;
 
+        for (i, xmm) in regs.xmm.iter_mut().enumerate() {
+            *xmm = core.sse.get_xmm(i);
+        }
+        regs.mxcsr = core.sse.mxcsr.to_u32();
+
         Ok(())
     }
 
@@ -103,6 +119,11 @@ impl SingleThreadOps for emulator::Emulator {
         core.sgregs.get_mut(SgReg::FS).selector.from_u16(regs.segments[4] as u16);
         core.sgregs.get_mut(SgReg::GS).selector.from_u16(regs.segments[5] as u16);
 
+        for (i, xmm) in regs.xmm.iter().enumerate() {
+            core.sse.set_xmm(i, *xmm);
+        }
+        core.sse.mxcsr.from_u32(regs.mxcsr);
+
         Ok(())
     }
 
@@ -183,4 +204,35 @@ impl target::ext::breakpoints::SwBreakpoint for emulator::Emulator {
 
         Ok(true)
     }
-}
\ No newline at end of file
+}
+impl target::ext::breakpoints::HwWatchpoint for emulator::Emulator {
+    fn add_hw_watchpoint(&mut self, addr: u32, kind: WatchKind) -> TargetResult<bool, Self> {
+        self.ac.watchpoints.push((addr as u64, emulator_watch_kind(kind)));
+        Ok(true)
+    }
+
+    fn remove_hw_watchpoint(&mut self, addr: u32, kind: WatchKind) -> TargetResult<bool, Self> {
+        let wp = (addr as u64, emulator_watch_kind(kind));
+        match self.ac.watchpoints.iter().position(|x| *x == wp) {
+            None => return Ok(false),
+            Some(pos) => self.ac.watchpoints.remove(pos),
+        };
+
+        Ok(true)
+    }
+}
+
+impl emulator::Emulator {
+    fn stop_watch_kind(&self, addr: u32, kind: WatchKind) -> WatchKind {
+        let access = self.ac.watchpoints.iter().any(|(a, k)| *a == addr as u64 && *k == emulator::WatchKind::ReadWrite);
+        if access { WatchKind::ReadWrite } else { kind }
+    }
+}
+
+fn emulator_watch_kind(kind: WatchKind) -> emulator::WatchKind {
+    match kind {
+        WatchKind::Write     => emulator::WatchKind::Write,
+        WatchKind::Read      => emulator::WatchKind::Read,
+        WatchKind::ReadWrite => emulator::WatchKind::ReadWrite,
+    }
+}
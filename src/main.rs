@@ -11,6 +11,9 @@ use gdbstub::{Connection, GdbStub};
 struct Args {
     input: Vec<String>,
     gdbport: Option<u16>,
+    triple_fault: emulator::TripleFaultAction,
+    cpuid: Option<String>,
+    tsc: emulator::TscMode,
 }
 
 fn main() {
@@ -22,9 +25,14 @@ fn main() {
     let gui = interface::gui::GUI::new(320, 200);
 
     let (mut dev, chan_dev)  = device::Device::new();
-    dev.init_devices(chan_dev, hw.mem.clone(), gui.buffer.clone());
+    dev.init_devices(chan_dev, (hw.mem.clone(), hw.bus.clone()), gui.buffer.clone());
 
     let mut emu = emulator::Emulator::new(hw, dev);
+    emu.triple_fault = args.triple_fault;
+    emu.set_tsc_mode(args.tsc);
+    if let Some(path) = &args.cpuid {
+        emu.load_cpuid(path.clone()).expect("Failed to load cpuid table");
+    }
 
     emu.map_binary(0xffff0, include_bytes!("bios/crt0.bin")).expect("Failed to map");
     emu.map_binary(0xf0000, include_bytes!("bios/bios.bin")).expect("Failed to map");
@@ -39,7 +47,9 @@ fn main() {
 
             debugger.run(&mut emu).expect("debugger error");
         } else {
-            emu.run();
+            let ev = emu.run();
+            eprintln!("Emulator stopped : {:?}", ev);
+            process::exit(1);
         }
     });
     gui.persistent();
@@ -57,6 +67,9 @@ fn parse_args() -> Args {
 
     let mut opts = Options::new();
     opts.optopt("s", "gdb", "set gdb tcp port", "1234");
+    opts.optopt("t", "triple-fault", "action on triple fault (reset|stop)", "reset");
+    opts.optopt("c", "cpuid", "override cpuid table (lines of 'leaf subleaf|* eax ebx ecx edx')", "FILE");
+    opts.optopt("", "tsc", "tsc mode (det[:ticks per instruction]|host[:MHz])", "det:1");
     opts.optflag("h", "help", "print this help menu");
 
     let matches = opts.parse(&args[1..])
@@ -75,5 +88,8 @@ fn parse_args() -> Args {
     Args {
         input: matches.free.clone(),
         gdbport: matches.opt_get("s").unwrap(),
+        triple_fault: matches.opt_get_default("t", emulator::TripleFaultAction::Reset).unwrap(),
+        cpuid: matches.opt_str("c"),
+        tsc: matches.opt_get_default("tsc", emulator::TscMode::Deterministic(1)).unwrap(),
     }
 }
\ No newline at end of file